@@ -4,9 +4,16 @@ use htc::{
     models::{
         Entity,
         admins::Admin,
+        audit::{AuditEntry, AuditModelError, AuditQuerySchema},
+        health::{HealthModelError, HealthReportSchema, RegionHealth},
         meals::{Meal, MealModelError, MealSchema},
         restaurants::{Restaurant, RestaurantModelError, RestaurantSchema},
-        scrape_batch::ScrapedBatchModelError,
+        scrape_batch::{BatchQuerySchema, BatchSummary, ScrapeBatch, ScrapedBatchModelError},
+        tokens::{IssuedTokenSchema, TokenModelError, TokenRequestSchema},
+        webhooks::{
+            RegisteredWebhookSchema, Webhook, WebhookDelivery, WebhookModelError,
+            WebhookRequestSchema,
+        },
     },
     regions::CrousRegion,
 };
@@ -15,10 +22,14 @@ use uuid::Uuid;
 
 use crate::{
     admins::service::{AdminError, AdminService, AdminServiceImpl},
+    audit::service::{AuditService, AuditServiceImpl},
     batches::service::{BatchesService, BatchesServiceImpl},
     config::Config,
+    health::service::{HealthService, HealthServiceImpl},
     meals::service::{MealsService, MealsServiceImpl},
     restaurants::service::{RestaurantsService, RestaurantsServiceImpl},
+    tokens::service::{TokensService, TokensServiceImpl},
+    webhooks::service::{WebhooksService, WebhooksServiceImpl},
 };
 
 pub trait App {
@@ -30,6 +41,7 @@ pub trait App {
     fn get_restaurant_by_id(
         &self,
         name: String,
+        region: CrousRegion,
     ) -> impl Future<Output = Result<Restaurant, RestaurantModelError>> + Send;
     fn save_restaurants(
         &self,
@@ -51,13 +63,74 @@ pub trait App {
         name: String,
         region: CrousRegion,
     ) -> impl Future<Output = Result<Vec<Meal>, MealModelError>> + Send;
+    fn get_current_meals(
+        &self,
+        restaurant_ids: &[String],
+        region: CrousRegion,
+    ) -> impl Future<Output = Result<Vec<Meal>, MealModelError>> + Send;
     fn create_batch(
         &'_ self,
         entity: Entity,
         author_id: Uuid,
         region: CrousRegion,
         checksum: String,
+        summary: Option<BatchSummary>,
     ) -> impl Future<Output = Result<(Uuid, PgTransaction<'_>), ScrapedBatchModelError>> + Send;
+    fn get_batches(
+        &self,
+        region: CrousRegion,
+        query: BatchQuerySchema,
+    ) -> impl Future<Output = Result<Vec<ScrapeBatch>, ScrapedBatchModelError>> + Send;
+    fn issue_token(
+        &self,
+        request: TokenRequestSchema,
+        admin: Admin,
+    ) -> impl Future<Output = Result<IssuedTokenSchema, TokenModelError>> + Send;
+    fn revoke_token(
+        &self,
+        token_id: Uuid,
+    ) -> impl Future<Output = Result<(), TokenModelError>> + Send;
+    fn record_audit(
+        &self,
+        entry: AuditEntry,
+    ) -> impl Future<Output = Result<(), AuditModelError>> + Send;
+    fn get_audit(
+        &self,
+        query: &AuditQuerySchema,
+    ) -> impl Future<Output = Result<Vec<AuditEntry>, AuditModelError>> + Send;
+    fn register_webhook(
+        &self,
+        request: WebhookRequestSchema,
+        admin: Admin,
+    ) -> impl Future<Output = Result<RegisteredWebhookSchema, WebhookModelError>> + Send;
+    fn get_webhooks(
+        &self,
+        active: Option<bool>,
+    ) -> impl Future<Output = Result<Vec<Webhook>, WebhookModelError>> + Send;
+    fn delete_webhook(
+        &self,
+        webhook_id: Uuid,
+    ) -> impl Future<Output = Result<(), WebhookModelError>> + Send;
+    fn enable_webhook(
+        &self,
+        webhook_id: Uuid,
+    ) -> impl Future<Output = Result<(), WebhookModelError>> + Send;
+    fn get_webhook_deliveries(
+        &self,
+        webhook_id: Uuid,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<WebhookDelivery>, WebhookModelError>> + Send;
+    fn record_health(
+        &self,
+        region: CrousRegion,
+        report: HealthReportSchema,
+        admin: Admin,
+    ) -> impl Future<Output = Result<RegionHealth, HealthModelError>> + Send;
+    fn get_health(
+        &self,
+        region: CrousRegion,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<RegionHealth>, HealthModelError>> + Send;
 }
 
 pub type DefaultApp = AppImpl<
@@ -65,29 +138,45 @@ pub type DefaultApp = AppImpl<
     MealsServiceImpl<BatchesServiceImpl>,
     AdminServiceImpl,
     BatchesServiceImpl,
+    TokensServiceImpl,
+    AuditServiceImpl,
+    WebhooksServiceImpl,
+    HealthServiceImpl,
 >;
 
 #[derive(Clone)]
-pub struct AppImpl<R, M, A, S>
+pub struct AppImpl<R, M, A, S, T, L, W, H>
 where
     R: RestaurantsService + Send + Sync,
     M: MealsService + Send + Sync,
     A: AdminService + Send + Sync,
     S: BatchesService + Send + Sync,
+    T: TokensService + Send + Sync,
+    L: AuditService + Send + Sync,
+    W: WebhooksService + Send + Sync,
+    H: HealthService + Send + Sync,
 {
     restaurants_service: R,
     meals_service: M,
     admin_service: A,
     batch_service: Arc<S>,
+    tokens_service: T,
+    audit_service: L,
+    webhooks_service: Arc<W>,
+    health_service: H,
     config: Arc<Config>,
 }
 
-impl<R, M, A, S> App for AppImpl<R, M, A, S>
+impl<R, M, A, S, T, L, W, H> App for AppImpl<R, M, A, S, T, L, W, H>
 where
     R: RestaurantsService + Send + Sync,
     M: MealsService + Send + Sync,
     A: AdminService + Send + Sync,
     S: BatchesService + Send + Sync,
+    T: TokensService + Send + Sync,
+    L: AuditService + Send + Sync,
+    W: WebhooksService + Send + Sync,
+    H: HealthService + Send + Sync,
 {
     async fn get_restaurants(
         &self,
@@ -96,8 +185,14 @@ where
         self.restaurants_service.get_restaurants(region).await
     }
 
-    async fn get_restaurant_by_id(&self, name: String) -> Result<Restaurant, RestaurantModelError> {
-        self.restaurants_service.get_restaurant_by_id(name).await
+    async fn get_restaurant_by_id(
+        &self,
+        name: String,
+        region: CrousRegion,
+    ) -> Result<Restaurant, RestaurantModelError> {
+        self.restaurants_service
+            .get_restaurant_by_id(name, region)
+            .await
     }
 
     async fn save_restaurants(
@@ -142,31 +237,131 @@ where
             .await
     }
 
+    async fn get_current_meals(
+        &self,
+        restaurant_ids: &[String],
+        region: CrousRegion,
+    ) -> Result<Vec<Meal>, MealModelError> {
+        self.meals_service
+            .get_current_meals(restaurant_ids, region)
+            .await
+    }
+
     async fn create_batch(
         &'_ self,
         entity: Entity,
         author_id: Uuid,
         region: CrousRegion,
         checksum: String,
+        summary: Option<BatchSummary>,
     ) -> Result<(Uuid, PgTransaction<'_>), ScrapedBatchModelError> {
         self.batch_service
-            .create_batch(entity, author_id, region, checksum)
+            .create_batch(entity, author_id, region, checksum, summary)
             .await
     }
+
+    async fn get_batches(
+        &self,
+        region: CrousRegion,
+        query: BatchQuerySchema,
+    ) -> Result<Vec<ScrapeBatch>, ScrapedBatchModelError> {
+        self.batch_service.get_batches(region, query).await
+    }
+
+    async fn issue_token(
+        &self,
+        request: TokenRequestSchema,
+        admin: Admin,
+    ) -> Result<IssuedTokenSchema, TokenModelError> {
+        self.tokens_service.issue_token(request, admin).await
+    }
+
+    async fn revoke_token(&self, token_id: Uuid) -> Result<(), TokenModelError> {
+        self.tokens_service.revoke_token(token_id).await
+    }
+
+    async fn record_audit(&self, entry: AuditEntry) -> Result<(), AuditModelError> {
+        self.audit_service.record(entry).await
+    }
+
+    async fn get_audit(
+        &self,
+        query: &AuditQuerySchema,
+    ) -> Result<Vec<AuditEntry>, AuditModelError> {
+        self.audit_service.get_entries(query).await
+    }
+
+    async fn register_webhook(
+        &self,
+        request: WebhookRequestSchema,
+        admin: Admin,
+    ) -> Result<RegisteredWebhookSchema, WebhookModelError> {
+        self.webhooks_service.register_webhook(request, admin).await
+    }
+
+    async fn get_webhooks(&self, active: Option<bool>) -> Result<Vec<Webhook>, WebhookModelError> {
+        self.webhooks_service.get_webhooks(active).await
+    }
+
+    async fn delete_webhook(&self, webhook_id: Uuid) -> Result<(), WebhookModelError> {
+        self.webhooks_service.delete_webhook(webhook_id).await
+    }
+
+    async fn enable_webhook(&self, webhook_id: Uuid) -> Result<(), WebhookModelError> {
+        self.webhooks_service.enable_webhook(webhook_id).await
+    }
+
+    async fn get_webhook_deliveries(
+        &self,
+        webhook_id: Uuid,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, WebhookModelError> {
+        self.webhooks_service
+            .get_deliveries(webhook_id, limit)
+            .await
+    }
+
+    async fn record_health(
+        &self,
+        region: CrousRegion,
+        report: HealthReportSchema,
+        admin: Admin,
+    ) -> Result<RegionHealth, HealthModelError> {
+        self.health_service
+            .record_health(region, report, admin)
+            .await
+    }
+
+    async fn get_health(
+        &self,
+        region: CrousRegion,
+        limit: i64,
+    ) -> Result<Vec<RegionHealth>, HealthModelError> {
+        self.health_service.get_health(region, limit).await
+    }
 }
 
-impl<R, M, A, S> AppImpl<R, M, A, S>
+impl<R, M, A, S, T, L, W, H> AppImpl<R, M, A, S, T, L, W, H>
 where
     R: RestaurantsService + Send + Sync,
     M: MealsService + Send + Sync,
     A: AdminService + Send + Sync,
     S: BatchesService + Send + Sync,
+    T: TokensService + Send + Sync,
+    L: AuditService + Send + Sync,
+    W: WebhooksService + Send + Sync,
+    H: HealthService + Send + Sync,
 {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         restaurants_service: R,
         meals_service: M,
         admin_service: A,
         batch_service: Arc<S>,
+        tokens_service: T,
+        audit_service: L,
+        webhooks_service: Arc<W>,
+        health_service: H,
         config: Arc<Config>,
     ) -> Self {
         Self {
@@ -174,6 +369,10 @@ where
             meals_service,
             admin_service,
             batch_service,
+            tokens_service,
+            audit_service,
+            webhooks_service,
+            health_service,
             config,
         }
     }
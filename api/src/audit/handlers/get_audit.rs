@@ -0,0 +1,50 @@
+use axum::{Json, extract::State};
+use chrono::Utc;
+use htc::{
+    models::audit::{AuditEntrySchema, AuditQuerySchema},
+    verifiable::{SignedPayload, check_issued_at},
+};
+use tracing::error;
+
+use crate::{app::App, error::ApiError};
+
+#[utoipa::path(
+    get,
+    path = "/audit",
+    tag = "Audit",
+    request_body = SignedPayload<AuditQuerySchema>,
+    responses(
+        (status = 200, description = "Audit log entries, most recent first", body = [Vec<AuditEntrySchema>]),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn get_audit<A>(
+    State(state): State<A>,
+    Json(body): Json<SignedPayload<AuditQuerySchema>>,
+) -> Result<Json<Vec<AuditEntrySchema>>, ApiError>
+where
+    A: App + Send + Sync + Clone,
+{
+    let admin = state.get_admin(&body.author).await.map_err(|e| {
+        error!("{}", e.to_string());
+        ApiError::Unauthorized(e.to_string())
+    })?;
+    let (query, _) = body.verify(admin.ssh_key.as_str()).map_err(|e| {
+        error!("{}", e.to_string());
+        ApiError::Unauthorized(e.to_string())
+    })?;
+    check_issued_at(query.issued_at, Utc::now().timestamp()).map_err(|e| {
+        error!("{}", e.to_string());
+        ApiError::Unauthorized(e.to_string())
+    })?;
+
+    let entries = state
+        .get_audit(query)
+        .await
+        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+
+    Ok(Json(
+        entries.into_iter().map(AuditEntrySchema::from).collect(),
+    ))
+}
@@ -0,0 +1,4 @@
+pub mod handlers;
+pub mod router;
+pub mod service;
+pub mod trail;
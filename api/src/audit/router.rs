@@ -0,0 +1,12 @@
+use axum::{Router, routing::get};
+
+use crate::{app::App, audit::handlers::get_audit::get_audit};
+
+pub fn audit_router<A>(app: A) -> Router
+where
+    A: App + Send + Sync + Clone + 'static,
+{
+    Router::new()
+        .route("/audit", get(get_audit::<A>))
+        .with_state(app)
+}
@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use htc::models::audit::{AuditEntry, AuditModel as _, AuditModelError, AuditQuerySchema};
+use sqlx::PgPool;
+use tracing::instrument;
+
+pub trait AuditService {
+    fn record(&self, entry: AuditEntry)
+    -> impl Future<Output = Result<(), AuditModelError>> + Send;
+    fn get_entries(
+        &self,
+        query: &AuditQuerySchema,
+    ) -> impl Future<Output = Result<Vec<AuditEntry>, AuditModelError>> + Send;
+}
+
+#[derive(Clone)]
+pub struct AuditServiceImpl {
+    pool: Arc<PgPool>,
+}
+
+impl AuditService for AuditServiceImpl {
+    #[instrument(skip(self, entry), fields(action=%entry.action, outcome=%entry.outcome), err)]
+    async fn record(&self, entry: AuditEntry) -> Result<(), AuditModelError> {
+        self.pool.create_audit_entry(entry).await
+    }
+
+    async fn get_entries(
+        &self,
+        query: &AuditQuerySchema,
+    ) -> Result<Vec<AuditEntry>, AuditModelError> {
+        self.pool.get_audit_entries(query).await
+    }
+}
+
+impl AuditServiceImpl {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
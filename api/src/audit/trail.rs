@@ -0,0 +1,177 @@
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{HeaderMap, header, request::Parts},
+};
+use htc::{
+    models::{
+        admins::Admin,
+        audit::{AuditEntry, AuditOutcome},
+    },
+    verifiable::key_fingerprint,
+};
+use uuid::Uuid;
+
+use crate::error::ApiError;
+
+/// Reverse proxies whose `x-forwarded-for` header is believed, set on the
+/// requests by the router.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(pub Arc<Vec<IpAddr>>);
+
+/// Where a request comes from, as far as the server can tell.
+#[derive(Debug, Clone, Default)]
+pub struct Provenance {
+    pub source_ip: Option<String>,
+    pub client_version: Option<String>,
+}
+
+impl<S> FromRequestParts<S> for Provenance
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let peer_ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let trusted = parts
+            .extensions
+            .get::<TrustedProxies>()
+            .cloned()
+            .unwrap_or_default();
+        let client_version = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+
+        Ok(Self {
+            source_ip: source_ip(peer_ip, &parts.headers, &trusted.0).map(|ip| ip.to_string()),
+            client_version,
+        })
+    }
+}
+
+/// The peer, or when the peer is a trusted proxy the last address it
+/// forwarded for that isn't a trusted proxy too. Anyone else can write
+/// whatever they like in `x-forwarded-for`.
+fn source_ip(peer: Option<IpAddr>, headers: &HeaderMap, trusted: &[IpAddr]) -> Option<IpAddr> {
+    let peer = peer?;
+    if !trusted.contains(&peer) {
+        return Some(peer);
+    }
+    let forwarded: Vec<IpAddr> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|ip| ip.trim().parse().ok())
+        .collect();
+    forwarded
+        .iter()
+        .rev()
+        .find(|ip| !trusted.contains(ip))
+        .or(forwarded.first())
+        .copied()
+        .or(Some(peer))
+}
+
+/// Collects what is known about a write while it is being handled, then turns
+/// into an audit entry once the outcome is known.
+pub struct AuditTrail {
+    entry: AuditEntry,
+}
+
+impl AuditTrail {
+    pub fn new(action: &str, author: &str, provenance: Provenance) -> Self {
+        Self {
+            entry: AuditEntry {
+                audit_id: Uuid::new_v4(),
+                action: action.to_string(),
+                author: author.to_string(),
+                admin_id: None,
+                key_fingerprint: None,
+                client_version: provenance.client_version,
+                source_ip: provenance.source_ip,
+                region: None,
+                payload_digest: None,
+                outcome: AuditOutcome::Failed,
+                detail: None,
+                created_at: None,
+            },
+        }
+    }
+
+    pub fn region(mut self, region: &str) -> Self {
+        self.entry.region = Some(region.to_string());
+        self
+    }
+
+    pub fn admin(&mut self, admin: &Admin) {
+        self.entry.admin_id = Some(admin.admin_id);
+        self.entry.key_fingerprint = Some(key_fingerprint(&admin.ssh_key));
+    }
+
+    pub fn digest(&mut self, digest: &str) {
+        self.entry.payload_digest = Some(digest.to_string());
+    }
+
+    pub fn finish<T>(mut self, result: &Result<T, ApiError>) -> AuditEntry {
+        let (outcome, detail) = match result {
+            Ok(_) => (AuditOutcome::Success, None),
+            Err(e) => {
+                let outcome = match e {
+                    ApiError::Conflict => AuditOutcome::SyncSkipped,
+                    ApiError::Unauthorized(_) | ApiError::Forbidden(_) => {
+                        AuditOutcome::Unauthorized
+                    }
+                    ApiError::NotFound(_)
+                    | ApiError::BadRequest(_)
+                    | ApiError::UnProcessableEntity(_) => AuditOutcome::Rejected,
+                    ApiError::InternalServerError(_) | ApiError::ServiceUnavailable(_) => {
+                        AuditOutcome::Failed
+                    }
+                };
+                (outcome, Some(e.to_string()))
+            }
+        };
+        self.entry.outcome = outcome;
+        self.entry.detail = detail;
+        self.entry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_forwarded_for_only_trusted_from_proxies() {
+        let proxy: IpAddr = "10.0.0.2".parse().unwrap();
+        let client: IpAddr = "203.0.113.7".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("198.51.100.1, 203.0.113.7, 10.0.0.2"),
+        );
+
+        assert_eq!(source_ip(Some(client), &headers, &[proxy]), Some(client));
+        assert_eq!(source_ip(Some(proxy), &headers, &[]), Some(proxy));
+        // the spoofable leftmost entry is ignored
+        assert_eq!(source_ip(Some(proxy), &headers, &[proxy]), Some(client));
+        assert_eq!(
+            source_ip(Some(proxy), &HeaderMap::new(), &[proxy]),
+            Some(proxy)
+        );
+    }
+}
@@ -0,0 +1,46 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use htc::{
+    models::scrape_batch::{BatchQuerySchema, ScrapeBatch},
+    regions::CrousRegion,
+};
+
+use crate::{app::App, error::ApiError};
+
+const MAX_BATCHES_LIMIT: i64 = 200;
+
+#[utoipa::path(
+    get,
+    path = "/{region}/batches",
+    params(
+        ("region" = String, Path, description = "Region of the batches"),
+        BatchQuerySchema
+    ),
+    tag = "Batches",
+    responses(
+        (status = 200, description = "Scrape batches of the region, most recent first", body = [Vec<ScrapeBatch>]),
+        (status = 404, description = "Unknown region"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn get_batches<A>(
+    Path(region): Path<String>,
+    Query(mut query): Query<BatchQuerySchema>,
+    State(state): State<A>,
+) -> Result<Json<Vec<ScrapeBatch>>, ApiError>
+where
+    A: App + Send + Sync + Clone,
+{
+    let region: CrousRegion = region
+        .parse()
+        .map_err(|_| ApiError::NotFound(format!("Unknown region: {}", region)))?;
+    query.limit = query.limit.map(|limit| limit.clamp(1, MAX_BATCHES_LIMIT));
+
+    let batches = state
+        .get_batches(region, query)
+        .await
+        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+    Ok(Json(batches))
+}
@@ -0,0 +1,12 @@
+use axum::{Router, routing::get};
+
+use crate::{app::App, batches::handlers::get_batches::get_batches};
+
+pub fn batches_router<A>(app: A) -> Router
+where
+    A: App + Send + Sync + Clone + 'static,
+{
+    Router::new()
+        .route("/{region}/batches", get(get_batches::<A>))
+        .with_state(app)
+}
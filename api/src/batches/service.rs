@@ -3,7 +3,9 @@ use std::sync::Arc;
 use htc::{
     models::{
         Entity,
-        scrape_batch::{ScrapeBatch, ScrapedBatchModel, ScrapedBatchModelError},
+        scrape_batch::{
+            BatchQuerySchema, BatchSummary, ScrapeBatch, ScrapedBatchModel, ScrapedBatchModelError,
+        },
     },
     regions::CrousRegion,
 };
@@ -23,6 +25,7 @@ pub trait BatchesService {
         author: Uuid,
         region: CrousRegion,
         checksum: String,
+        summary: Option<BatchSummary>,
     ) -> impl Future<Output = Result<(Uuid, PgTransaction<'_>), ScrapedBatchModelError>> + Send;
 
     fn current_batch(
@@ -30,6 +33,25 @@ pub trait BatchesService {
         entity: &Entity,
         region: CrousRegion,
     ) -> impl Future<Output = Result<Option<ScrapeBatch>, ScrapedBatchModelError>> + Send;
+
+    fn get_batch(
+        &self,
+        batch_id: Uuid,
+    ) -> impl Future<Output = Result<Option<ScrapeBatch>, ScrapedBatchModelError>> + Send;
+
+    fn get_batches(
+        &self,
+        region: CrousRegion,
+        query: BatchQuerySchema,
+    ) -> impl Future<Output = Result<Vec<ScrapeBatch>, ScrapedBatchModelError>> + Send;
+
+    fn get_batches_since(
+        &self,
+        seq: i64,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<ScrapeBatch>, ScrapedBatchModelError>> + Send;
+
+    fn last_seq(&self) -> impl Future<Output = Result<i64, ScrapedBatchModelError>> + Send;
 }
 
 impl BatchesService for BatchesServiceImpl {
@@ -40,6 +62,7 @@ impl BatchesService for BatchesServiceImpl {
         author_id: Uuid,
         region: CrousRegion,
         checksum: String,
+        summary: Option<BatchSummary>,
     ) -> Result<(Uuid, PgTransaction<'_>), ScrapedBatchModelError> {
         let current_batch = self.current_batch(&entity, region).await?;
 
@@ -59,6 +82,8 @@ impl BatchesService for BatchesServiceImpl {
                 scraped_at: None,
                 region: region.to_string(),
                 checksum,
+                seq: None,
+                summary,
             })
             .await?;
         Ok((batch_uuid, tx))
@@ -71,6 +96,33 @@ impl BatchesService for BatchesServiceImpl {
     ) -> Result<Option<ScrapeBatch>, ScrapedBatchModelError> {
         self.pool.current_batch(entity, region).await
     }
+
+    async fn get_batch(
+        &self,
+        batch_id: Uuid,
+    ) -> Result<Option<ScrapeBatch>, ScrapedBatchModelError> {
+        self.pool.get_batch(batch_id).await
+    }
+
+    async fn get_batches(
+        &self,
+        region: CrousRegion,
+        query: BatchQuerySchema,
+    ) -> Result<Vec<ScrapeBatch>, ScrapedBatchModelError> {
+        self.pool.get_batches(region, query).await
+    }
+
+    async fn get_batches_since(
+        &self,
+        seq: i64,
+        limit: i64,
+    ) -> Result<Vec<ScrapeBatch>, ScrapedBatchModelError> {
+        self.pool.get_batches_since(seq, limit).await
+    }
+
+    async fn last_seq(&self) -> Result<i64, ScrapedBatchModelError> {
+        self.pool.last_seq().await
+    }
 }
 
 impl BatchesServiceImpl {
@@ -1,3 +1,5 @@
+use std::net::IpAddr;
+
 use clap::Parser;
 
 #[derive(Parser, Default, Clone, Debug)]
@@ -23,7 +25,15 @@ pub struct Config {
     #[clap(
         env,
         long,
-        help = "Bearer token required to connect to the SSE /events endpoint"
+        help = "Static bearer token granting unrestricted access to the SSE /events endpoint, scoped tokens are issued through /tokens"
     )]
     pub sse_token: String,
+
+    #[clap(
+        env,
+        long,
+        value_delimiter = ',',
+        help = "Addresses of the reverse proxies whose x-forwarded-for header is trusted"
+    )]
+    pub trusted_proxies: Vec<IpAddr>,
 }
@@ -0,0 +1,36 @@
+use opentelemetry::{global, metrics::Counter};
+
+/// Counters of the event listener, exported through OpenTelemetry.
+#[derive(Clone)]
+pub struct ListenerMetrics {
+    pub received: Counter<u64>,
+    pub handled: Counter<u64>,
+    /// Handler failures stored in `event_dead_letters`
+    pub dead_lettered: Counter<u64>,
+    /// Notifications lost, without handler or dead letter
+    pub dropped: Counter<u64>,
+    pub reconnects: Counter<u64>,
+    /// Batches whose notification never came, replayed from `scrape_batch`
+    /// after a reconnect or skipped over
+    pub gaps: Counter<u64>,
+}
+
+impl ListenerMetrics {
+    pub fn new() -> Self {
+        let meter = global::meter("htc.events");
+        Self {
+            received: meter.u64_counter("htc.events.received").build(),
+            handled: meter.u64_counter("htc.events.handled").build(),
+            dead_lettered: meter.u64_counter("htc.events.dead_lettered").build(),
+            dropped: meter.u64_counter("htc.events.dropped").build(),
+            reconnects: meter.u64_counter("htc.events.reconnects").build(),
+            gaps: meter.u64_counter("htc.events.gaps").build(),
+        }
+    }
+}
+
+impl Default for ListenerMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
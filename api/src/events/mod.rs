@@ -1,78 +1,333 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
+pub mod metrics;
 pub mod scraping_channel;
 
+use htc::models::{
+    dead_letters::{DeadLetter, DeadLetterModel as _},
+    scrape_batch::{BatchNotification, ScrapedBatchModel as _},
+};
+use opentelemetry::KeyValue;
 use serde::{Deserialize, de::DeserializeOwned};
 use sqlx::{PgPool, postgres::PgListener};
-use tracing::{error, instrument};
+use tracing::{debug, error, info, instrument, warn};
+use uuid::Uuid;
+
+use crate::events::metrics::ListenerMetrics;
+
+const RECONNECT_BASE_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+/// Batches read at once when catching up after a reconnect
+const REPLAY_LIMIT: i64 = 100;
 
 pub trait EventHandler {
     type Input: DeserializeOwned + Send;
-    type Rejection;
+    type Rejection: Display;
     fn handle(
         &self,
         input: Self::Input,
     ) -> impl Future<Output = Result<(), Self::Rejection>> + Send;
 }
 
-pub struct EventListener<H>
-where
-    H: EventHandler,
-{
-    handler: Arc<H>,
-    pool: Arc<PgPool>,
-}
-
 #[derive(thiserror::Error, Debug)]
 pub enum EventError {
     #[error("Couldn't listen : {0}")]
     CouldntListen(String),
     #[error("Coudln't parse: {0}")]
     CouldntParse(String),
+    #[error("Rejected by {0} : {1}")]
+    Rejected(String, String),
 }
 
-impl<H> EventListener<H>
+type HandlerFuture<'a> = Pin<Box<dyn Future<Output = Result<(), EventError>> + Send + 'a>>;
+
+/// Object safe side of an `EventHandler`. Each handler parses the payload
+/// itself, so handlers with different inputs can share a channel and a
+/// payload one of them doesn't understand doesn't reach the others.
+trait RegisteredHandler: Send + Sync {
+    fn name(&self) -> &str;
+    fn handle_payload<'a>(&'a self, payload: &'a str) -> HandlerFuture<'a>;
+}
+
+struct Registration<H> {
+    name: String,
+    handler: Arc<H>,
+}
+
+impl<H> RegisteredHandler for Registration<H>
 where
     H: EventHandler + Send + Sync + 'static,
 {
-    pub fn new(handler: Arc<H>, pool: Arc<PgPool>) -> Self {
-        Self { handler, pool }
+    fn name(&self) -> &str {
+        &self.name
     }
-    #[instrument(skip(self), fields(topic=topic), err)]
-    pub async fn listen(
-        &self,
-        topic: String,
-    ) -> Result<tokio::task::JoinHandle<Result<(), EventError>>, EventError> {
+
+    fn handle_payload<'a>(&'a self, payload: &'a str) -> HandlerFuture<'a> {
+        Box::pin(async move {
+            let notification: Notification<H::Input> = serde_json::from_str(payload)
+                .map_err(|e| EventError::CouldntParse(e.to_string()))?;
+            self.handler
+                .handle(notification.data)
+                .await
+                .map_err(|e| EventError::Rejected(self.name.clone(), e.to_string()))
+        })
+    }
+}
+
+/// Dispatches Postgres notifications to the handlers registered on their
+/// channel. The connection is reestablished with backoff whenever it drops,
+/// and a notification a handler fails on is stored in `event_dead_letters`
+/// instead of stopping the listener.
+pub struct EventListener {
+    pool: Arc<PgPool>,
+    handlers: HashMap<String, Vec<Arc<dyn RegisteredHandler>>>,
+    metrics: ListenerMetrics,
+    /// Channel carrying the batch notifications, replayed from
+    /// `scrape_batch` after a reconnect
+    replay: Option<String>,
+    /// Seq of the last batch dispatched on the replayed channel
+    last_seq: Mutex<Option<i64>>,
+}
+
+impl EventListener {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self {
+            pool,
+            handlers: HashMap::new(),
+            metrics: ListenerMetrics::new(),
+            replay: None,
+            last_seq: Mutex::new(None),
+        }
+    }
+
+    /// Re-reads the batches committed past the last one seen on `channel`
+    /// whenever the connection comes back, their notifications having been
+    /// sent while nobody listened.
+    pub fn replay_batches(mut self, channel: &str) -> Self {
+        self.replay = Some(channel.to_string());
+        self
+    }
+
+    /// Registers `handler` on `channel`, several handlers can listen to the
+    /// same channel.
+    pub fn register<H>(mut self, channel: &str, name: &str, handler: Arc<H>) -> Self
+    where
+        H: EventHandler + Send + Sync + 'static,
+    {
+        self.handlers
+            .entry(channel.to_string())
+            .or_default()
+            .push(Arc::new(Registration {
+                name: name.to_string(),
+                handler,
+            }));
+        self
+    }
+
+    /// Runs until the process exits, the task never ends on its own.
+    pub fn listen(self) -> tokio::task::JoinHandle<()> {
+        let listener = Arc::new(self);
+        tokio::spawn(async move { listener.run().await })
+    }
+
+    #[instrument(skip(self), err)]
+    async fn connect(&self) -> Result<PgListener, EventError> {
         let mut listener = PgListener::connect_with(&self.pool)
             .await
             .map_err(|e| EventError::CouldntListen(e.to_string()))?;
         listener
-            .listen(&topic)
+            .listen_all(self.handlers.keys().map(String::as_str))
             .await
             .map_err(|e| EventError::CouldntListen(e.to_string()))?;
-        let handler = self.handler.clone();
-        Ok(tokio::spawn(async move {
-            loop {
-                let Ok(notification) = listener.recv().await else {
-                    error!("got an error on notification");
-                    continue;
-                };
-                let payload: Notification<H::Input> = serde_json::from_str(notification.payload())
-                    .map_err(|e| {
-                        error!(
-                            "Couldn't parse {:#?} because of {}",
-                            notification.payload(),
-                            e.to_string()
-                        );
-                        EventError::CouldntParse(e.to_string())
-                    })?;
-                let handler = handler.clone();
-                let data = payload.data;
-                tokio::spawn(async move {
-                    let _ = handler.handle(data).await;
+        Ok(listener)
+    }
+
+    fn reconnect_delay(attempt: u32) -> Duration {
+        RECONNECT_BASE_DELAY
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(RECONNECT_MAX_DELAY)
+    }
+
+    async fn run(self: Arc<Self>) {
+        let mut attempt = 0;
+        loop {
+            if let Ok(mut listener) = self.connect().await {
+                if attempt > 0 {
+                    self.metrics.reconnects.add(1, &[]);
+                }
+                info!("Listening to {:?}", self.handlers.keys());
+                attempt = 0;
+                // notifications received meanwhile are buffered by the
+                // listener and skipped once replayed
+                self.catch_up().await;
+                // `recv` would reconnect silently, the connection is
+                // reestablished here instead to catch up afterwards
+                loop {
+                    match listener.try_recv().await {
+                        Ok(Some(notification)) => {
+                            self.dispatch(notification.channel(), notification.payload().into())
+                                .await
+                        }
+                        Ok(None) => {
+                            warn!("Lost the notification connection");
+                            break;
+                        }
+                        Err(e) => {
+                            warn!("Lost the notification connection : {}", e);
+                            break;
+                        }
+                    }
+                }
+            }
+
+            let delay = Self::reconnect_delay(attempt);
+            attempt = attempt.saturating_add(1);
+            warn!("Reconnecting the event listener in {:?}", delay);
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Dispatches the batches committed since the last one seen, or starts
+    /// from the last committed one on the first connection.
+    async fn catch_up(self: &Arc<Self>) {
+        let Some(channel) = &self.replay else {
+            return;
+        };
+        let last_seq = *self.last_seq.lock().unwrap();
+        let Some(mut seq) = last_seq else {
+            match self.pool.last_seq().await {
+                Ok(seq) => {
+                    self.last_seq.lock().unwrap().get_or_insert(seq);
+                }
+                Err(e) => warn!("Couldn't read the last batch seq : {}", e),
+            }
+            return;
+        };
+
+        let attributes = [KeyValue::new("channel", channel.clone())];
+        loop {
+            let batches = match self.pool.get_batches_since(seq, REPLAY_LIMIT).await {
+                Ok(batches) => batches,
+                Err(e) => {
+                    warn!("Couldn't replay the batches past {} : {}", seq, e);
+                    return;
+                }
+            };
+            let Some(last) = batches.last().and_then(|batch| batch.seq) else {
+                return;
+            };
+            info!("Replaying {} batches past {}", batches.len(), seq);
+            self.metrics.gaps.add(batches.len() as u64, &attributes);
+            for batch in batches {
+                let notification = serde_json::json!({
+                    "operation": "REPLAY",
+                    "table": "scrape_batch",
+                    "data": BatchNotification {
+                        batch_id: batch.batch_id,
+                        seq: batch.seq,
+                    },
+                    "entity": batch.entity.to_string(),
                 });
+                self.dispatch(channel, notification.to_string().into())
+                    .await;
+            }
+            seq = last;
+        }
+    }
+
+    /// Moves the cursor of the replayed channel, false when the batch was
+    /// already dispatched.
+    fn advance(&self, channel: &str, payload: &str) -> bool {
+        if self.replay.as_deref() != Some(channel) {
+            return true;
+        }
+        let Some(seq) = serde_json::from_str::<Notification<BatchNotification>>(payload)
+            .ok()
+            .and_then(|notification| notification.data.seq)
+        else {
+            return true;
+        };
+
+        let mut last_seq = self.last_seq.lock().unwrap();
+        match *last_seq {
+            Some(last) if seq <= last => return false,
+            Some(last) if seq > last + 1 => {
+                warn!("Missed the batches between {} and {}", last, seq);
+                self.metrics.gaps.add(
+                    (seq - last - 1) as u64,
+                    &[KeyValue::new("channel", channel.to_string())],
+                );
+            }
+            _ => {}
+        }
+        *last_seq = Some(seq);
+        true
+    }
+
+    async fn dispatch(self: &Arc<Self>, channel: &str, payload: Arc<str>) {
+        if !self.advance(channel, &payload) {
+            debug!("Skipping {} already dispatched", payload);
+            return;
+        }
+        let channel = channel.to_string();
+        let attributes = [KeyValue::new("channel", channel.clone())];
+        self.metrics.received.add(1, &attributes);
+
+        let Some(handlers) = self.handlers.get(&channel) else {
+            self.metrics.dropped.add(1, &attributes);
+            return;
+        };
+        // batches are handled one at a time in seq order, subscribers skip
+        // the seqs below the last one they got
+        if self.replay.as_deref() == Some(channel.as_str()) {
+            for handler in handlers {
+                self.handle(&channel, handler.as_ref(), &payload).await;
             }
-        }))
+            return;
+        }
+        for handler in handlers {
+            let listener = self.clone();
+            let handler = handler.clone();
+            let channel = channel.clone();
+            let payload = payload.clone();
+            tokio::spawn(
+                async move { listener.handle(&channel, handler.as_ref(), &payload).await },
+            );
+        }
+    }
+
+    async fn handle(&self, channel: &str, handler: &dyn RegisteredHandler, payload: &str) {
+        let attributes = [
+            KeyValue::new("channel", channel.to_string()),
+            KeyValue::new("handler", handler.name().to_string()),
+        ];
+        let Err(e) = handler.handle_payload(payload).await else {
+            self.metrics.handled.add(1, &attributes);
+            return;
+        };
+
+        error!("{} couldn't handle {:#?} : {}", handler.name(), payload, e);
+        let dead_letter = DeadLetter {
+            dead_letter_id: Uuid::new_v4(),
+            channel: channel.to_string(),
+            handler: handler.name().to_string(),
+            payload: payload.to_string(),
+            error: e.to_string(),
+            created_at: None,
+        };
+        match self.pool.record_dead_letter(dead_letter).await {
+            Ok(()) => self.metrics.dead_lettered.add(1, &attributes),
+            Err(e) => {
+                error!("Couldn't store the dead letter, dropping it : {}", e);
+                self.metrics.dropped.add(1, &attributes);
+            }
+        }
     }
 }
 
@@ -84,3 +339,136 @@ pub struct Notification<T> {
     data: T,
     entity: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Failing;
+
+    impl EventHandler for Failing {
+        type Input = serde_json::Value;
+        type Rejection = String;
+
+        async fn handle(&self, _: Self::Input) -> Result<(), Self::Rejection> {
+            Err("teapot".to_string())
+        }
+    }
+
+    /// Records the seqs it handles, the first batches taking the longest.
+    #[derive(Default)]
+    struct Recording {
+        seqs: Mutex<Vec<i64>>,
+    }
+
+    impl EventHandler for Recording {
+        type Input = BatchNotification;
+        type Rejection = String;
+
+        async fn handle(&self, input: Self::Input) -> Result<(), Self::Rejection> {
+            let seq = input.seq.unwrap_or_default();
+            tokio::time::sleep(Duration::from_millis(50 * (3 - seq).max(0) as u64)).await;
+            self.seqs.lock().unwrap().push(seq);
+            Ok(())
+        }
+    }
+
+    fn lazy_pool() -> Arc<PgPool> {
+        dotenv::dotenv().ok();
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL is needed by the tests");
+        Arc::new(PgPool::connect_lazy(&url).unwrap())
+    }
+
+    #[test]
+    fn test_reconnect_backoff() {
+        assert_eq!(EventListener::reconnect_delay(0), RECONNECT_BASE_DELAY);
+        assert_eq!(EventListener::reconnect_delay(3), RECONNECT_BASE_DELAY * 8);
+        assert_eq!(EventListener::reconnect_delay(10), RECONNECT_MAX_DELAY);
+        assert_eq!(
+            EventListener::reconnect_delay(u32::MAX),
+            RECONNECT_MAX_DELAY
+        );
+    }
+
+    #[tokio::test]
+    async fn test_batch_cursor() {
+        let listener = EventListener::new(lazy_pool()).replay_batches("scraping_channel");
+        let notification = |seq: i64| {
+            serde_json::json!({
+                "operation": "INSERT",
+                "table": "scrape_batch",
+                "data": { "batch_id": Uuid::nil(), "seq": seq },
+                "entity": "restaurants",
+            })
+            .to_string()
+        };
+        assert!(listener.advance("scraping_channel", &notification(3)));
+        assert!(!listener.advance("scraping_channel", &notification(3)));
+        assert!(listener.advance("scraping_channel", &notification(7)));
+        assert!(!listener.advance("scraping_channel", &notification(5)));
+        assert_eq!(*listener.last_seq.lock().unwrap(), Some(7));
+        // other channels aren't sequenced
+        assert!(listener.advance("other_channel", &notification(1)));
+    }
+
+    #[tokio::test]
+    async fn test_batches_are_handled_in_seq_order() {
+        let recording = Arc::new(Recording::default());
+        let listener = Arc::new(
+            EventListener::new(lazy_pool())
+                .register("scraping_channel", "recording", recording.clone())
+                .replay_batches("scraping_channel"),
+        );
+        let notification = |seq: i64| -> Arc<str> {
+            serde_json::json!({
+                "operation": "INSERT",
+                "table": "scrape_batch",
+                "data": { "batch_id": Uuid::new_v4(), "seq": seq },
+                "entity": "restaurants",
+            })
+            .to_string()
+            .into()
+        };
+
+        // the first batch takes longer to handle than the second one
+        listener.dispatch("scraping_channel", notification(1)).await;
+        listener.dispatch("scraping_channel", notification(2)).await;
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while recording.seqs.lock().unwrap().len() < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(*recording.seqs.lock().unwrap(), vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_failing_handler_is_dead_lettered() {
+        let pool = lazy_pool();
+        let channel = format!("test_{}", Uuid::new_v4());
+        let listener = EventListener::new(pool.clone());
+        let handler = Registration {
+            name: "failing".to_string(),
+            handler: Arc::new(Failing),
+        };
+        let payload = r#"{"operation":"INSERT","table":"t","data":{},"entity":"e"}"#;
+        listener.handle(&channel, &handler, payload).await;
+
+        let rows: Vec<(String, String, String)> = sqlx::query_as(
+            "DELETE FROM event_dead_letters WHERE channel = $1 RETURNING handler, payload, error",
+        )
+        .bind(&channel)
+        .fetch_all(pool.as_ref())
+        .await
+        .unwrap();
+        assert_eq!(
+            rows,
+            vec![(
+                "failing".to_string(),
+                payload.to_string(),
+                "Rejected by failing : teapot".to_string()
+            )]
+        );
+    }
+}
@@ -1,24 +1,44 @@
-use htc::models::scrape_batch::ScrapeBatch;
+use std::sync::Arc;
+
+use htc::models::scrape_batch::{BatchNotification, ScrapeBatch};
 use tokio::sync::broadcast;
-use tracing::{info, instrument};
+use tracing::instrument;
+use uuid::Uuid;
 
-use crate::events::EventHandler;
+use crate::{
+    batches::service::{BatchesService, BatchesServiceImpl},
+    events::EventHandler,
+    webhooks::dispatcher::WebhookDispatcher,
+};
 
 pub struct ScrapingChannel {
     pub sender: broadcast::Sender<ScrapeBatch>,
+    pub batches: Arc<BatchesServiceImpl>,
+    pub webhooks: WebhookDispatcher,
 }
 
 #[derive(thiserror::Error, Debug)]
-pub enum ScrapingChannelError {}
+pub enum ScrapingChannelError {
+    #[error("Batch {0} not found")]
+    BatchNotFound(Uuid),
+    #[error("Couldn't load the batch : {0}")]
+    CouldntLoad(String),
+}
 
 impl EventHandler for ScrapingChannel {
-    type Input = ScrapeBatch;
+    type Input = BatchNotification;
     type Rejection = ScrapingChannelError;
 
     #[instrument(skip(self), err)]
     async fn handle(&self, input: Self::Input) -> Result<(), Self::Rejection> {
-        info!("FOUND NEW BATCH : {:#?}", input.scraped_at);
-        let _ = self.sender.send(input);
+        let batch = self
+            .batches
+            .get_batch(input.batch_id)
+            .await
+            .map_err(|e| ScrapingChannelError::CouldntLoad(e.to_string()))?
+            .ok_or(ScrapingChannelError::BatchNotFound(input.batch_id))?;
+        let _ = self.sender.send(batch.clone());
+        self.webhooks.dispatch(batch);
         Ok(())
     }
 }
@@ -0,0 +1,49 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use htc::{
+    models::health::{DEFAULT_HEALTH_LIMIT, HealthQuerySchema, RegionHealthSchema},
+    regions::CrousRegion,
+};
+
+use crate::{app::App, error::ApiError};
+
+const MAX_HEALTH_LIMIT: i64 = 100;
+
+#[utoipa::path(
+    get,
+    path = "/{region}/health",
+    params(
+        ("region" = String, Path, description = "Region of the health reports"),
+        HealthQuerySchema
+    ),
+    tag = "Health",
+    responses(
+        (status = 200, description = "Scraper health reports of the region, most recent first", body = [Vec<RegionHealthSchema>]),
+        (status = 404, description = "Unknown region"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn get_health<A>(
+    Path(region): Path<String>,
+    Query(query): Query<HealthQuerySchema>,
+    State(state): State<A>,
+) -> Result<Json<Vec<RegionHealthSchema>>, ApiError>
+where
+    A: App + Send + Sync + Clone,
+{
+    let region: CrousRegion = region
+        .parse()
+        .map_err(|_| ApiError::NotFound(format!("Unknown region: {}", region)))?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_HEALTH_LIMIT)
+        .clamp(1, MAX_HEALTH_LIMIT);
+
+    let health = state
+        .get_health(region, limit)
+        .await
+        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+    Ok(Json(health.into_iter().map(Into::into).collect()))
+}
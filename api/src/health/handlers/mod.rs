@@ -0,0 +1,2 @@
+pub mod get_health;
+pub mod put_health;
@@ -0,0 +1,88 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use htc::{
+    models::health::{HealthReportSchema, HealthStatus, RegionHealthSchema},
+    regions::CrousRegion,
+    verifiable::SignedPayload,
+};
+use tracing::{error, warn};
+
+use crate::{
+    app::App,
+    audit::trail::{AuditTrail, Provenance},
+    error::ApiError,
+};
+
+#[utoipa::path(
+    put,
+    path = "/{region}/health",
+    params(("region" = String, Path, description = "Region the pages were sampled from")),
+    tag = "Health",
+    request_body = SignedPayload<HealthReportSchema>,
+    responses(
+        (status = 201, description = "Health report recorded", body = RegionHealthSchema),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Unknown region"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn put_health<A>(
+    Path(region): Path<String>,
+    State(state): State<A>,
+    provenance: Provenance,
+    Json(body): Json<SignedPayload<HealthReportSchema>>,
+) -> Result<(StatusCode, Json<RegionHealthSchema>), ApiError>
+where
+    A: App + Send + Sync + Clone,
+{
+    let mut trail = AuditTrail::new("put_health", &body.author, provenance).region(&region);
+    let result = record_health(&state, region, body, &mut trail).await;
+    if let Err(e) = state.record_audit(trail.finish(&result)).await {
+        error!("Couldn't record audit entry : {}", e);
+    }
+    result
+}
+
+async fn record_health<A>(
+    state: &A,
+    region: String,
+    body: SignedPayload<HealthReportSchema>,
+    trail: &mut AuditTrail,
+) -> Result<(StatusCode, Json<RegionHealthSchema>), ApiError>
+where
+    A: App + Send + Sync + Clone,
+{
+    let region: CrousRegion = region
+        .parse()
+        .map_err(|_| ApiError::NotFound(format!("Unknown region: {}", region)))?;
+    let admin = state.get_admin(&body.author).await.map_err(|e| {
+        error!("{}", e.to_string());
+        ApiError::Unauthorized(e.to_string())
+    })?;
+    trail.admin(&admin);
+    let (payload, digest) = body.verify(admin.ssh_key.as_str()).map_err(|e| {
+        error!("{}", e.to_string());
+        ApiError::Unauthorized(e.to_string())
+    })?;
+    trail.digest(&digest);
+
+    if payload.status == HealthStatus::Drifted {
+        warn!(
+            "Layout drift in {} : {}",
+            region,
+            payload.drifted_selectors.join(", ")
+        );
+    }
+    let health = state
+        .record_health(region, payload.clone(), admin)
+        .await
+        .map_err(|e| {
+            error!("{}", e.to_string());
+            ApiError::InternalServerError(e.to_string())
+        })?;
+
+    Ok((StatusCode::CREATED, Json(health.into())))
+}
@@ -0,0 +1,18 @@
+use axum::{Router, routing::get};
+
+use crate::{
+    app::App,
+    health::handlers::{get_health::get_health, put_health::put_health},
+};
+
+pub fn health_router<A>(app: A) -> Router
+where
+    A: App + Send + Sync + Clone + 'static,
+{
+    Router::new()
+        .route(
+            "/{region}/health",
+            get(get_health::<A>).put(put_health::<A>),
+        )
+        .with_state(app)
+}
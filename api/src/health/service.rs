@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use htc::{
+    models::{
+        admins::Admin,
+        health::{HealthModel as _, HealthModelError, HealthReportSchema, RegionHealth},
+    },
+    regions::CrousRegion,
+};
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+pub trait HealthService {
+    fn record_health(
+        &self,
+        region: CrousRegion,
+        report: HealthReportSchema,
+        admin: Admin,
+    ) -> impl Future<Output = Result<RegionHealth, HealthModelError>> + Send;
+
+    fn get_health(
+        &self,
+        region: CrousRegion,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<RegionHealth>, HealthModelError>> + Send;
+}
+
+#[derive(Clone)]
+pub struct HealthServiceImpl {
+    pool: Arc<PgPool>,
+}
+
+impl HealthService for HealthServiceImpl {
+    #[instrument(skip(self, report, admin), fields(region=%region, status=%report.status), err)]
+    async fn record_health(
+        &self,
+        region: CrousRegion,
+        report: HealthReportSchema,
+        admin: Admin,
+    ) -> Result<RegionHealth, HealthModelError> {
+        let health = RegionHealth {
+            health_id: Uuid::new_v4(),
+            region: region.to_string(),
+            author: admin.admin_id,
+            checked_at: None,
+            report,
+        };
+        self.pool.record_health(health.clone()).await?;
+        Ok(health)
+    }
+
+    async fn get_health(
+        &self,
+        region: CrousRegion,
+        limit: i64,
+    ) -> Result<Vec<RegionHealth>, HealthModelError> {
+        self.pool.get_health(region.to_string(), limit).await
+    }
+}
+
+impl HealthServiceImpl {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
 use axum::{
     Router,
@@ -20,7 +20,12 @@ pub async fn serve(app: Router, config: Arc<Config>) -> Result<JoinHandle<()>, A
     info!("Starting server on 0.0.0.0:{}", config.port);
 
     Ok(tokio::spawn(async move {
-        axum::serve(listener, app).await.unwrap();
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
     }))
 }
 
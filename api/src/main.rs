@@ -6,28 +6,37 @@ use clap::Parser as _;
 use crate::{
     admins::service::{AdminService, AdminServiceImpl},
     app::AppImpl,
+    audit::service::AuditServiceImpl,
     batches::service::BatchesServiceImpl,
     config::Config,
     events::{EventListener, scraping_channel::ScrapingChannel},
+    health::service::HealthServiceImpl,
     meals::service::MealsServiceImpl,
     restaurants::service::RestaurantsServiceImpl,
     router::root,
     sse::SseState,
+    tokens::service::TokensServiceImpl,
     tracing::init_tracing_subscriber,
+    webhooks::{dispatcher::WebhookDispatcher, service::WebhooksServiceImpl},
 };
 
 pub mod admins;
 pub mod app;
+pub mod audit;
 pub mod batches;
 pub mod config;
 pub mod error;
 pub mod events;
+pub mod health;
 pub mod http;
 pub mod meals;
 pub mod restaurants;
 pub mod router;
 pub mod sse;
+pub mod tokens;
 pub mod tracing;
+pub mod webhooks;
+pub mod ws;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -44,6 +53,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let restaurants_service = RestaurantsServiceImpl::new(pool.clone(), batch_service.clone());
     let meals_service = MealsServiceImpl::new(pool.clone(), batch_service.clone());
     let admin_service = AdminServiceImpl::new(pool.clone());
+    let tokens_service = TokensServiceImpl::new(pool.clone());
+    let audit_service = AuditServiceImpl::new(pool.clone());
+    let webhooks_service = Arc::new(WebhooksServiceImpl::new(pool.clone()));
+    let health_service = HealthServiceImpl::new(pool.clone());
     let key = config.admin_public_key.clone();
 
     if !key.is_empty() {
@@ -59,17 +72,36 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         info!("No default key found");
     }
 
-    let (sse_state, sse_sender) = SseState::new(config.sse_token.clone());
+    let (sse_state, sse_sender) = SseState::new(
+        config.sse_token.clone(),
+        tokens_service.clone(),
+        batch_service.clone(),
+    );
     let sse_state = Arc::new(sse_state);
 
-    let event_handler = Arc::new(ScrapingChannel { sender: sse_sender });
-    let event_listener = EventListener::new(event_handler, pool.clone());
+    let webhook_dispatcher = WebhookDispatcher::new(webhooks_service.clone()).map_err(|e| {
+        error!("Failed to create the webhook client: {}", e);
+        e
+    })?;
+    webhook_dispatcher.run();
+    let event_handler = Arc::new(ScrapingChannel {
+        sender: sse_sender,
+        batches: batch_service.clone(),
+        webhooks: webhook_dispatcher,
+    });
+    let event_listener = EventListener::new(pool.clone())
+        .register("scraping_channel", "scraping", event_handler)
+        .replay_batches("scraping_channel");
 
     let app = AppImpl::new(
         restaurants_service,
         meals_service,
         admin_service,
         batch_service,
+        tokens_service,
+        audit_service,
+        webhooks_service,
+        health_service,
         config.clone(),
     );
     let root = root(app, sse_state).await.map_err(|e| {
@@ -81,13 +113,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .await
         .inspect_err(|e| error!("{}", e))?;
 
-    let listener_handle = event_listener
-        .listen("scraping_channel".to_string())
-        .await
-        .map_err(|e| {
-            error!("Failed to create listener: {}", e);
-            e
-        })?;
+    let listener_handle = event_listener.listen();
 
     let (http_result, listener_result) = tokio::join!(http_server, listener_handle);
     if let Err(e) = http_result {
@@ -4,46 +4,13 @@ use axum::{
     Json,
     extract::{Path, State},
 };
-use htc::{models::meals::MealSchema, regions::CrousRegion};
-use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use htc::{
+    models::meals::{MealModelError, MealSchema, MenuSchema},
+    regions::CrousRegion,
+};
 
 use crate::{app::App, error::ApiError};
 
-#[derive(Debug, Clone, ToSchema, Serialize, Deserialize)]
-pub struct MenuSchema {
-    date: String,
-    meals: Vec<MenuSectionSchema>,
-}
-
-#[derive(Debug, Clone, ToSchema, Serialize, Deserialize)]
-pub struct MenuSectionSchema {
-    meal_type: String,
-    foods: Vec<String>,
-}
-
-impl From<Vec<MealSchema>> for MenuSchema {
-    fn from(value: Vec<MealSchema>) -> Self {
-        let date = value
-            .first()
-            .and_then(|m| m.date.clone())
-            .unwrap_or_default();
-
-        let mut sections: HashMap<String, Vec<String>> = HashMap::new();
-        for meal in value {
-            let food = meal.foodies.unwrap_or_default();
-            sections.entry(meal.meal_type).or_default().push(food);
-        }
-
-        let meals = sections
-            .into_iter()
-            .map(|(meal_type, foods)| MenuSectionSchema { meal_type, foods })
-            .collect();
-
-        MenuSchema { date, meals }
-    }
-}
-
 #[utoipa::path(
     get,
     path = "/{region}/meals/{name}",
@@ -54,6 +21,7 @@ impl From<Vec<MealSchema>> for MenuSchema {
     tag = "Meals",
     responses(
         (status = 200, description = "List of meals for particular restaurant", body = [Vec<MenuSchema>]),
+        (status = 404, description = "Unknown region or no meals for this restaurant"),
         (status = 500, description = "Internal server error")
     )
 )]
@@ -70,7 +38,10 @@ where
     let meals = state
         .get_meals_by_restaurant_id(name, region)
         .await
-        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+        .map_err(|e| match e {
+            MealModelError::NotFound => ApiError::NotFound(e.to_string()),
+            _ => ApiError::InternalServerError(e.to_string()),
+        })?;
 
     let meal_schemas: Vec<MealSchema> = meals.into_iter().map(&MealSchema::from).collect();
 
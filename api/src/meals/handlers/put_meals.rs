@@ -2,11 +2,20 @@ use axum::{
     Json,
     extract::{Path, State},
 };
-use htc::{models::meals::MealSchema, regions::CrousRegion, verifiable::SignedPayload};
+use htc::{
+    models::meals::{MealModelError, MealSchema},
+    regions::CrousRegion,
+    validation::{ValidationRules, validate_meals},
+    verifiable::SignedPayload,
+};
 use reqwest::StatusCode;
-use tracing::error;
+use tracing::{error, warn};
 
-use crate::{app::App, error::ApiError};
+use crate::{
+    app::App,
+    audit::trail::{AuditTrail, Provenance},
+    error::ApiError,
+};
 
 #[utoipa::path(
     put,
@@ -16,14 +25,34 @@ use crate::{app::App, error::ApiError};
     responses(
         (status = 201, description = "Meals created"),
         (status = 401, description = "Unauthorized"),
+        (status = 409, description = "Same batch as the current one, sync skipped"),
+        (status = 422, description = "Empty body or batch failed validation"),
         (status = 500, description = "Internal server error")
     )
 )]
 pub async fn put_meals<A>(
     Path(region): Path<String>,
     State(state): State<A>,
+    provenance: Provenance,
     Json(body): Json<SignedPayload<Vec<MealSchema>>>,
 ) -> Result<StatusCode, ApiError>
+where
+    A: App + Send + Sync + Clone,
+{
+    let mut trail = AuditTrail::new("put_meals", &body.author, provenance).region(&region);
+    let result = save_meals(&state, region, body, &mut trail).await;
+    if let Err(e) = state.record_audit(trail.finish(&result)).await {
+        error!("Couldn't record audit entry : {}", e);
+    }
+    result
+}
+
+async fn save_meals<A>(
+    state: &A,
+    region: String,
+    body: SignedPayload<Vec<MealSchema>>,
+    trail: &mut AuditTrail,
+) -> Result<StatusCode, ApiError>
 where
     A: App + Send + Sync + Clone,
 {
@@ -34,18 +63,49 @@ where
         error!("{}", e.to_string());
         ApiError::Unauthorized(e.to_string())
     })?;
+    trail.admin(&admin);
     let user_key = admin.ssh_key.clone();
     let (payload, digest) = body.verify(&user_key).map_err(|e| {
         error!("{}", e.to_string());
         ApiError::Unauthorized(e.to_string())
     })?;
+    trail.digest(&digest);
+
+    let mut restaurant_ids: Vec<String> = payload
+        .iter()
+        .map(|meal| meal.restaurant_id.clone())
+        .collect();
+    restaurant_ids.sort();
+    restaurant_ids.dedup();
+    let current: Vec<MealSchema> = match state.get_current_meals(&restaurant_ids, region).await {
+        Ok(meals) => meals.iter().map(MealSchema::from).collect(),
+        Err(e) => {
+            warn!("Couldn't load the current meals : {}", e);
+            Vec::new()
+        }
+    };
+    let today = chrono::Local::now().date_naive();
+    let report = validate_meals(payload, &current, today, &ValidationRules::default());
+    for warning in report.warnings() {
+        warn!("{}", warning);
+    }
+    if report.is_blocking() {
+        return Err(ApiError::UnProcessableEntity(format!(
+            "Batch failed validation : {}",
+            report
+        )));
+    }
 
     state
         .save_meals(payload, admin, region, digest)
         .await
         .map_err(|e| {
             error!("{}", e.to_string());
-            ApiError::InternalServerError(e.to_string())
+            match e {
+                MealModelError::SyncSkipped => ApiError::Conflict,
+                MealModelError::EmptyBody => ApiError::UnProcessableEntity(e.to_string()),
+                _ => ApiError::InternalServerError(e.to_string()),
+            }
         })?;
 
     Ok(StatusCode::CREATED)
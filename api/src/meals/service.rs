@@ -4,8 +4,8 @@ use htc::{
     models::{
         Entity,
         admins::Admin,
-        meals::{Meal, MealModel as _, MealModelError, MealSchema},
-        scrape_batch::ScrapedBatchModelError,
+        meals::{Meal, MealModel as _, MealModelError, MealSchema, menu_index},
+        scrape_batch::{BatchSummary, ScrapedBatchModelError},
     },
     regions::CrousRegion,
 };
@@ -28,6 +28,11 @@ pub trait MealsService {
         name: String,
         region: CrousRegion,
     ) -> impl Future<Output = Result<Vec<Meal>, MealModelError>> + Send;
+    fn get_current_meals(
+        &self,
+        restaurant_ids: &[String],
+        region: CrousRegion,
+    ) -> impl Future<Output = Result<Vec<Meal>, MealModelError>> + Send;
 }
 
 #[derive(Clone)]
@@ -53,6 +58,20 @@ impl MealsService for MealsServiceImpl<BatchesServiceImpl> {
         };
 
         let restaurant_id = first_meal.restaurant_id.clone();
+        let previous: Vec<MealSchema> = match self
+            .get_meals_by_restaurant_id(restaurant_id.clone(), region)
+            .await
+        {
+            Ok(previous) => previous.iter().map(MealSchema::from).collect(),
+            Err(MealModelError::NotFound) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let notices = meals.iter().filter_map(|meal| {
+            Some((meal.date.clone().unwrap_or_default(), meal.status.as_ref()?))
+        });
+        let summary =
+            BatchSummary::diff(&menu_index(&previous), &menu_index(meals)).with_notices(notices);
+
         let (batch, mut tx) = self
             .batch_service
             .create_batch(
@@ -60,6 +79,7 @@ impl MealsService for MealsServiceImpl<BatchesServiceImpl> {
                 admin.admin_id,
                 region,
                 checksum,
+                Some(summary),
             )
             .await
             .map_err(|e| match e {
@@ -76,6 +96,7 @@ impl MealsService for MealsServiceImpl<BatchesServiceImpl> {
                 date: schema.date.clone(),
                 restaurant_id: schema.restaurant_id.clone(),
                 batch_id: batch,
+                status: schema.status.clone(),
             })
             .collect();
 
@@ -106,6 +127,16 @@ impl MealsService for MealsServiceImpl<BatchesServiceImpl> {
             .get_meals_by_restaurant_id_batch(name, current_batch.batch_id)
             .await
     }
+
+    async fn get_current_meals(
+        &self,
+        restaurant_ids: &[String],
+        region: CrousRegion,
+    ) -> Result<Vec<Meal>, MealModelError> {
+        self.pool
+            .get_current_meals_by_restaurant_ids(restaurant_ids, region)
+            .await
+    }
 }
 
 impl<B> MealsServiceImpl<B>
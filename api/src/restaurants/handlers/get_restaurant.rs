@@ -0,0 +1,47 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use chrono::Local;
+use htc::{
+    models::restaurants::{RestaurantModelError, RestaurantSchema},
+    regions::CrousRegion,
+};
+
+use crate::{app::App, error::ApiError};
+
+#[utoipa::path(
+    get,
+    path = "/{region}/restaurants/{restaurant_id}",
+    params(
+        ("region" = String, Path, description = "Region of the restaurant"),
+        ("restaurant_id" = String, Path, description = "Restaurant id")
+    ),
+    tag = "Restaurants",
+    responses(
+        (status = 200, description = "Restaurant", body = RestaurantSchema),
+        (status = 404, description = "Unknown region, or no such restaurant in it"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn get_restaurant<A>(
+    Path((region, restaurant_id)): Path<(String, String)>,
+    State(state): State<A>,
+) -> Result<Json<RestaurantSchema>, ApiError>
+where
+    A: App + Send + Sync + Clone,
+{
+    let region: CrousRegion = region
+        .parse()
+        .map_err(|_| ApiError::NotFound(format!("Unknown region: {}", region)))?;
+    let restaurant = state
+        .get_restaurant_by_id(restaurant_id, region)
+        .await
+        .map_err(|e| match e {
+            RestaurantModelError::NotFound => ApiError::NotFound(e.to_string()),
+            _ => ApiError::InternalServerError(e.to_string()),
+        })?;
+    Ok(Json(
+        RestaurantSchema::from(restaurant).on(Local::now().date_naive()),
+    ))
+}
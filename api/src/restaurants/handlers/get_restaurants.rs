@@ -1,22 +1,32 @@
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
+};
+use chrono::Local;
+use htc::{
+    models::restaurants::{RestaurantModelError, RestaurantQuerySchema, RestaurantSchema},
+    regions::CrousRegion,
 };
-use htc::{models::restaurants::RestaurantSchema, regions::CrousRegion};
 
 use crate::{app::App, error::ApiError};
 
 #[utoipa::path(
     get,
     path = "/{region}/restaurants",
+    params(
+        ("region" = String, Path, description = "Region of the restaurants"),
+        RestaurantQuerySchema
+    ),
     tag = "Restaurants",
     responses(
         (status = 200, description = "List of restaurants", body = [Vec<RestaurantSchema>]),
+        (status = 404, description = "Unknown region or no restaurants scraped yet"),
         (status = 500, description = "Internal server error")
     )
 )]
 pub async fn get_restaurants<A>(
     Path(region): Path<String>,
+    Query(query): Query<RestaurantQuerySchema>,
     State(state): State<A>,
 ) -> Result<Json<Vec<RestaurantSchema>>, ApiError>
 where
@@ -25,13 +35,15 @@ where
     let region: CrousRegion = region
         .parse()
         .map_err(|_| ApiError::NotFound(format!("Unknown region: {}", region)))?;
-    let restaurants = state
-        .get_restaurants(region)
-        .await
-        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+    let restaurants = state.get_restaurants(region).await.map_err(|e| match e {
+        RestaurantModelError::NotFound => ApiError::NotFound(e.to_string()),
+        _ => ApiError::InternalServerError(e.to_string()),
+    })?;
+    let today = Local::now().date_naive();
     let restaurants: Vec<RestaurantSchema> = restaurants
         .into_iter()
-        .map(&RestaurantSchema::from)
+        .map(|restaurant| RestaurantSchema::from(restaurant).on(today))
+        .filter(|restaurant| query.matches(restaurant))
         .collect();
     Ok(Json(restaurants))
 }
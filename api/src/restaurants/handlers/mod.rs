@@ -1,2 +1,3 @@
+pub mod get_restaurant;
 pub mod get_restaurants;
 pub mod put_restaurants;
@@ -2,10 +2,15 @@ use axum::extract::Path;
 use axum::{Json, extract::State, http::StatusCode};
 use htc::models::restaurants::RestaurantSchema;
 use htc::regions::CrousRegion;
+use htc::validation::{ValidationRules, validate_restaurants};
 use htc::verifiable::SignedPayload;
-use tracing::error;
+use tracing::{error, warn};
 
-use crate::{app::App, error::ApiError};
+use crate::{
+    app::App,
+    audit::trail::{AuditTrail, Provenance},
+    error::ApiError,
+};
 
 #[utoipa::path(
     put,
@@ -14,14 +19,35 @@ use crate::{app::App, error::ApiError};
     request_body = SignedPayload<Vec<RestaurantSchema>>,
     responses(
         (status = 201, description = "Restaurants created"),
+        (status = 401, description = "Unauthorized"),
+        (status = 409, description = "Same batch as the current one, sync skipped"),
+        (status = 422, description = "Batch failed validation"),
         (status = 500, description = "Internal server error")
     )
 )]
 pub async fn put_restaurant<A>(
     Path(region): Path<String>,
     State(state): State<A>,
+    provenance: Provenance,
     Json(body): Json<SignedPayload<Vec<RestaurantSchema>>>,
 ) -> Result<StatusCode, ApiError>
+where
+    A: App + Send + Sync + Clone,
+{
+    let mut trail = AuditTrail::new("put_restaurants", &body.author, provenance).region(&region);
+    let result = save_restaurants(&state, region, body, &mut trail).await;
+    if let Err(e) = state.record_audit(trail.finish(&result)).await {
+        error!("Couldn't record audit entry : {}", e);
+    }
+    result
+}
+
+async fn save_restaurants<A>(
+    state: &A,
+    region: String,
+    body: SignedPayload<Vec<RestaurantSchema>>,
+    trail: &mut AuditTrail,
+) -> Result<StatusCode, ApiError>
 where
     A: App + Send + Sync + Clone,
 {
@@ -32,10 +58,33 @@ where
         error!("{}", e.to_string());
         ApiError::Unauthorized(e.to_string())
     })?;
+    trail.admin(&admin);
     let (payload, digest) = body.verify(admin.ssh_key.as_str()).map_err(|e| {
         error!("{}", e.to_string());
         ApiError::Unauthorized(e.to_string())
     })?;
+    trail.digest(&digest);
+
+    let current: Vec<RestaurantSchema> = match state.get_restaurants(region).await {
+        Ok(restaurants) => restaurants.iter().map(RestaurantSchema::from).collect(),
+        Err(e) => {
+            warn!(
+                "Couldn't load the current restaurants, validating without : {}",
+                e
+            );
+            Vec::new()
+        }
+    };
+    let report = validate_restaurants(region, payload, &current, &ValidationRules::default());
+    for warning in report.warnings() {
+        warn!("{}", warning);
+    }
+    if report.is_blocking() {
+        return Err(ApiError::UnProcessableEntity(format!(
+            "Batch failed validation : {}",
+            report
+        )));
+    }
 
     state
         .save_restaurants(payload, admin, region, digest)
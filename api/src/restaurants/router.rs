@@ -5,7 +5,10 @@ use axum::{
 
 use crate::{
     app::App,
-    restaurants::handlers::{get_restaurants::get_restaurants, put_restaurants::put_restaurant},
+    restaurants::handlers::{
+        get_restaurant::get_restaurant, get_restaurants::get_restaurants,
+        put_restaurants::put_restaurant,
+    },
 };
 
 pub fn restaurants_router<A>(app: A) -> Router
@@ -15,5 +18,9 @@ where
     Router::new()
         .route("/{region}/restaurants", get(get_restaurants::<A>))
         .route("/{region}/restaurants", put(put_restaurant::<A>))
+        .route(
+            "/{region}/restaurants/{restaurant_id}",
+            get(get_restaurant::<A>),
+        )
         .with_state(app)
 }
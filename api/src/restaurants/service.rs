@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::future::Future;
 use std::sync::Arc;
 
@@ -7,7 +8,7 @@ use htc::{
         Entity,
         admins::Admin,
         restaurants::{Restaurant, RestaurantModel as _, RestaurantModelError, RestaurantSchema},
-        scrape_batch::ScrapedBatchModelError,
+        scrape_batch::{BatchSummary, ScrapedBatchModelError},
     },
     regions::CrousRegion,
 };
@@ -27,6 +28,7 @@ pub trait RestaurantsService {
     fn get_restaurant_by_id(
         &self,
         id: String,
+        region: CrousRegion,
     ) -> impl Future<Output = Result<Restaurant, RestaurantModelError>> + Send;
     fn get_restaurants(
         &self,
@@ -52,9 +54,16 @@ impl RestaurantsService for RestaurantsServiceImpl<BatchesServiceImpl> {
         region: CrousRegion,
         checksum: String,
     ) -> Result<(), RestaurantModelError> {
+        let summary = self.summarize(restaurants, region).await?;
         let (batch, mut tx) = self
             .batch_service
-            .create_batch(Entity::Restaurants, admin.admin_id, region, checksum)
+            .create_batch(
+                Entity::Restaurants,
+                admin.admin_id,
+                region,
+                checksum,
+                Some(summary),
+            )
             .await
             .map_err(|e| -> RestaurantModelError {
                 match e {
@@ -71,8 +80,17 @@ impl RestaurantsService for RestaurantsServiceImpl<BatchesServiceImpl> {
                 name: schema.name.clone(),
                 url: schema.url.clone(),
                 city: schema.city.clone(),
+                venue_type: schema.venue_type,
                 coordinates: schema.coordinates.clone(),
                 opening_hours: schema.opening_hours.clone(),
+                address: schema.address.clone(),
+                phone: schema.phone.clone(),
+                payment_methods: schema.payment_methods.clone(),
+                amenities: schema.amenities.clone(),
+                accessible: schema.accessible,
+                access: schema.access.clone(),
+                photos: schema.photos.clone(),
+                status: schema.status.clone(),
                 created_at: None,
                 updated_at: None,
                 batch_id: batch,
@@ -89,8 +107,12 @@ impl RestaurantsService for RestaurantsServiceImpl<BatchesServiceImpl> {
         Ok(())
     }
 
-    async fn get_restaurant_by_id(&self, id: String) -> Result<Restaurant, RestaurantModelError> {
-        self.pool.get_restaurant_by_id(id).await
+    async fn get_restaurant_by_id(
+        &self,
+        id: String,
+        region: CrousRegion,
+    ) -> Result<Restaurant, RestaurantModelError> {
+        self.pool.get_restaurant_by_id(id, region).await
     }
 
     async fn get_restaurants(
@@ -111,6 +133,40 @@ impl RestaurantsService for RestaurantsServiceImpl<BatchesServiceImpl> {
     }
 }
 
+impl RestaurantsServiceImpl<BatchesServiceImpl> {
+    /// Compares the pushed restaurants with the current batch of the region.
+    async fn summarize(
+        &self,
+        restaurants: &[RestaurantSchema],
+        region: CrousRegion,
+    ) -> Result<BatchSummary, RestaurantModelError> {
+        let previous = match self.get_restaurants(region).await {
+            Ok(previous) => previous,
+            Err(RestaurantModelError::NotFound) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let previous: HashMap<String, RestaurantSchema> = previous
+            .into_iter()
+            .map(|restaurant| (restaurant.restaurant_id.clone(), restaurant.into()))
+            .collect();
+        let current: HashMap<String, RestaurantSchema> = restaurants
+            .iter()
+            .map(|restaurant| {
+                let id = build_id(&restaurant.name);
+                let restaurant = RestaurantSchema {
+                    id: id.clone(),
+                    ..restaurant.clone()
+                };
+                (id, restaurant)
+            })
+            .collect();
+        let notices = current
+            .iter()
+            .map(|(id, restaurant)| (id.clone(), &restaurant.status));
+        Ok(BatchSummary::diff(&previous, &current).with_notices(notices))
+    }
+}
+
 impl<B> RestaurantsServiceImpl<B>
 where
     B: BatchesService,
@@ -1,14 +1,23 @@
 use std::sync::Arc;
 
-use axum::Router;
-use tower_http::trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer};
-use tracing::Level;
+use axum::{
+    Extension, Router,
+    http::{Request, Uri},
+};
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tracing::{Level, Span};
 use utoipa::OpenApi;
 use utoipa_scalar::{Scalar, Servable};
 
 use crate::{
     app::App,
+    audit::{handlers::get_audit::__path_get_audit, router::audit_router, trail::TrustedProxies},
+    batches::{handlers::get_batches::__path_get_batches, router::batches_router},
     error::ApiError,
+    health::{
+        handlers::{get_health::__path_get_health, put_health::__path_put_health},
+        router::health_router,
+    },
     http::default_cors_layer,
     meals::{
         handlers::get_meals::__path_get_meals, handlers::put_meals::__path_put_meals,
@@ -16,17 +25,48 @@ use crate::{
     },
     restaurants::{
         handlers::{
-            get_restaurants::__path_get_restaurants, put_restaurants::__path_put_restaurant,
+            get_restaurant::__path_get_restaurant, get_restaurants::__path_get_restaurants,
+            put_restaurants::__path_put_restaurant,
         },
         router::restaurants_router,
     },
     sse::{SseState, sse_router},
+    tokens::{
+        handlers::{delete_tokens::__path_delete_tokens, post_tokens::__path_post_tokens},
+        router::tokens_router,
+    },
+    webhooks::{
+        handlers::{
+            delete_webhooks::__path_delete_webhooks, get_deliveries::__path_get_deliveries,
+            get_webhooks::__path_get_webhooks, post_webhooks::__path_post_webhooks,
+            put_webhook_enabled::__path_put_webhook_enabled,
+        },
+        router::webhooks_router,
+    },
+    ws::ws_router,
 };
 
 #[derive(OpenApi)]
 #[openapi(
     info(title = "Hack The Crous API"),
-    paths(put_restaurant, get_restaurants, put_meals, get_meals)
+    paths(
+        put_restaurant,
+        get_restaurants,
+        get_restaurant,
+        put_meals,
+        get_meals,
+        post_tokens,
+        delete_tokens,
+        get_audit,
+        get_batches,
+        post_webhooks,
+        get_webhooks,
+        delete_webhooks,
+        put_webhook_enabled,
+        get_deliveries,
+        put_health,
+        get_health
+    )
 )]
 pub struct ApiDoc;
 
@@ -35,16 +75,49 @@ where
     A: App + Send + Sync + Clone + 'static,
 {
     let origins = app.clone().config().origins.clone();
+    let trusted_proxies = TrustedProxies(Arc::new(app.config().trusted_proxies.clone()));
     let openapi = ApiDoc::openapi();
     Ok(Router::new()
         .merge(Scalar::with_url("/docs", openapi))
         .merge(restaurants_router(app.clone()))
-        .merge(meals_router(app))
-        .merge(sse_router(sse_state))
+        .merge(meals_router(app.clone()))
+        .merge(tokens_router(app.clone()))
+        .merge(batches_router(app.clone()))
+        .merge(webhooks_router(app.clone()))
+        .merge(health_router(app.clone()))
+        .merge(audit_router(app))
+        .merge(sse_router(sse_state.clone()))
+        .merge(ws_router(sse_state))
+        .layer(Extension(trusted_proxies))
         .layer(default_cors_layer(&origins)?)
         .layer(
             TraceLayer::new_for_http()
-                .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
+                .make_span_with(request_span)
                 .on_response(DefaultOnResponse::new().level(Level::INFO)),
         ))
 }
+
+/// Span of a request, the `token` a WebSocket handshake passes in the query
+/// left out of the logs.
+fn request_span<B>(request: &Request<B>) -> Span {
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %redacted_uri(request.uri()),
+        version = ?request.version(),
+    )
+}
+
+fn redacted_uri(uri: &Uri) -> String {
+    let Some(query) = uri.query() else {
+        return uri.to_string();
+    };
+    let query: Vec<&str> = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some(("token", _)) => "token=[redacted]",
+            _ => pair,
+        })
+        .collect();
+    format!("{}?{}", uri.path(), query.join("&"))
+}
@@ -1,58 +1,286 @@
-use std::{convert::Infallible, sync::Arc};
+use std::{collections::VecDeque, convert::Infallible, sync::Arc, time::Duration};
 
 use axum::{
     Router,
-    extract::State,
+    extract::{Query, State},
     http::{HeaderMap, StatusCode, header},
     response::{
         IntoResponse, Response,
-        sse::{Event, Sse},
+        sse::{Event, KeepAlive, Sse},
     },
     routing::get,
 };
-use htc::models::scrape_batch::ScrapeBatch;
-use tokio::sync::broadcast;
-use tokio_stream::{StreamExt, wrappers::BroadcastStream};
+use futures::stream;
+use htc::models::{
+    scrape_batch::{EventFilterSchema, ScrapeBatch, ScrapedBatchModelError},
+    tokens::TokenScope,
+};
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    time::{Instant, Interval, interval_at},
+};
+use tracing::{error, info};
+
+use crate::{
+    batches::service::{BatchesService, BatchesServiceImpl},
+    error::ApiError,
+    tokens::service::{TokensService, TokensServiceImpl},
+};
+
+/// Batches fetched per query while replaying after a `Last-Event-ID`.
+const REPLAY_PAGE_SIZE: i64 = 100;
+/// How often an open stream checks its token again, so a revoked or expired
+/// token doesn't keep receiving batches.
+pub const REAUTHORIZE_INTERVAL: Duration = Duration::from_secs(30);
 
 pub struct SseState {
     sender: broadcast::Sender<ScrapeBatch>,
     token: String,
+    tokens: TokensServiceImpl,
+    batches: Arc<BatchesServiceImpl>,
 }
 
 impl SseState {
-    pub fn new(token: String) -> (Self, broadcast::Sender<ScrapeBatch>) {
+    pub fn new(
+        token: String,
+        tokens: TokensServiceImpl,
+        batches: Arc<BatchesServiceImpl>,
+    ) -> (Self, broadcast::Sender<ScrapeBatch>) {
         let (sender, _) = broadcast::channel(100);
         (
             Self {
                 sender: sender.clone(),
                 token,
+                tokens,
+                batches,
             },
             sender,
         )
     }
+
+    /// Resolves a subscriber token to the scope it grants. The static token
+    /// from the config keeps an unrestricted access.
+    pub async fn authorize_token(&self, token: &str) -> Option<TokenScope> {
+        if token == self.token {
+            return Some(TokenScope::default());
+        }
+
+        self.tokens
+            .authenticate(token)
+            .await
+            .inspect_err(|e| info!("Rejected subscriber token : {}", e))
+            .ok()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ScrapeBatch> {
+        self.sender.subscribe()
+    }
 }
 
-async fn sse_handler(State(state): State<Arc<SseState>>, headers: HeaderMap) -> Response {
-    let authorized = headers
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
         .get(header::AUTHORIZATION)
         .and_then(|v| v.to_str().ok())
         .and_then(|v| v.strip_prefix("Bearer "))
-        .map(|t| t == state.token)
-        .unwrap_or(false);
+}
+
+/// Ticks every `REAUTHORIZE_INTERVAL`, the first time one interval from now.
+pub fn reauthorizations() -> Interval {
+    interval_at(Instant::now() + REAUTHORIZE_INTERVAL, REAUTHORIZE_INTERVAL)
+}
+
+fn batch_event(batch: &ScrapeBatch) -> Option<Event> {
+    let json = serde_json::to_string(batch).ok()?;
+    let event = Event::default().data(json);
+    Some(match batch.seq {
+        Some(seq) => event.id(seq.to_string()),
+        None => event,
+    })
+}
+
+/// Tells a subscriber it missed `missed` batches, with the last seq it got
+/// so it keeps its place.
+fn lagged_event(missed: u64, last_seq: i64) -> Event {
+    let event = Event::default()
+        .event("lagged")
+        .data(serde_json::json!({ "missed": missed, "last_seq": last_seq }).to_string());
+    if last_seq > 0 {
+        event.id(last_seq.to_string())
+    } else {
+        event
+    }
+}
+
+/// Events of one subscriber: first the batches missed since `Last-Event-ID`
+/// read back from `scrape_batch`, then the live ones. Live batches already
+/// sent by the replay are skipped using their sequence.
+struct EventCursor {
+    state: Arc<SseState>,
+    token: String,
+    reauthorizations: Interval,
+    scope: TokenScope,
+    filter: EventFilterSchema,
+    receiver: broadcast::Receiver<ScrapeBatch>,
+    replay_after: Option<i64>,
+    last_seq: i64,
+    pending: VecDeque<Event>,
+}
+
+impl EventCursor {
+    /// Starts after `Last-Event-ID`, or after the last batch committed so
+    /// far, so a lag before the first live batch still knows where to
+    /// catch up from.
+    async fn new(
+        state: Arc<SseState>,
+        token: String,
+        scope: TokenScope,
+        filter: EventFilterSchema,
+        last_event_id: Option<i64>,
+    ) -> Result<Self, ScrapedBatchModelError> {
+        let last_seq = match last_event_id {
+            Some(last_event_id) => last_event_id,
+            None => state.batches.last_seq().await?,
+        };
+        // subscribe before replaying so nothing committed meanwhile is lost
+        let receiver = state.subscribe();
+        Ok(EventCursor {
+            state,
+            token,
+            reauthorizations: reauthorizations(),
+            scope,
+            filter,
+            receiver,
+            replay_after: Some(last_seq),
+            last_seq,
+            pending: VecDeque::new(),
+        })
+    }
+
+    fn push(&mut self, batch: &ScrapeBatch) {
+        if let Some(seq) = batch.seq {
+            self.last_seq = self.last_seq.max(seq);
+        }
+        if self.scope.allows(batch)
+            && self.filter.matches(batch)
+            && let Some(event) = batch_event(batch)
+        {
+            self.pending.push_back(event);
+        }
+    }
+
+    /// `false` when the database couldn't be read, the stream is then
+    /// closed so the client reconnects with its `Last-Event-ID`.
+    async fn replay(&mut self, after: i64) -> bool {
+        match self
+            .state
+            .batches
+            .get_batches_since(after, REPLAY_PAGE_SIZE)
+            .await
+        {
+            Ok(batches) => {
+                self.replay_after = (batches.len() as i64 == REPLAY_PAGE_SIZE)
+                    .then(|| batches.last().and_then(|batch| batch.seq))
+                    .flatten();
+                for batch in &batches {
+                    self.push(batch);
+                }
+                true
+            }
+            Err(e) => {
+                error!("Couldn't replay batches after {} : {}", after, e);
+                false
+            }
+        }
+    }
+
+    async fn next(&mut self) -> Option<Event> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
 
-    if !authorized {
+            if let Some(after) = self.replay_after {
+                if !self.replay(after).await {
+                    return None;
+                }
+                continue;
+            }
+
+            let received = tokio::select! {
+                received = self.receiver.recv() => received,
+                _ = self.reauthorizations.tick() => {
+                    if self.state.authorize_token(&self.token).await.is_none() {
+                        info!("Closing the stream of a rejected token");
+                        return None;
+                    }
+                    continue;
+                }
+            };
+            match received {
+                Ok(batch) => {
+                    if batch.seq.is_some_and(|seq| seq <= self.last_seq) {
+                        continue;
+                    }
+                    self.push(&batch);
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    // tell the subscriber, then catch up from the database
+                    self.pending.push_back(lagged_event(missed, self.last_seq));
+                    self.replay_after = Some(self.last_seq);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+fn last_event_id(headers: &HeaderMap) -> Option<i64> {
+    headers
+        .get("Last-Event-ID")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+}
+
+async fn sse_handler(
+    State(state): State<Arc<SseState>>,
+    Query(filter): Query<EventFilterSchema>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(token) = bearer_token(&headers).map(str::to_string) else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let Some(scope) = state.authorize_token(&token).await else {
         return StatusCode::UNAUTHORIZED.into_response();
+    };
+    if let Err(e) = filter.validate() {
+        return ApiError::BadRequest(e.to_string()).into_response();
     }
 
-    let rx = state.sender.subscribe();
-    let stream = BroadcastStream::new(rx).filter_map(|result| match result {
-        Ok(batch) => serde_json::to_string(&batch)
-            .ok()
-            .map(|json| Ok::<Event, Infallible>(Event::default().data(json))),
-        Err(_) => None,
+    let cursor = match EventCursor::new(
+        state.clone(),
+        token,
+        scope,
+        filter,
+        last_event_id(&headers),
+    )
+    .await
+    {
+        Ok(cursor) => cursor,
+        Err(e) => {
+            error!("Couldn't read the last batch seq : {}", e);
+            return ApiError::InternalServerError(e.to_string()).into_response();
+        }
+    };
+    let stream = stream::unfold(cursor, |mut cursor| async move {
+        cursor
+            .next()
+            .await
+            .map(|event| (Ok::<Event, Infallible>(event), cursor))
     });
 
-    Sse::new(stream).into_response()
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
 }
 
 pub fn sse_router(state: Arc<SseState>) -> Router {
@@ -60,3 +288,69 @@ pub fn sse_router(state: Arc<SseState>) -> Router {
         .route("/events", get(sse_handler))
         .with_state(state)
 }
+
+#[cfg(test)]
+mod tests {
+    use htc::models::{Entity, scrape_batch::ScrapedBatchModel as _};
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{batches::service::BatchesServiceImpl, tokens::service::TokensServiceImpl};
+
+    fn lazy_pool() -> Arc<PgPool> {
+        dotenv::dotenv().ok();
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL is needed by the tests");
+        Arc::new(PgPool::connect_lazy(&url).unwrap())
+    }
+
+    fn make_batch(seq: i64) -> ScrapeBatch {
+        ScrapeBatch {
+            batch_id: Uuid::nil(),
+            entity: Entity::Restaurants,
+            author: Uuid::nil(),
+            region: "Montpellier".to_string(),
+            scraped_at: None,
+            checksum: "checksum".to_string(),
+            seq: Some(seq),
+            summary: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lag_before_the_first_batch_keeps_the_seq() {
+        let pool = lazy_pool();
+        let (state, sender) = SseState::new(
+            "token".to_string(),
+            TokensServiceImpl::new(pool.clone()),
+            Arc::new(BatchesServiceImpl::new(pool.clone())),
+        );
+        let mut cursor = EventCursor::new(
+            Arc::new(state),
+            "token".to_string(),
+            TokenScope::default(),
+            EventFilterSchema::default(),
+            None,
+        )
+        .await
+        .unwrap();
+        let last_seq = pool.last_seq().await.unwrap();
+        assert_eq!(cursor.last_seq, last_seq);
+
+        // catch up with what was committed before subscribing
+        assert!(cursor.replay(last_seq).await);
+        cursor.pending.clear();
+
+        // one more than the channel holds, its capacity of 100 is rounded
+        // up to 128, so the first receive lags
+        for seq in 1..=129 {
+            sender.send(make_batch(last_seq + seq)).unwrap();
+        }
+        let event = cursor.next().await.unwrap();
+        assert_eq!(
+            format!("{:?}", event),
+            format!("{:?}", lagged_event(1, last_seq))
+        );
+        assert_eq!(cursor.replay_after, Some(last_seq));
+    }
+}
@@ -0,0 +1,87 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use htc::{
+    models::tokens::{TokenModelError, TokenRevocationSchema},
+    verifiable::SignedPayload,
+};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    app::App,
+    audit::trail::{AuditTrail, Provenance},
+    error::ApiError,
+};
+
+#[utoipa::path(
+    delete,
+    path = "/tokens/{token_id}",
+    params(
+        ("token_id" = String, Path, description = "Identifier of the subscriber token")
+    ),
+    tag = "Tokens",
+    request_body = SignedPayload<TokenRevocationSchema>,
+    responses(
+        (status = 204, description = "Subscriber token revoked"),
+        (status = 400, description = "Signed token id doesn't match the path"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Token not found or already revoked"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn delete_tokens<A>(
+    Path(token_id): Path<Uuid>,
+    State(state): State<A>,
+    provenance: Provenance,
+    Json(body): Json<SignedPayload<TokenRevocationSchema>>,
+) -> Result<StatusCode, ApiError>
+where
+    A: App + Send + Sync + Clone,
+{
+    let mut trail = AuditTrail::new("revoke_token", &body.author, provenance);
+    let result = revoke_token(&state, token_id, body, &mut trail).await;
+    if let Err(e) = state.record_audit(trail.finish(&result)).await {
+        error!("Couldn't record audit entry : {}", e);
+    }
+    result
+}
+
+async fn revoke_token<A>(
+    state: &A,
+    token_id: Uuid,
+    body: SignedPayload<TokenRevocationSchema>,
+    trail: &mut AuditTrail,
+) -> Result<StatusCode, ApiError>
+where
+    A: App + Send + Sync + Clone,
+{
+    let admin = state.get_admin(&body.author).await.map_err(|e| {
+        error!("{}", e.to_string());
+        ApiError::Unauthorized(e.to_string())
+    })?;
+    trail.admin(&admin);
+    let (payload, digest) = body.verify(admin.ssh_key.as_str()).map_err(|e| {
+        error!("{}", e.to_string());
+        ApiError::Unauthorized(e.to_string())
+    })?;
+    trail.digest(&digest);
+
+    if payload.token_id != token_id {
+        return Err(ApiError::BadRequest(
+            "Signed token id doesn't match the path".to_string(),
+        ));
+    }
+
+    state.revoke_token(token_id).await.map_err(|e| {
+        error!("{}", e.to_string());
+        match e {
+            TokenModelError::NotFound => ApiError::NotFound(e.to_string()),
+            _ => ApiError::InternalServerError(e.to_string()),
+        }
+    })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
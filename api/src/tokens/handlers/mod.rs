@@ -0,0 +1,2 @@
+pub mod delete_tokens;
+pub mod post_tokens;
@@ -0,0 +1,78 @@
+use axum::{Json, extract::State, http::StatusCode};
+use chrono::Utc;
+use htc::{
+    models::tokens::{IssuedTokenSchema, TokenModelError, TokenRequestSchema},
+    verifiable::{SignedPayload, check_issued_at},
+};
+use tracing::error;
+
+use crate::{
+    app::App,
+    audit::trail::{AuditTrail, Provenance},
+    error::ApiError,
+};
+
+#[utoipa::path(
+    post,
+    path = "/tokens",
+    tag = "Tokens",
+    request_body = SignedPayload<TokenRequestSchema>,
+    responses(
+        (status = 201, description = "Subscriber token issued, the secret is only returned once", body = IssuedTokenSchema),
+        (status = 400, description = "Invalid token scope"),
+        (status = 401, description = "Unauthorized, or the signed request is stale"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn post_tokens<A>(
+    State(state): State<A>,
+    provenance: Provenance,
+    Json(body): Json<SignedPayload<TokenRequestSchema>>,
+) -> Result<(StatusCode, Json<IssuedTokenSchema>), ApiError>
+where
+    A: App + Send + Sync + Clone,
+{
+    let mut trail = AuditTrail::new("issue_token", &body.author, provenance);
+    let result = issue_token(&state, body, &mut trail).await;
+    if let Err(e) = state.record_audit(trail.finish(&result)).await {
+        error!("Couldn't record audit entry : {}", e);
+    }
+    result
+}
+
+async fn issue_token<A>(
+    state: &A,
+    body: SignedPayload<TokenRequestSchema>,
+    trail: &mut AuditTrail,
+) -> Result<(StatusCode, Json<IssuedTokenSchema>), ApiError>
+where
+    A: App + Send + Sync + Clone,
+{
+    let admin = state.get_admin(&body.author).await.map_err(|e| {
+        error!("{}", e.to_string());
+        ApiError::Unauthorized(e.to_string())
+    })?;
+    trail.admin(&admin);
+    let (payload, digest) = body.verify(admin.ssh_key.as_str()).map_err(|e| {
+        error!("{}", e.to_string());
+        ApiError::Unauthorized(e.to_string())
+    })?;
+    trail.digest(&digest);
+    check_issued_at(payload.issued_at, Utc::now().timestamp()).map_err(|e| {
+        error!("{}", e.to_string());
+        ApiError::Unauthorized(e.to_string())
+    })?;
+
+    let token = state
+        .issue_token(payload.clone(), admin)
+        .await
+        .map_err(|e| {
+            error!("{}", e.to_string());
+            match e {
+                TokenModelError::InvalidScope(_) => ApiError::BadRequest(e.to_string()),
+                _ => ApiError::InternalServerError(e.to_string()),
+            }
+        })?;
+
+    Ok((StatusCode::CREATED, Json(token)))
+}
@@ -0,0 +1,19 @@
+use axum::{
+    Router,
+    routing::{delete, post},
+};
+
+use crate::{
+    app::App,
+    tokens::handlers::{delete_tokens::delete_tokens, post_tokens::post_tokens},
+};
+
+pub fn tokens_router<A>(app: A) -> Router
+where
+    A: App + Send + Sync + Clone + 'static,
+{
+    Router::new()
+        .route("/tokens", post(post_tokens::<A>))
+        .route("/tokens/{token_id}", delete(delete_tokens::<A>))
+        .with_state(app)
+}
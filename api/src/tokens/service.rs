@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use htc::{
+    models::{
+        admins::Admin,
+        tokens::{
+            IssuedTokenSchema, SubscriberToken, TOKEN_ENTITIES, TokenModel as _, TokenModelError,
+            TokenRequestSchema, TokenScope, hash_token,
+        },
+    },
+    regions::CrousRegion,
+};
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+pub trait TokensService {
+    fn issue_token(
+        &self,
+        request: TokenRequestSchema,
+        admin: Admin,
+    ) -> impl Future<Output = Result<IssuedTokenSchema, TokenModelError>> + Send;
+    fn revoke_token(
+        &self,
+        token_id: Uuid,
+    ) -> impl Future<Output = Result<(), TokenModelError>> + Send;
+    fn authenticate(
+        &self,
+        token: &str,
+    ) -> impl Future<Output = Result<TokenScope, TokenModelError>> + Send;
+}
+
+#[derive(Clone)]
+pub struct TokensServiceImpl {
+    pool: Arc<PgPool>,
+}
+
+impl TokensService for TokensServiceImpl {
+    #[instrument(skip(self, admin), err)]
+    async fn issue_token(
+        &self,
+        request: TokenRequestSchema,
+        admin: Admin,
+    ) -> Result<IssuedTokenSchema, TokenModelError> {
+        for region in &request.regions {
+            region
+                .parse::<CrousRegion>()
+                .map_err(TokenModelError::InvalidScope)?;
+        }
+        if let Some(entity) = request
+            .entities
+            .iter()
+            .find(|entity| !TOKEN_ENTITIES.contains(&entity.as_str()))
+        {
+            return Err(TokenModelError::InvalidScope(format!(
+                "unknown entity: {}",
+                entity
+            )));
+        }
+
+        let secret = format!("htc_{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let token = SubscriberToken {
+            token_id: Uuid::new_v4(),
+            name: request.name,
+            token_hash: hash_token(&secret),
+            regions: request.regions,
+            entities: request.entities,
+            author: admin.admin_id,
+            created_at: None,
+            expires_at: request.expires_at,
+            revoked_at: None,
+        };
+        self.pool.create_token(token.clone()).await?;
+
+        Ok(IssuedTokenSchema {
+            token_id: token.token_id,
+            name: token.name,
+            token: secret,
+            regions: token.regions,
+            entities: token.entities,
+            expires_at: token.expires_at,
+        })
+    }
+
+    #[instrument(skip(self), err)]
+    async fn revoke_token(&self, token_id: Uuid) -> Result<(), TokenModelError> {
+        self.pool.revoke_token(token_id).await
+    }
+
+    async fn authenticate(&self, token: &str) -> Result<TokenScope, TokenModelError> {
+        let token = self.pool.get_token_by_hash(hash_token(token)).await?;
+        if token.revoked_at.is_some() {
+            return Err(TokenModelError::Revoked);
+        }
+        if let Some(expires_at) = token.expires_at
+            && expires_at <= Utc::now().naive_utc()
+        {
+            return Err(TokenModelError::Expired);
+        }
+        Ok(token.scope())
+    }
+}
+
+impl TokensServiceImpl {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
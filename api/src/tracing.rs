@@ -1,6 +1,7 @@
-use opentelemetry::{KeyValue, trace::TracerProvider as _};
+use opentelemetry::{KeyValue, global, trace::TracerProvider as _};
 use opentelemetry_sdk::{
     Resource,
+    metrics::SdkMeterProvider,
     trace::{RandomIdGenerator, Sampler, SdkTracerProvider},
 };
 use opentelemetry_semantic_conventions::{
@@ -39,8 +40,23 @@ fn init_tracer_provider() -> SdkTracerProvider {
         .build()
 }
 
+fn init_meter_provider() -> SdkMeterProvider {
+    let exporter = opentelemetry_otlp::MetricExporter::builder()
+        .with_tonic()
+        .build()
+        .unwrap();
+
+    let meter_provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(exporter)
+        .with_resource(resource())
+        .build();
+    global::set_meter_provider(meter_provider.clone());
+    meter_provider
+}
+
 pub fn init_tracing_subscriber() -> OtelGuard {
     let tracer_provider = init_tracer_provider();
+    let meter_provider = init_meter_provider();
 
     let tracer = tracer_provider.tracer("tracing-otel-subscriber");
 
@@ -53,11 +69,15 @@ pub fn init_tracing_subscriber() -> OtelGuard {
         .with(env_filter)
         .init();
 
-    OtelGuard { tracer_provider }
+    OtelGuard {
+        tracer_provider,
+        meter_provider,
+    }
 }
 
 pub struct OtelGuard {
     tracer_provider: SdkTracerProvider,
+    meter_provider: SdkMeterProvider,
 }
 
 impl Drop for OtelGuard {
@@ -65,5 +85,8 @@ impl Drop for OtelGuard {
         if let Err(err) = self.tracer_provider.shutdown() {
             eprintln!("{err:?}");
         }
+        if let Err(err) = self.meter_provider.shutdown() {
+            eprintln!("{err:?}");
+        }
     }
 }
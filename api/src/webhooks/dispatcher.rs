@@ -0,0 +1,303 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use htc::models::{
+    scrape_batch::ScrapeBatch,
+    webhooks::{
+        DELIVERY_HEADER, EVENT_HEADER, PendingDelivery, SIGNATURE_HEADER, TIMESTAMP_HEADER,
+        Webhook, WebhookDelivery, WebhookNotification, sign_webhook,
+    },
+};
+use reqwest::{Url, header};
+use tokio::{sync::Notify, task::JoinHandle};
+use tracing::{error, info, instrument, warn};
+use uuid::Uuid;
+
+use crate::webhooks::{
+    guard::{PublicResolver, check_public_url},
+    service::{WebhooksService, WebhooksServiceImpl},
+};
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// Attempts of one delivery before it counts as failed.
+const MAX_ATTEMPTS: i32 = 5;
+const BASE_DELAY: Duration = Duration::from_secs(2);
+const MAX_DELAY: Duration = Duration::from_secs(300);
+/// Failed deliveries in a row after which a webhook is disabled.
+const MAX_CONSECUTIVE_FAILURES: i32 = 10;
+/// How often the outbox is checked for deliveries due for a retry.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Deliveries taken from the outbox at once.
+const CLAIM_BATCH: i64 = 32;
+/// How long a taken delivery stays hidden from the other workers, longer
+/// than one attempt can last.
+const CLAIM_LEASE: Duration = Duration::from_secs(60);
+
+pub const BATCH_EVENT: &str = "batch";
+
+/// POSTs the new batches to the registered webhooks. Deliveries are queued
+/// in the `webhook_outbox` table so the retries survive a restart, and each
+/// one runs in its own task so a slow receiver doesn't hold back the others.
+#[derive(Clone)]
+pub struct WebhookDispatcher {
+    webhooks: Arc<WebhooksServiceImpl>,
+    client: reqwest::Client,
+    wake: Arc<Notify>,
+}
+
+struct Attempt {
+    status_code: Option<i32>,
+    error: Option<String>,
+}
+
+impl Attempt {
+    fn success(&self) -> bool {
+        self.error.is_none()
+    }
+
+    fn refused(error: String) -> Self {
+        Attempt {
+            status_code: None,
+            error: Some(error),
+        }
+    }
+}
+
+impl WebhookDispatcher {
+    /// Fails if the HTTP client can't be built, falling back to a default
+    /// one would drop the timeout and the private address guard.
+    pub fn new(webhooks: Arc<WebhooksServiceImpl>) -> Result<Self, reqwest::Error> {
+        let client = reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .user_agent(concat!("htc-webhooks/", env!("CARGO_PKG_VERSION")))
+            // a redirect could lead to an address the url check didn't see
+            .redirect(reqwest::redirect::Policy::none())
+            .dns_resolver(Arc::new(PublicResolver))
+            .build()?;
+        Ok(Self {
+            webhooks,
+            client,
+            wake: Arc::new(Notify::new()),
+        })
+    }
+
+    /// Queues the deliveries of `batch` in the background, without holding
+    /// back the caller.
+    pub fn dispatch(&self, batch: ScrapeBatch) {
+        let dispatcher = self.clone();
+        tokio::spawn(async move { dispatcher.enqueue(batch).await });
+    }
+
+    /// Delivers the queued notifications until the API stops.
+    pub fn run(&self) -> JoinHandle<()> {
+        let dispatcher = self.clone();
+        tokio::spawn(async move {
+            loop {
+                dispatcher.deliver_due().await;
+                tokio::select! {
+                    _ = dispatcher.wake.notified() => {}
+                    _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                }
+            }
+        })
+    }
+
+    #[instrument(skip(self, batch), fields(batch=%batch.batch_id))]
+    async fn enqueue(&self, batch: ScrapeBatch) {
+        let webhooks = match self.webhooks.get_webhooks(Some(true)).await {
+            Ok(webhooks) => webhooks,
+            Err(e) => {
+                error!("Couldn't load webhooks : {}", e);
+                return;
+            }
+        };
+        let webhook_ids: Vec<Uuid> = webhooks
+            .into_iter()
+            .filter(|webhook| webhook.scope().allows(&batch))
+            .map(|webhook| webhook.webhook_id)
+            .collect();
+        if webhook_ids.is_empty() {
+            return;
+        }
+
+        if let Err(e) = self.webhooks.enqueue_deliveries(webhook_ids, &batch).await {
+            error!("Couldn't queue webhook deliveries : {}", e);
+            return;
+        }
+        self.wake.notify_one();
+    }
+
+    async fn deliver_due(&self) {
+        loop {
+            let pending = match self
+                .webhooks
+                .claim_deliveries(CLAIM_BATCH, CLAIM_LEASE.as_secs_f64())
+                .await
+            {
+                Ok(pending) => pending,
+                Err(e) => {
+                    error!("Couldn't load the webhook outbox : {}", e);
+                    return;
+                }
+            };
+            if pending.is_empty() {
+                return;
+            }
+            let webhooks: HashMap<Uuid, Webhook> = match self.webhooks.get_webhooks(None).await {
+                Ok(webhooks) => webhooks
+                    .into_iter()
+                    .map(|webhook| (webhook.webhook_id, webhook))
+                    .collect(),
+                Err(e) => {
+                    // the lease brings the deliveries back later
+                    error!("Couldn't load webhooks : {}", e);
+                    return;
+                }
+            };
+
+            let claimed = pending.len();
+            for delivery in pending {
+                let dispatcher = self.clone();
+                match webhooks.get(&delivery.webhook_id) {
+                    Some(webhook) if webhook.active => {
+                        let webhook = webhook.clone();
+                        tokio::spawn(async move { dispatcher.deliver(webhook, delivery).await });
+                    }
+                    // disabled since it was queued
+                    _ => dispatcher.complete(delivery.delivery_id).await,
+                }
+            }
+            if (claimed as i64) < CLAIM_BATCH {
+                return;
+            }
+        }
+    }
+
+    fn delay(attempt: i32) -> Duration {
+        BASE_DELAY
+            .saturating_mul(2u32.saturating_pow(attempt.max(1) as u32 - 1))
+            .min(MAX_DELAY)
+    }
+
+    async fn complete(&self, delivery_id: Uuid) {
+        if let Err(e) = self.webhooks.complete_delivery(delivery_id).await {
+            error!("Couldn't remove webhook delivery {} : {}", delivery_id, e);
+        }
+    }
+
+    #[instrument(skip(self, webhook, delivery), fields(webhook=%webhook.webhook_id, batch=%delivery.batch.batch_id, attempt=delivery.attempt))]
+    async fn deliver(&self, webhook: Webhook, delivery: PendingDelivery) {
+        let PendingDelivery {
+            delivery_id,
+            batch,
+            attempt,
+            ..
+        } = delivery;
+        let batch_id = batch.batch_id;
+        let notification = WebhookNotification {
+            event: BATCH_EVENT.to_string(),
+            delivery_id,
+            batch,
+        };
+        let body = match serde_json::to_vec(&notification) {
+            Ok(body) => body,
+            Err(e) => {
+                error!("Couldn't serialize notification : {}", e);
+                self.complete(delivery_id).await;
+                return;
+            }
+        };
+
+        let started = Instant::now();
+        let result = self.attempt(&webhook, delivery_id, &body).await;
+        let success = result.success();
+
+        let record = WebhookDelivery {
+            delivery_id,
+            webhook_id: webhook.webhook_id,
+            batch_id,
+            attempt,
+            status_code: result.status_code,
+            success,
+            error: result.error,
+            duration_ms: started.elapsed().as_millis() as i64,
+            delivered_at: None,
+        };
+        if let Err(e) = self.webhooks.record_delivery(record).await {
+            error!("Couldn't record webhook delivery : {}", e);
+        }
+
+        if !success && attempt < MAX_ATTEMPTS {
+            if let Err(e) = self
+                .webhooks
+                .retry_delivery(delivery_id, attempt + 1, Self::delay(attempt).as_secs_f64())
+                .await
+            {
+                error!("Couldn't schedule the webhook retry : {}", e);
+            }
+            return;
+        }
+
+        self.complete(delivery_id).await;
+        match self
+            .webhooks
+            .record_outcome(webhook.webhook_id, success, MAX_CONSECUTIVE_FAILURES)
+            .await
+        {
+            Ok(false) => warn!(
+                "Webhook {} disabled after {} failed deliveries",
+                webhook.name, MAX_CONSECUTIVE_FAILURES
+            ),
+            Ok(true) => info!("Webhook {} delivered : {}", webhook.name, success),
+            Err(e) => error!("Couldn't record webhook outcome : {}", e),
+        }
+    }
+
+    async fn attempt(&self, webhook: &Webhook, delivery_id: Uuid, body: &[u8]) -> Attempt {
+        // checked on each attempt as the name may resolve elsewhere by now,
+        // ip literals don't go through the resolver
+        let url = match Url::parse(&webhook.url) {
+            Ok(url) => url,
+            Err(e) => return Attempt::refused(format!("invalid url: {}", e)),
+        };
+        if let Err(e) = check_public_url(&url).await {
+            return Attempt::refused(e);
+        }
+
+        // signed again on each attempt so the timestamp stays fresh
+        let timestamp = Utc::now().timestamp();
+        let response = self
+            .client
+            .post(url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(
+                SIGNATURE_HEADER,
+                sign_webhook(&webhook.secret, timestamp, body),
+            )
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(EVENT_HEADER, BATCH_EVENT)
+            .header(DELIVERY_HEADER, delivery_id.to_string())
+            .body(body.to_vec())
+            .send()
+            .await;
+
+        match response {
+            Ok(response) if response.status().is_success() => Attempt {
+                status_code: Some(response.status().as_u16() as i32),
+                error: None,
+            },
+            Ok(response) => Attempt {
+                status_code: Some(response.status().as_u16() as i32),
+                error: Some(format!("Unexpected status {}", response.status())),
+            },
+            Err(e) => Attempt {
+                status_code: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
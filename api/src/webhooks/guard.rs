@@ -0,0 +1,54 @@
+use std::net::{IpAddr, SocketAddr};
+
+use htc::models::webhooks::is_public_ip;
+use reqwest::{
+    Url,
+    dns::{Addrs, Name, Resolve, Resolving},
+};
+
+/// Refuses a webhook url whose host is, or resolves to, an address that isn't
+/// public, so a webhook can't be pointed at the services next to the API.
+pub async fn check_public_url(url: &Url) -> Result<(), String> {
+    let host = url.host_str().ok_or("url has no host")?;
+    let port = url.port_or_known_default().unwrap_or(80);
+    let literal = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<SocketAddr> = match literal.parse::<IpAddr>() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|e| format!("couldn't resolve {} : {}", host, e))?
+            .collect(),
+    };
+    if addrs.is_empty() {
+        return Err(format!("{} doesn't resolve to any address", host));
+    }
+    match addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        Some(addr) => Err(format!(
+            "{} points to a non public address {}",
+            host,
+            addr.ip()
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Resolver of the delivery client, refusing the names resolving to a non
+/// public address when the request is sent and not only when the webhook
+/// was registered.
+pub struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+                return Err(
+                    format!("{} points to a non public address {}", host, addr.ip()).into(),
+                );
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
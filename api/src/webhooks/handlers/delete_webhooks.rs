@@ -0,0 +1,97 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use chrono::Utc;
+use htc::{
+    models::webhooks::{WebhookAction, WebhookModelError, WebhookTargetSchema},
+    verifiable::{SignedPayload, check_issued_at},
+};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    app::App,
+    audit::trail::{AuditTrail, Provenance},
+    error::ApiError,
+};
+
+#[utoipa::path(
+    delete,
+    path = "/webhooks/{webhook_id}",
+    params(
+        ("webhook_id" = String, Path, description = "Identifier of the webhook")
+    ),
+    tag = "Webhooks",
+    request_body = SignedPayload<WebhookTargetSchema>,
+    responses(
+        (status = 204, description = "Webhook and its delivery log deleted"),
+        (status = 400, description = "Signed webhook id or action doesn't match the request"),
+        (status = 401, description = "Unauthorized, or the signed request is stale"),
+        (status = 404, description = "Webhook not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn delete_webhooks<A>(
+    Path(webhook_id): Path<Uuid>,
+    State(state): State<A>,
+    provenance: Provenance,
+    Json(body): Json<SignedPayload<WebhookTargetSchema>>,
+) -> Result<StatusCode, ApiError>
+where
+    A: App + Send + Sync + Clone,
+{
+    let mut trail = AuditTrail::new("delete_webhook", &body.author, provenance);
+    let result = delete_webhook(&state, webhook_id, body, &mut trail).await;
+    if let Err(e) = state.record_audit(trail.finish(&result)).await {
+        error!("Couldn't record audit entry : {}", e);
+    }
+    result
+}
+
+async fn delete_webhook<A>(
+    state: &A,
+    webhook_id: Uuid,
+    body: SignedPayload<WebhookTargetSchema>,
+    trail: &mut AuditTrail,
+) -> Result<StatusCode, ApiError>
+where
+    A: App + Send + Sync + Clone,
+{
+    let admin = state.get_admin(&body.author).await.map_err(|e| {
+        error!("{}", e.to_string());
+        ApiError::Unauthorized(e.to_string())
+    })?;
+    trail.admin(&admin);
+    let (payload, digest) = body.verify(admin.ssh_key.as_str()).map_err(|e| {
+        error!("{}", e.to_string());
+        ApiError::Unauthorized(e.to_string())
+    })?;
+    trail.digest(&digest);
+
+    if payload.webhook_id != webhook_id {
+        return Err(ApiError::BadRequest(
+            "Signed webhook id doesn't match the path".to_string(),
+        ));
+    }
+    if payload.action != WebhookAction::Delete {
+        return Err(ApiError::BadRequest(
+            "Signed request wasn't meant to delete the webhook".to_string(),
+        ));
+    }
+    check_issued_at(payload.issued_at, Utc::now().timestamp()).map_err(|e| {
+        error!("{}", e.to_string());
+        ApiError::Unauthorized(e.to_string())
+    })?;
+
+    state.delete_webhook(webhook_id).await.map_err(|e| {
+        error!("{}", e.to_string());
+        match e {
+            WebhookModelError::NotFound => ApiError::NotFound(e.to_string()),
+            _ => ApiError::InternalServerError(e.to_string()),
+        }
+    })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
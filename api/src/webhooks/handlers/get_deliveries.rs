@@ -0,0 +1,70 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use htc::{
+    models::webhooks::{DeliveryQuerySchema, WebhookDeliverySchema},
+    verifiable::SignedPayload,
+};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{app::App, error::ApiError};
+
+const DEFAULT_DELIVERIES_LIMIT: i64 = 50;
+const MAX_DELIVERIES_LIMIT: i64 = 500;
+
+#[utoipa::path(
+    get,
+    path = "/webhooks/{webhook_id}/deliveries",
+    params(
+        ("webhook_id" = String, Path, description = "Identifier of the webhook")
+    ),
+    tag = "Webhooks",
+    request_body = SignedPayload<DeliveryQuerySchema>,
+    responses(
+        (status = 200, description = "Delivery attempts, most recent first", body = [Vec<WebhookDeliverySchema>]),
+        (status = 400, description = "Signed webhook id doesn't match the path"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn get_deliveries<A>(
+    Path(webhook_id): Path<Uuid>,
+    State(state): State<A>,
+    Json(body): Json<SignedPayload<DeliveryQuerySchema>>,
+) -> Result<Json<Vec<WebhookDeliverySchema>>, ApiError>
+where
+    A: App + Send + Sync + Clone,
+{
+    let admin = state.get_admin(&body.author).await.map_err(|e| {
+        error!("{}", e.to_string());
+        ApiError::Unauthorized(e.to_string())
+    })?;
+    let (query, _) = body.verify(admin.ssh_key.as_str()).map_err(|e| {
+        error!("{}", e.to_string());
+        ApiError::Unauthorized(e.to_string())
+    })?;
+
+    if query.webhook_id != webhook_id {
+        return Err(ApiError::BadRequest(
+            "Signed webhook id doesn't match the path".to_string(),
+        ));
+    }
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_DELIVERIES_LIMIT)
+        .clamp(1, MAX_DELIVERIES_LIMIT);
+    let deliveries = state
+        .get_webhook_deliveries(webhook_id, limit)
+        .await
+        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+
+    Ok(Json(
+        deliveries
+            .into_iter()
+            .map(WebhookDeliverySchema::from)
+            .collect(),
+    ))
+}
@@ -0,0 +1,45 @@
+use axum::{Json, extract::State};
+use htc::{
+    models::webhooks::{WebhookQuerySchema, WebhookSchema},
+    verifiable::SignedPayload,
+};
+use tracing::error;
+
+use crate::{app::App, error::ApiError};
+
+#[utoipa::path(
+    get,
+    path = "/webhooks",
+    tag = "Webhooks",
+    request_body = SignedPayload<WebhookQuerySchema>,
+    responses(
+        (status = 200, description = "Registered webhooks, without their secret", body = [Vec<WebhookSchema>]),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn get_webhooks<A>(
+    State(state): State<A>,
+    Json(body): Json<SignedPayload<WebhookQuerySchema>>,
+) -> Result<Json<Vec<WebhookSchema>>, ApiError>
+where
+    A: App + Send + Sync + Clone,
+{
+    let admin = state.get_admin(&body.author).await.map_err(|e| {
+        error!("{}", e.to_string());
+        ApiError::Unauthorized(e.to_string())
+    })?;
+    let (query, _) = body.verify(admin.ssh_key.as_str()).map_err(|e| {
+        error!("{}", e.to_string());
+        ApiError::Unauthorized(e.to_string())
+    })?;
+
+    let webhooks = state
+        .get_webhooks(query.active)
+        .await
+        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+
+    Ok(Json(
+        webhooks.into_iter().map(WebhookSchema::from).collect(),
+    ))
+}
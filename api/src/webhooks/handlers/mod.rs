@@ -0,0 +1,5 @@
+pub mod delete_webhooks;
+pub mod get_deliveries;
+pub mod get_webhooks;
+pub mod post_webhooks;
+pub mod put_webhook_enabled;
@@ -0,0 +1,78 @@
+use axum::{Json, extract::State, http::StatusCode};
+use chrono::Utc;
+use htc::{
+    models::webhooks::{RegisteredWebhookSchema, WebhookModelError, WebhookRequestSchema},
+    verifiable::{SignedPayload, check_issued_at},
+};
+use tracing::error;
+
+use crate::{
+    app::App,
+    audit::trail::{AuditTrail, Provenance},
+    error::ApiError,
+};
+
+#[utoipa::path(
+    post,
+    path = "/webhooks",
+    tag = "Webhooks",
+    request_body = SignedPayload<WebhookRequestSchema>,
+    responses(
+        (status = 201, description = "Webhook registered, the HMAC secret is only returned once", body = RegisteredWebhookSchema),
+        (status = 400, description = "Invalid url or filter"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn post_webhooks<A>(
+    State(state): State<A>,
+    provenance: Provenance,
+    Json(body): Json<SignedPayload<WebhookRequestSchema>>,
+) -> Result<(StatusCode, Json<RegisteredWebhookSchema>), ApiError>
+where
+    A: App + Send + Sync + Clone,
+{
+    let mut trail = AuditTrail::new("register_webhook", &body.author, provenance);
+    let result = register_webhook(&state, body, &mut trail).await;
+    if let Err(e) = state.record_audit(trail.finish(&result)).await {
+        error!("Couldn't record audit entry : {}", e);
+    }
+    result
+}
+
+async fn register_webhook<A>(
+    state: &A,
+    body: SignedPayload<WebhookRequestSchema>,
+    trail: &mut AuditTrail,
+) -> Result<(StatusCode, Json<RegisteredWebhookSchema>), ApiError>
+where
+    A: App + Send + Sync + Clone,
+{
+    let admin = state.get_admin(&body.author).await.map_err(|e| {
+        error!("{}", e.to_string());
+        ApiError::Unauthorized(e.to_string())
+    })?;
+    trail.admin(&admin);
+    let (payload, digest) = body.verify(admin.ssh_key.as_str()).map_err(|e| {
+        error!("{}", e.to_string());
+        ApiError::Unauthorized(e.to_string())
+    })?;
+    trail.digest(&digest);
+    check_issued_at(payload.issued_at, Utc::now().timestamp()).map_err(|e| {
+        error!("{}", e.to_string());
+        ApiError::Unauthorized(e.to_string())
+    })?;
+
+    let webhook = state
+        .register_webhook(payload.clone(), admin)
+        .await
+        .map_err(|e| {
+            error!("{}", e.to_string());
+            match e {
+                WebhookModelError::InvalidWebhook(_) => ApiError::BadRequest(e.to_string()),
+                _ => ApiError::InternalServerError(e.to_string()),
+            }
+        })?;
+
+    Ok((StatusCode::CREATED, Json(webhook)))
+}
@@ -0,0 +1,5 @@
+pub mod dispatcher;
+pub mod guard;
+pub mod handlers;
+pub mod router;
+pub mod service;
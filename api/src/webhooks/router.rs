@@ -0,0 +1,31 @@
+use axum::{
+    Router,
+    routing::{delete, get, put},
+};
+
+use crate::{
+    app::App,
+    webhooks::handlers::{
+        delete_webhooks::delete_webhooks, get_deliveries::get_deliveries,
+        get_webhooks::get_webhooks, post_webhooks::post_webhooks,
+        put_webhook_enabled::put_webhook_enabled,
+    },
+};
+
+pub fn webhooks_router<A>(app: A) -> Router
+where
+    A: App + Send + Sync + Clone + 'static,
+{
+    Router::new()
+        .route("/webhooks", get(get_webhooks::<A>).post(post_webhooks::<A>))
+        .route("/webhooks/{webhook_id}", delete(delete_webhooks::<A>))
+        .route(
+            "/webhooks/{webhook_id}/enabled",
+            put(put_webhook_enabled::<A>),
+        )
+        .route(
+            "/webhooks/{webhook_id}/deliveries",
+            get(get_deliveries::<A>),
+        )
+        .with_state(app)
+}
@@ -0,0 +1,219 @@
+use std::sync::Arc;
+
+use htc::{
+    models::scrape_batch::ScrapeBatch,
+    models::{
+        admins::Admin,
+        tokens::TOKEN_ENTITIES,
+        webhooks::{
+            PendingDelivery, RegisteredWebhookSchema, Webhook, WebhookDelivery, WebhookModel as _,
+            WebhookModelError, WebhookRequestSchema,
+        },
+    },
+    regions::CrousRegion,
+};
+use reqwest::Url;
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::webhooks::guard::check_public_url;
+
+pub trait WebhooksService {
+    fn register_webhook(
+        &self,
+        request: WebhookRequestSchema,
+        admin: Admin,
+    ) -> impl Future<Output = Result<RegisteredWebhookSchema, WebhookModelError>> + Send;
+    fn get_webhooks(
+        &self,
+        active: Option<bool>,
+    ) -> impl Future<Output = Result<Vec<Webhook>, WebhookModelError>> + Send;
+    fn delete_webhook(
+        &self,
+        webhook_id: Uuid,
+    ) -> impl Future<Output = Result<(), WebhookModelError>> + Send;
+    fn enable_webhook(
+        &self,
+        webhook_id: Uuid,
+    ) -> impl Future<Output = Result<(), WebhookModelError>> + Send;
+    fn record_delivery(
+        &self,
+        delivery: WebhookDelivery,
+    ) -> impl Future<Output = Result<(), WebhookModelError>> + Send;
+    fn record_outcome(
+        &self,
+        webhook_id: Uuid,
+        success: bool,
+        max_failures: i32,
+    ) -> impl Future<Output = Result<bool, WebhookModelError>> + Send;
+    fn get_deliveries(
+        &self,
+        webhook_id: Uuid,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<WebhookDelivery>, WebhookModelError>> + Send;
+    fn enqueue_deliveries(
+        &self,
+        webhook_ids: Vec<Uuid>,
+        batch: &ScrapeBatch,
+    ) -> impl Future<Output = Result<(), WebhookModelError>> + Send;
+    fn claim_deliveries(
+        &self,
+        limit: i64,
+        lease_secs: f64,
+    ) -> impl Future<Output = Result<Vec<PendingDelivery>, WebhookModelError>> + Send;
+    fn retry_delivery(
+        &self,
+        delivery_id: Uuid,
+        attempt: i32,
+        delay_secs: f64,
+    ) -> impl Future<Output = Result<(), WebhookModelError>> + Send;
+    fn complete_delivery(
+        &self,
+        delivery_id: Uuid,
+    ) -> impl Future<Output = Result<(), WebhookModelError>> + Send;
+}
+
+#[derive(Clone)]
+pub struct WebhooksServiceImpl {
+    pool: Arc<PgPool>,
+}
+
+impl WebhooksService for WebhooksServiceImpl {
+    #[instrument(skip(self, admin), err)]
+    async fn register_webhook(
+        &self,
+        request: WebhookRequestSchema,
+        admin: Admin,
+    ) -> Result<RegisteredWebhookSchema, WebhookModelError> {
+        let url = Url::parse(&request.url)
+            .map_err(|e| WebhookModelError::InvalidWebhook(format!("invalid url: {}", e)))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(WebhookModelError::InvalidWebhook(format!(
+                "unsupported scheme: {}",
+                url.scheme()
+            )));
+        }
+        check_public_url(&url)
+            .await
+            .map_err(WebhookModelError::InvalidWebhook)?;
+        for region in &request.regions {
+            region
+                .parse::<CrousRegion>()
+                .map_err(WebhookModelError::InvalidWebhook)?;
+        }
+        if let Some(entity) = request
+            .entities
+            .iter()
+            .find(|entity| !TOKEN_ENTITIES.contains(&entity.as_str()))
+        {
+            return Err(WebhookModelError::InvalidWebhook(format!(
+                "unknown entity: {}",
+                entity
+            )));
+        }
+
+        let webhook = Webhook {
+            webhook_id: Uuid::new_v4(),
+            name: request.name,
+            url: url.to_string(),
+            secret: format!(
+                "whsec_{}{}",
+                Uuid::new_v4().simple(),
+                Uuid::new_v4().simple()
+            ),
+            regions: request.regions,
+            entities: request.entities,
+            author: admin.admin_id,
+            created_at: None,
+            active: true,
+            consecutive_failures: 0,
+            disabled_at: None,
+            disabled_reason: None,
+        };
+        self.pool.create_webhook(webhook.clone()).await?;
+
+        Ok(RegisteredWebhookSchema {
+            webhook_id: webhook.webhook_id,
+            name: webhook.name,
+            url: webhook.url,
+            secret: webhook.secret,
+            regions: webhook.regions,
+            entities: webhook.entities,
+        })
+    }
+
+    async fn get_webhooks(&self, active: Option<bool>) -> Result<Vec<Webhook>, WebhookModelError> {
+        self.pool.get_webhooks(active).await
+    }
+
+    #[instrument(skip(self), err)]
+    async fn delete_webhook(&self, webhook_id: Uuid) -> Result<(), WebhookModelError> {
+        self.pool.delete_webhook(webhook_id).await
+    }
+
+    #[instrument(skip(self), err)]
+    async fn enable_webhook(&self, webhook_id: Uuid) -> Result<(), WebhookModelError> {
+        self.pool.enable_webhook(webhook_id).await
+    }
+
+    async fn record_delivery(&self, delivery: WebhookDelivery) -> Result<(), WebhookModelError> {
+        self.pool.record_delivery(delivery).await
+    }
+
+    async fn record_outcome(
+        &self,
+        webhook_id: Uuid,
+        success: bool,
+        max_failures: i32,
+    ) -> Result<bool, WebhookModelError> {
+        self.pool
+            .record_outcome(webhook_id, success, max_failures)
+            .await
+    }
+
+    async fn get_deliveries(
+        &self,
+        webhook_id: Uuid,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, WebhookModelError> {
+        self.pool.get_deliveries(webhook_id, limit).await
+    }
+
+    async fn enqueue_deliveries(
+        &self,
+        webhook_ids: Vec<Uuid>,
+        batch: &ScrapeBatch,
+    ) -> Result<(), WebhookModelError> {
+        self.pool.enqueue_deliveries(webhook_ids, batch).await
+    }
+
+    async fn claim_deliveries(
+        &self,
+        limit: i64,
+        lease_secs: f64,
+    ) -> Result<Vec<PendingDelivery>, WebhookModelError> {
+        self.pool.claim_deliveries(limit, lease_secs).await
+    }
+
+    async fn retry_delivery(
+        &self,
+        delivery_id: Uuid,
+        attempt: i32,
+        delay_secs: f64,
+    ) -> Result<(), WebhookModelError> {
+        self.pool
+            .retry_delivery(delivery_id, attempt, delay_secs)
+            .await
+    }
+
+    async fn complete_delivery(&self, delivery_id: Uuid) -> Result<(), WebhookModelError> {
+        self.pool.complete_delivery(delivery_id).await
+    }
+}
+
+impl WebhooksServiceImpl {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
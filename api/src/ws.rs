@@ -0,0 +1,387 @@
+use std::{collections::BTreeSet, sync::Arc, time::Duration};
+
+use axum::{
+    Router,
+    extract::{
+        Query, State, WebSocketUpgrade,
+        ws::{Message, WebSocket},
+    },
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use htc::models::{
+    Entity,
+    scrape_batch::ScrapeBatch,
+    tokens::{TOKEN_ENTITIES, TokenScope},
+};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::broadcast::error::RecvError,
+    time::{Instant, interval},
+};
+use tracing::info;
+
+use crate::sse::{SseState, bearer_token, reauthorizations};
+
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+/// A connection that didn't answer two pings in a row is dropped.
+const PONG_TIMEOUT: Duration = Duration::from_secs(65);
+
+/// Messages sent by the client.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Subscribe(Topics),
+    Unsubscribe(Topics),
+    Ping,
+}
+
+/// Messages sent by the server.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage<'a> {
+    Subscribed(&'a Topics),
+    Batch { batch: &'a ScrapeBatch },
+    Lagged { missed: u64 },
+    Pong,
+    Error { message: String },
+}
+
+/// What a connection listens to. A missing list doesn't restrict while an
+/// empty one matches nothing, and nothing is sent until the first
+/// `subscribe`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Topics {
+    #[serde(default)]
+    pub regions: Option<BTreeSet<String>>,
+    #[serde(default)]
+    pub entities: Option<BTreeSet<String>>,
+    #[serde(default)]
+    pub restaurants: Option<BTreeSet<String>>,
+}
+
+impl Topics {
+    /// Topics of a connection listening to nothing.
+    fn none() -> Self {
+        Topics {
+            regions: Some(BTreeSet::new()),
+            entities: Some(BTreeSet::new()),
+            restaurants: Some(BTreeSet::new()),
+        }
+    }
+
+    fn normalize(mut self) -> Result<Self, String> {
+        self.regions = self.regions.map(|regions| {
+            regions
+                .into_iter()
+                .map(|region| region.to_lowercase())
+                .collect()
+        });
+        if let Some(entity) = self
+            .entities
+            .iter()
+            .flatten()
+            .find(|entity| !TOKEN_ENTITIES.contains(&entity.as_str()))
+        {
+            return Err(format!("Unknown entity : {}", entity));
+        }
+        Ok(self)
+    }
+
+    /// Whether a list was emptied, so that no batch can match anymore.
+    fn is_exhausted(&self) -> bool {
+        [&self.regions, &self.entities, &self.restaurants]
+            .into_iter()
+            .any(|list| list.as_ref().is_some_and(BTreeSet::is_empty))
+    }
+
+    fn add(&mut self, topics: Topics) {
+        for (current, added) in [
+            (&mut self.regions, topics.regions),
+            (&mut self.entities, topics.entities),
+            (&mut self.restaurants, topics.restaurants),
+        ] {
+            if let Some(added) = added {
+                current.get_or_insert_default().extend(added);
+            }
+        }
+    }
+
+    /// Removes the listed topics. A list that isn't restricted has nothing
+    /// to remove and keeps matching everything.
+    fn remove(&mut self, topics: &Topics) {
+        for (current, removed) in [
+            (&mut self.regions, &topics.regions),
+            (&mut self.entities, &topics.entities),
+            (&mut self.restaurants, &topics.restaurants),
+        ] {
+            if let (Some(current), Some(removed)) = (current, removed) {
+                current.retain(|topic| !removed.contains(topic));
+            }
+        }
+    }
+
+    fn matches(&self, batch: &ScrapeBatch) -> bool {
+        let region_matches = self
+            .regions
+            .as_ref()
+            .is_none_or(|regions| regions.contains(&batch.region.to_lowercase()));
+        let entity_matches = self
+            .entities
+            .as_ref()
+            .is_none_or(|entities| entities.contains(batch.entity.kind()));
+        let restaurant_matches = self.restaurants.as_ref().is_none_or(
+            |restaurants| matches!(&batch.entity, Entity::Meals(id) if restaurants.contains(id)),
+        );
+        region_matches && entity_matches && restaurant_matches
+    }
+}
+
+#[derive(Deserialize)]
+struct WsParams {
+    /// Browsers can't set headers on a WebSocket handshake
+    token: Option<String>,
+}
+
+async fn ws_handler(
+    State(state): State<Arc<SseState>>,
+    Query(params): Query<WsParams>,
+    headers: HeaderMap,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let Some(token) = params
+        .token
+        .or_else(|| bearer_token(&headers).map(str::to_string))
+    else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let Some(scope) = state.authorize_token(&token).await else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+
+    upgrade.on_upgrade(move |socket| serve_socket(socket, state, token, scope))
+}
+
+async fn send(socket: &mut WebSocket, message: &ServerMessage<'_>) -> bool {
+    let Ok(json) = serde_json::to_string(message) else {
+        return false;
+    };
+    socket.send(Message::Text(json.into())).await.is_ok()
+}
+
+async fn serve_socket(
+    mut socket: WebSocket,
+    state: Arc<SseState>,
+    token: String,
+    scope: TokenScope,
+) {
+    let mut receiver = state.subscribe();
+    let mut topics: Option<Topics> = None;
+    let mut pings = interval(PING_INTERVAL);
+    let mut reauthorizations = reauthorizations();
+    let mut last_seen = Instant::now();
+
+    loop {
+        let keep_going = tokio::select! {
+            message = socket.recv() => {
+                let Some(Ok(message)) = message else { break };
+                last_seen = Instant::now();
+                match message {
+                    Message::Text(text) => {
+                        handle_message(&mut socket, &mut topics, text.as_str()).await
+                    }
+                    Message::Close(_) => false,
+                    // axum answers pings on its own, pongs only refresh `last_seen`
+                    _ => true,
+                }
+            }
+            batch = receiver.recv() => match batch {
+                Ok(batch) => match &topics {
+                    Some(topics) if scope.allows(&batch) && topics.matches(&batch) => {
+                        send(&mut socket, &ServerMessage::Batch { batch: &batch }).await
+                    }
+                    _ => true,
+                },
+                Err(RecvError::Lagged(missed)) => {
+                    send(&mut socket, &ServerMessage::Lagged { missed }).await
+                }
+                Err(RecvError::Closed) => false,
+            },
+            _ = reauthorizations.tick() => {
+                if state.authorize_token(&token).await.is_none() {
+                    info!("Closing the websocket of a rejected token");
+                    false
+                } else {
+                    true
+                }
+            }
+            _ = pings.tick() => {
+                if last_seen.elapsed() > PONG_TIMEOUT {
+                    info!("Closing unresponsive websocket");
+                    false
+                } else {
+                    socket.send(Message::Ping(Default::default())).await.is_ok()
+                }
+            }
+        };
+
+        if !keep_going {
+            break;
+        }
+    }
+}
+
+async fn handle_message(socket: &mut WebSocket, topics: &mut Option<Topics>, text: &str) -> bool {
+    let message = match serde_json::from_str::<ClientMessage>(text) {
+        Ok(message) => message,
+        Err(e) => {
+            let message = format!("Invalid message : {}", e);
+            return send(socket, &ServerMessage::Error { message }).await;
+        }
+    };
+
+    match message {
+        ClientMessage::Ping => return send(socket, &ServerMessage::Pong).await,
+        ClientMessage::Subscribe(requested) => match requested.normalize() {
+            Ok(requested) => topics.get_or_insert_default().add(requested),
+            Err(message) => return send(socket, &ServerMessage::Error { message }).await,
+        },
+        ClientMessage::Unsubscribe(requested) => match requested.normalize() {
+            Ok(requested) => {
+                if let Some(current) = topics.as_mut() {
+                    current.remove(&requested);
+                    if current.is_exhausted() {
+                        *topics = None;
+                    }
+                }
+            }
+            Err(message) => return send(socket, &ServerMessage::Error { message }).await,
+        },
+    }
+
+    let current = topics.clone().unwrap_or_else(Topics::none);
+    send(socket, &ServerMessage::Subscribed(&current)).await
+}
+
+pub fn ws_router(state: Arc<SseState>) -> Router {
+    Router::new()
+        .route("/ws", get(ws_handler))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn set(topics: &[&str]) -> Option<BTreeSet<String>> {
+        Some(topics.iter().map(|topic| topic.to_string()).collect())
+    }
+
+    fn make_batch(entity: Entity, region: &str) -> ScrapeBatch {
+        ScrapeBatch {
+            batch_id: Uuid::nil(),
+            entity,
+            author: Uuid::nil(),
+            region: region.to_string(),
+            scraped_at: None,
+            checksum: "checksum".to_string(),
+            seq: None,
+            summary: None,
+        }
+    }
+
+    #[test]
+    fn test_normalize_lowercases_regions_and_checks_entities() {
+        let topics = Topics {
+            regions: set(&["Montpellier", "PARIS"]),
+            ..Default::default()
+        }
+        .normalize()
+        .unwrap();
+        assert_eq!(topics.regions, set(&["montpellier", "paris"]));
+
+        let unknown = Topics {
+            entities: set(&["meals", "dishes"]),
+            ..Default::default()
+        };
+        assert_eq!(
+            unknown.normalize().unwrap_err(),
+            "Unknown entity : dishes".to_string()
+        );
+    }
+
+    #[test]
+    fn test_add_and_remove_topics() {
+        let mut topics = Topics::none();
+        assert!(topics.is_exhausted());
+
+        topics.add(Topics {
+            regions: set(&["montpellier", "paris"]),
+            entities: set(&["meals"]),
+            restaurants: None,
+        });
+        assert_eq!(topics.regions, set(&["montpellier", "paris"]));
+        assert_eq!(topics.entities, set(&["meals"]));
+        // still restricted to no restaurant
+        assert!(topics.is_exhausted());
+
+        topics.remove(&Topics {
+            regions: set(&["paris"]),
+            ..Default::default()
+        });
+        assert_eq!(topics.regions, set(&["montpellier"]));
+    }
+
+    #[test]
+    fn test_remove_from_unrestricted_list_keeps_matching_everything() {
+        let mut topics = Topics::default();
+        topics.remove(&Topics {
+            regions: set(&["montpellier"]),
+            ..Default::default()
+        });
+        assert_eq!(topics.regions, None);
+        assert!(!topics.is_exhausted());
+        assert!(topics.matches(&make_batch(Entity::Restaurants, "Montpellier")));
+    }
+
+    #[test]
+    fn test_emptied_list_is_exhausted() {
+        let mut topics = Topics {
+            entities: set(&["restaurants"]),
+            ..Default::default()
+        };
+        assert!(!topics.is_exhausted());
+        topics.remove(&Topics {
+            entities: set(&["restaurants"]),
+            ..Default::default()
+        });
+        assert!(topics.is_exhausted());
+        assert!(!topics.matches(&make_batch(Entity::Restaurants, "Montpellier")));
+    }
+
+    #[test]
+    fn test_matches_region_entity_and_restaurant() {
+        let topics = Topics {
+            regions: set(&["Montpellier"]),
+            entities: set(&["meals"]),
+            restaurants: set(&["brasserie-triolet"]),
+        }
+        .normalize()
+        .unwrap();
+        let meals = |restaurant: &str| Entity::Meals(restaurant.to_string());
+        assert!(topics.matches(&make_batch(meals("brasserie-triolet"), "Montpellier")));
+        assert!(!topics.matches(&make_batch(meals("ri"), "Montpellier")));
+        assert!(!topics.matches(&make_batch(meals("brasserie-triolet"), "Paris")));
+        assert!(!topics.matches(&make_batch(Entity::Restaurants, "Montpellier")));
+
+        // a restaurant list only lets meals through
+        let restaurants_only = Topics {
+            restaurants: set(&["brasserie-triolet"]),
+            ..Default::default()
+        };
+        assert!(!restaurants_only.matches(&make_batch(Entity::Restaurants, "Montpellier")));
+    }
+}
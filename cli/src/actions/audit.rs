@@ -0,0 +1,79 @@
+use htc::{
+    client::HTCClient,
+    models::audit::{AuditEntrySchema, AuditQuerySchema},
+};
+use tabled::{
+    Table, Tabled,
+    settings::{Style, Width, object::Columns},
+};
+
+use crate::actions::{Executable, ExecutionResult};
+
+pub struct AuditAction {
+    pub query: AuditQuerySchema,
+
+    pub client: HTCClient,
+}
+
+impl AuditAction {
+    pub fn new(query: AuditQuerySchema, client: HTCClient) -> Self {
+        Self { query, client }
+    }
+
+    async fn execute_inner(&self) -> Result<(), ExecutionResult> {
+        let entries = self
+            .client
+            .get_audit(self.query.clone())
+            .await
+            .map_err(|e| ExecutionResult::Failure(e.to_string()))?;
+
+        let mut table = Table::new(entries.into_iter().map(DisplayableAuditEntry::from));
+        table.with(Style::modern());
+        table.modify(Columns::last(), Width::wrap(40));
+        println!("{}", table);
+        Ok(())
+    }
+}
+
+impl Executable for AuditAction {
+    fn execute(
+        &self,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ExecutionResult>> + Send + '_>>
+    {
+        Box::pin(async move { self.execute_inner().await })
+    }
+}
+
+#[derive(Tabled)]
+pub struct DisplayableAuditEntry {
+    pub at: String,
+    pub action: String,
+    pub author: String,
+    pub key: String,
+    pub region: String,
+    pub outcome: String,
+    pub source_ip: String,
+    pub client: String,
+    pub digest: String,
+    pub detail: String,
+}
+
+impl From<AuditEntrySchema> for DisplayableAuditEntry {
+    fn from(entry: AuditEntrySchema) -> Self {
+        DisplayableAuditEntry {
+            at: entry
+                .created_at
+                .map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default(),
+            action: entry.action,
+            author: entry.author,
+            key: entry.key_fingerprint.unwrap_or_default(),
+            region: entry.region.unwrap_or_default(),
+            outcome: entry.outcome.to_string(),
+            source_ip: entry.source_ip.unwrap_or_default(),
+            client: entry.client_version.unwrap_or_default(),
+            digest: entry.payload_digest.unwrap_or_default(),
+            detail: entry.detail.unwrap_or_default(),
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use std::sync::Arc;
+
+use color_print::cprintln;
+use crawler::{
+    Scraper, diagnostics::ParseDiagnostics, fetcher::Fetcher, profile::SelectorProfiles,
+    restaurant_list::RestaurantListScraper, restaurant_page::RestaurantPageScraper,
+};
+use futures::future::join_all;
+use htc::{
+    client::HTCClient,
+    models::health::{HealthReportSchema, HealthStatus, PageHealthSchema},
+    regions::CrousRegion,
+};
+use tabled::{
+    Table, Tabled,
+    settings::{Style, Width, object::Columns},
+};
+
+use crate::actions::{Executable, ExecutionResult};
+
+/// Scrapes a sample of the pages of each region and reports the selectors
+/// that stopped matching, a sign the CROUS changed its layout.
+pub struct DoctorAction {
+    pub targets: Vec<CrousRegion>,
+    /// Restaurant pages checked per region, on top of the list
+    pub sample: usize,
+    pub dry_run: bool,
+
+    pub client: HTCClient,
+    pub fetcher: Arc<dyn Fetcher>,
+    pub profiles: Arc<SelectorProfiles>,
+}
+
+impl Executable for DoctorAction {
+    fn execute(
+        &self,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ExecutionResult>> + Send + '_>>
+    {
+        Box::pin(async move {
+            let mut rows = Vec::new();
+            for target in &self.targets {
+                let report = self.check(*target).await;
+                print_report(*target, &report);
+                let mut row = DisplayableRegionHealth {
+                    region: target.to_string(),
+                    status: report.status,
+                    pages_checked: report.pages_checked,
+                    error: String::new(),
+                };
+                // a region whose report couldn't be recorded doesn't keep
+                // the next ones from being checked
+                if !self.dry_run
+                    && let Err(e) = self.client.put_health(report, *target).await
+                {
+                    row.error = format!("Couldn't record the report : {}", e);
+                }
+                rows.push(row);
+            }
+
+            let mut table = Table::new(&rows);
+            table.with(Style::modern());
+            table.modify(Columns::last(), Width::wrap(60));
+            eprintln!("{}", table);
+
+            let drifted: Vec<&str> = rows
+                .iter()
+                .filter(|row| row.status == HealthStatus::Drifted)
+                .map(|row| row.region.as_str())
+                .collect();
+            let unrecorded: Vec<&str> = rows
+                .iter()
+                .filter(|row| !row.error.is_empty())
+                .map(|row| row.region.as_str())
+                .collect();
+            let mut errors = Vec::new();
+            if !drifted.is_empty() {
+                errors.push(format!("Layout drift in {}", drifted.join(", ")));
+            }
+            if !unrecorded.is_empty() {
+                errors.push(format!(
+                    "Couldn't record the health of {}",
+                    unrecorded.join(", ")
+                ));
+            }
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                Err(ExecutionResult::Failure(errors.join(" ; ")))
+            }
+        })
+    }
+}
+
+impl DoctorAction {
+    pub fn new(
+        targets: Vec<CrousRegion>,
+        sample: usize,
+        dry_run: bool,
+        client: HTCClient,
+        fetcher: Arc<dyn Fetcher>,
+        profiles: Arc<SelectorProfiles>,
+    ) -> Self {
+        Self {
+            targets,
+            sample,
+            dry_run,
+            client,
+            fetcher,
+            profiles,
+        }
+    }
+
+    async fn check(&self, target: CrousRegion) -> HealthReportSchema {
+        let profile = self.profiles.for_region(&target.to_string());
+        let list_url = profile.list_url(target.url());
+        let list_scraper = RestaurantListScraper::new(list_url.clone(), self.fetcher.clone())
+            .with_profile(profile.clone());
+        let mut pages = vec![page_health(&list_url, list_scraper.diagnose().await)];
+
+        // an empty list is already reported by the list selectors
+        let restaurants = list_scraper.scrape().await.unwrap_or_default();
+        let step = (restaurants.len() / self.sample.max(1)).max(1);
+        let sampled = restaurants
+            .iter()
+            .step_by(step)
+            .take(self.sample)
+            .map(|data| {
+                let url = data.crous_url.clone();
+                let scraper = RestaurantPageScraper::new(url.clone(), self.fetcher.clone())
+                    .with_profile(profile.clone());
+                async move { page_health(&url, scraper.diagnose().await) }
+            });
+        pages.extend(join_all(sampled).await);
+
+        HealthReportSchema::from_pages(pages)
+    }
+}
+
+fn page_health<E: ToString>(
+    url: &str,
+    diagnostics: Result<ParseDiagnostics, E>,
+) -> PageHealthSchema {
+    match diagnostics {
+        Ok(diagnostics) => diagnostics.into(),
+        Err(e) => PageHealthSchema::failed(url, e),
+    }
+}
+
+fn print_report(target: CrousRegion, report: &HealthReportSchema) {
+    match report.status {
+        HealthStatus::Healthy => cprintln!(
+            "🩺 <green>{} is healthy, {} page(s) checked</green>",
+            target,
+            report.pages_checked
+        ),
+        HealthStatus::Degraded => cprintln!(
+            "🩺 <yellow>{} is degraded, {} page(s) checked</yellow>",
+            target,
+            report.pages_checked
+        ),
+        HealthStatus::Drifted => cprintln!(
+            "🩺 <red>{} drifted, {} page(s) checked</red>",
+            target,
+            report.pages_checked
+        ),
+    }
+    let rows: Vec<DisplayablePageHealth> = report
+        .pages
+        .iter()
+        .filter(|page| {
+            page.error.is_some()
+                || !page.unknown_sections.is_empty()
+                || page.selectors.values().any(|count| *count == 0)
+        })
+        .map(|page| DisplayablePageHealth {
+            url: page.url.clone(),
+            missing: page
+                .selectors
+                .iter()
+                .filter(|(_, count)| **count == 0)
+                .map(|(selector, _)| selector.as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            unknown_sections: page.unknown_sections.join("\n"),
+            error: page.error.clone().unwrap_or_default(),
+        })
+        .collect();
+    if !rows.is_empty() {
+        let mut table = Table::new(rows);
+        table.with(Style::modern());
+        table.modify(Columns::last(), Width::wrap(60));
+        println!("{}", table);
+    }
+}
+
+#[derive(Tabled)]
+pub struct DisplayablePageHealth {
+    pub url: String,
+    pub missing: String,
+    pub unknown_sections: String,
+    pub error: String,
+}
+
+#[derive(Tabled)]
+pub struct DisplayableRegionHealth {
+    pub region: String,
+    pub status: HealthStatus,
+    pub pages_checked: usize,
+    pub error: String,
+}
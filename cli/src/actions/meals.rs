@@ -1,24 +1,41 @@
-use futures::future::join_all;
+use std::sync::Arc;
+
+use chrono::Local;
+use color_print::ceprintln;
+use crawler::{
+    diagnostics::ParseDiagnostics, fetcher::Fetcher, profile::SelectorProfile,
+    restaurant_page::RestaurantPageScraper,
+};
 use htc::{
-    client::HTCClient,
+    client::{ClientError, HTCClient},
     models::{meals::MealSchema, restaurants::RestaurantSchema},
     regions::CrousRegion,
     sources::meals::RestaurantPageScrapedData,
+    validation::{ValidationRules, validate_meals},
 };
-use crawler::{Scraper, restaurant_page::RestaurantPageScraper};
 use tabled::{
     Table, Tabled,
     settings::{Alignment, Style, object::Columns},
 };
 use thiserror::Error;
 
-use crate::actions::{Executable, ExecutionResult};
+use crate::actions::{
+    Executable, ExecutionResult,
+    output::{self, Capture, OutputFormat, Payload},
+    report::{COLLECT_PASSES, CollectReport, check, with_retries},
+};
+
+/// Name the meals collection acknowledges its pages under in the page
+/// cache, the restaurants collection keeps its own.
+const CACHE_CONSUMER: &str = "meals";
 
 pub struct MealsAction {
     pub target: CrousRegion,
     pub dry_run: bool,
 
     pub client: HTCClient,
+    pub fetcher: Arc<dyn Fetcher>,
+    pub profile: Arc<SelectorProfile>,
 }
 
 #[derive(Debug, Error)]
@@ -28,77 +45,160 @@ pub enum MealsActionResult {
 }
 
 impl MealsAction {
-    pub fn new(target: CrousRegion, dry_run: bool, client: HTCClient) -> Self {
+    pub fn new(
+        target: CrousRegion,
+        dry_run: bool,
+        client: HTCClient,
+        fetcher: Arc<dyn Fetcher>,
+        profile: Arc<SelectorProfile>,
+    ) -> Self {
         Self {
             target,
             dry_run,
             client,
+            fetcher,
+            profile,
         }
     }
 
-    pub async fn collect(&self) -> Result<Vec<Vec<MealSchema>>, MealsActionResult> {
+    /// Meals of every restaurant of the region with the URL of their page.
+    /// Outside of dry runs the pages that didn't change since their last
+    /// upload are skipped, and a page that can't be scraped is retried then
+    /// reported instead of failing the whole region.
+    pub async fn collect(
+        &self,
+    ) -> Result<(Vec<(String, Vec<MealSchema>)>, CollectReport), MealsActionResult> {
         let restaurants_url: Vec<RestaurantSchema> = self
             .client
             .get_restaurants(self.target)
             .await
             .map_err(|e| MealsActionResult::Failure(e.to_string()))?;
 
-        let meals_scrape_futures = restaurants_url.into_iter().map(Self::collect_restaurant);
+        let found = restaurants_url.len();
+        let (results, failures) = with_retries(restaurants_url, COLLECT_PASSES, |restaurant| {
+            Self::collect_restaurant(
+                restaurant,
+                self.fetcher.clone(),
+                self.profile.clone(),
+                !self.dry_run,
+            )
+        })
+        .await;
 
-        let results = join_all(meals_scrape_futures).await;
+        let mut report = CollectReport {
+            found,
+            ..Default::default()
+        };
+        for (restaurant, error) in failures {
+            report.fail(restaurant.url, error);
+        }
         let mut meals = Vec::new();
         for result in results {
-            meals.push(result?);
+            match result {
+                Some((url, restaurant_meals, diagnostics)) => {
+                    report.diagnose(diagnostics);
+                    meals.push((url, restaurant_meals));
+                }
+                None => report.unchanged += 1,
+            }
         }
-        Ok(meals)
+        report.dishes = meals
+            .iter()
+            .flat_map(|(_, meals)| meals)
+            .filter(|meal| !meal.is_notice())
+            .count();
+        Ok((meals, report))
     }
 
     pub async fn collect_restaurant(
         restaurant: RestaurantSchema,
-    ) -> Result<Vec<MealSchema>, MealsActionResult> {
-        let page_data = RestaurantPageScraper::new(restaurant.url.to_string())
-            .scrape()
-            .await
-            .map_err(|e| {
-                MealsActionResult::Failure(format!("Failed to scraper restaurant page : {}", e))
-            })?;
+        fetcher: Arc<dyn Fetcher>,
+        profile: Arc<SelectorProfile>,
+        only_changed: bool,
+    ) -> Result<Option<(String, Vec<MealSchema>, ParseDiagnostics)>, String> {
+        let url = restaurant.url.to_string();
+        let scraper = RestaurantPageScraper::new(url.clone(), fetcher).with_profile(profile);
+        let page_data = if only_changed {
+            scraper.scrape_if_changed(CACHE_CONSUMER).await
+        } else {
+            scraper.scrape_diagnosed().await.map(Some)
+        }
+        .map_err(|e| e.to_string())?;
+        let Some((page_data, diagnostics)) = page_data else {
+            return Ok(None);
+        };
         let scraped_data = RestaurantPageScrapedData {
             restaurant,
             page: page_data,
         };
         let meals: Vec<MealSchema> = scraped_data.into();
-        Ok(meals)
+        Ok(Some((url, meals, diagnostics)))
     }
 
-    async fn execute_inner(&self) -> Result<(), MealsActionResult> {
-        let meals = self.collect().await.map_err(|e| {
-            MealsActionResult::Failure(format!("Failed to collect restaurant page data : {:?}", e))
+    /// Collects the meals of the region then captures them on dry runs or
+    /// uploads them, `Err` when the region as a whole failed.
+    pub async fn run(&self) -> Result<CollectReport, ExecutionResult> {
+        let (meals, mut report) = self.collect().await.map_err(|e| {
+            ExecutionResult::Failure(format!("Failed to collect restaurant page data : {:?}", e))
         })?;
 
         if self.dry_run {
-            let table_data: Vec<&MealSchema> = meals.iter().flatten().collect();
-            let table_data = table_data.iter().map(|meal| DisplayableMeal {
-                meal_type: meal.meal_type.clone(),
-                foodies: meal.foodies.clone().unwrap_or("".to_string()),
-                date: meal.date.clone().unwrap_or("".to_string()),
-                restaurant_id: meal.restaurant_id.to_string(),
-            });
-
-            let mut table = Table::new(table_data);
-            table.with(Style::modern());
-            table.modify(Columns::first(), Alignment::right());
-            println!("{}", table);
+            report.succeeded = meals.len();
+            // one capture per upload, empty pages aren't uploaded
+            report.captures = meals
+                .into_iter()
+                .filter(|(_, meals)| !meals.is_empty())
+                .map(|(_, meals)| Capture {
+                    region: self.target.to_string(),
+                    payload: Payload::Meals(meals),
+                })
+                .collect();
         } else {
-            for meals_by_restaurant in meals {
+            let today = Local::now().date_naive();
+            for (url, meals_by_restaurant) in meals {
                 if !meals_by_restaurant.is_empty() {
-                    let _ = self
+                    let restaurant_id = meals_by_restaurant[0].restaurant_id.clone();
+                    let current: Vec<MealSchema> = self
+                        .client
+                        .get_meals(self.target, &restaurant_id)
+                        .await
+                        .unwrap_or_default()
+                        .into_iter()
+                        .flat_map(|menu| menu.into_meals(&restaurant_id))
+                        .collect();
+                    let validation = validate_meals(
+                        &meals_by_restaurant,
+                        &current,
+                        today,
+                        &ValidationRules::default(),
+                    );
+                    if let Err(e) = check(&validation) {
+                        report.fail(url, e);
+                        continue;
+                    }
+                    match self
                         .client
                         .put_meals(meals_by_restaurant, self.target)
-                        .await;
+                        .await
+                    {
+                        Ok(_) => {}
+                        Err(ClientError::SyncSkipped) => {
+                            ceprintln!("⏭️ <yellow>Meals unchanged, sync skipped</yellow>");
+                            report.skipped += 1;
+                        }
+                        Err(e) => {
+                            report.fail(url, e);
+                            continue;
+                        }
+                    }
+                }
+                report.succeeded += 1;
+                if let Err(e) = self.fetcher.acknowledge(&url, CACHE_CONSUMER) {
+                    ceprintln!("⚠️ <yellow>Couldn't update the page cache : {}</yellow>", e);
                 }
             }
         }
-        Ok(())
+        Ok(report)
     }
 }
 
@@ -108,13 +208,32 @@ impl Executable for MealsAction {
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ExecutionResult>> + Send + '_>>
     {
         Box::pin(async move {
-            self.execute_inner()
-                .await
-                .map_err(|e| ExecutionResult::Failure(e.to_string()))
+            let report = self.run().await?;
+            output::write(OutputFormat::Table, &report.captures)
+                .map_err(ExecutionResult::Failure)?;
+            report.print("restaurants");
+            report.into_result()
         })
     }
 }
 
+pub fn table(meals: &[&MealSchema]) -> Table {
+    let table_data = meals.iter().map(|meal| DisplayableMeal {
+        meal_type: meal.meal_type.clone(),
+        foodies: meal
+            .foodies
+            .clone()
+            .or(meal.status.as_ref().map(ToString::to_string))
+            .unwrap_or("".to_string()),
+        date: meal.date.clone().unwrap_or("".to_string()),
+        restaurant_id: meal.restaurant_id.to_string(),
+    });
+    let mut table = Table::new(table_data);
+    table.with(Style::modern());
+    table.modify(Columns::first(), Alignment::right());
+    table
+}
+
 #[derive(Tabled)]
 pub struct DisplayableMeal {
     pub meal_type: String,
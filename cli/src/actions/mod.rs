@@ -2,11 +2,20 @@ use std::{future::Future, pin::Pin};
 
 use thiserror::Error;
 
+pub mod audit;
 pub mod config_gen;
+pub mod doctor;
 pub mod meals;
+pub mod output;
+pub mod push;
+pub mod regions;
+pub mod report;
 pub mod restaurants;
 pub mod schedule;
 pub mod schools;
+pub mod signing;
+pub mod tokens;
+pub mod webhooks;
 
 pub trait Executable {
     fn execute(&self) -> Pin<Box<dyn Future<Output = Result<(), ExecutionResult>> + Send + '_>>;
@@ -18,4 +27,6 @@ pub enum ExecutionResult {
     Success,
     #[error("Failed : {0}")]
     Failure(String),
+    #[error("{0} restaurant(s) failed")]
+    PartialFailure(usize),
 }
@@ -0,0 +1,268 @@
+use std::{
+    fmt::Display,
+    io::{self, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use htc::models::{meals::MealSchema, restaurants::RestaurantSchema};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::actions::{meals, restaurants};
+
+/// How a dry run prints what it would have uploaded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    /// One JSON array of captures, the format `push --from` reads back
+    Json,
+    /// One capture per line
+    Jsonl,
+    /// One row per restaurant or meal
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "jsonl" => Ok(OutputFormat::Jsonl),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(format!("unknown output format: {}", s)),
+        }
+    }
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Table => f.write_str("table"),
+            OutputFormat::Json => f.write_str("json"),
+            OutputFormat::Jsonl => f.write_str("jsonl"),
+            OutputFormat::Csv => f.write_str("csv"),
+        }
+    }
+}
+
+/// Payload of one upload, exactly as it would be signed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "payload", rename_all = "lowercase")]
+pub enum Payload {
+    Restaurants(Vec<RestaurantSchema>),
+    Meals(Vec<MealSchema>),
+}
+
+impl Payload {
+    /// Whether there is nothing to upload.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Payload::Restaurants(restaurants) => restaurants.is_empty(),
+            Payload::Meals(meals) => meals.is_empty(),
+        }
+    }
+}
+
+/// An upload a dry run skipped, with the region it was meant for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Capture {
+    pub region: String,
+    #[serde(flatten)]
+    pub payload: Payload,
+}
+
+/// Prints the captures of a dry run on stdout.
+pub fn write(format: OutputFormat, captures: &[Capture]) -> Result<(), String> {
+    write_to(&mut io::stdout().lock(), format, captures)
+}
+
+fn write_to(
+    out: &mut impl Write,
+    format: OutputFormat,
+    captures: &[Capture],
+) -> Result<(), String> {
+    let io_error = |e: io::Error| e.to_string();
+    match format {
+        OutputFormat::Table => {
+            let restaurants: Vec<&RestaurantSchema> = captures
+                .iter()
+                .filter_map(|capture| match &capture.payload {
+                    Payload::Restaurants(restaurants) => Some(restaurants),
+                    Payload::Meals(_) => None,
+                })
+                .flatten()
+                .collect();
+            let meals: Vec<&MealSchema> = captures
+                .iter()
+                .filter_map(|capture| match &capture.payload {
+                    Payload::Meals(meals) => Some(meals),
+                    Payload::Restaurants(_) => None,
+                })
+                .flatten()
+                .collect();
+            if !restaurants.is_empty() {
+                writeln!(out, "{}", restaurants::table(&restaurants)).map_err(io_error)?;
+            }
+            if !meals.is_empty() {
+                writeln!(out, "{}", meals::table(&meals)).map_err(io_error)?;
+            }
+        }
+        OutputFormat::Json => {
+            let json = serde_json::to_string_pretty(captures).map_err(|e| e.to_string())?;
+            writeln!(out, "{}", json).map_err(io_error)?;
+        }
+        OutputFormat::Jsonl => {
+            for capture in captures {
+                let json = serde_json::to_string(capture).map_err(|e| e.to_string())?;
+                writeln!(out, "{}", json).map_err(io_error)?;
+            }
+        }
+        OutputFormat::Csv => write!(out, "{}", csv(captures)?).map_err(io_error)?,
+    }
+    Ok(())
+}
+
+/// Captures written by `write`, as a JSON array, a single capture or one
+/// capture per line.
+pub fn read(path: &Path) -> Result<Vec<Capture>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Couldn't read {} : {}", path.display(), e))?;
+    let invalid =
+        |e: serde_json::Error| format!("{} isn't a captured dry run : {}", path.display(), e);
+    match content.trim_start().chars().next() {
+        Some('[') => serde_json::from_str(&content).map_err(invalid),
+        _ => serde_json::Deserializer::from_str(&content)
+            .into_iter::<Capture>()
+            .collect::<Result<_, _>>()
+            .map_err(invalid),
+    }
+}
+
+/// One row per restaurant or meal under the fields of its schema, arrays
+/// and objects kept as JSON.
+fn csv(captures: &[Capture]) -> Result<String, String> {
+    let mut rows: Vec<(&str, Value)> = Vec::new();
+    for capture in captures {
+        let items = match &capture.payload {
+            Payload::Restaurants(restaurants) => serde_json::to_value(restaurants),
+            Payload::Meals(meals) => serde_json::to_value(meals),
+        }
+        .map_err(|e| e.to_string())?;
+        if let Value::Array(items) = items {
+            rows.extend(
+                items
+                    .into_iter()
+                    .map(|item| (capture.region.as_str(), item)),
+            );
+        }
+    }
+
+    let mut columns: Vec<String> = Vec::new();
+    for (_, item) in &rows {
+        if let Value::Object(fields) = item {
+            for field in fields.keys() {
+                if !columns.contains(field) {
+                    columns.push(field.clone());
+                }
+            }
+        }
+    }
+
+    let mut out = String::new();
+    let header: Vec<String> = std::iter::once("region")
+        .chain(columns.iter().map(String::as_str))
+        .map(csv_field)
+        .collect();
+    out.push_str(&header.join(","));
+    out.push('\n');
+    for (region, item) in &rows {
+        let cells: Vec<String> = std::iter::once(csv_field(region))
+            .chain(columns.iter().map(|column| match item.get(column) {
+                None | Some(Value::Null) => String::new(),
+                Some(Value::String(value)) => csv_field(value),
+                Some(value) => csv_field(&value.to_string()),
+            }))
+            .collect();
+        out.push_str(&cells.join(","));
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn captures() -> Vec<Capture> {
+        vec![
+            Capture {
+                region: "Montpellier".to_string(),
+                payload: Payload::Restaurants(vec![RestaurantSchema {
+                    id: "brasserie-triolet".to_string(),
+                    name: "Brasserie Triolet".to_string(),
+                    url: "https://www.crous-montpellier.fr/restaurant/brasserie-triolet/"
+                        .to_string(),
+                    payment_methods: vec!["IZLY".to_string()],
+                    ..Default::default()
+                }]),
+            },
+            Capture {
+                region: "Montpellier".to_string(),
+                payload: Payload::Meals(vec![MealSchema {
+                    meal_type: "Déjeuner".to_string(),
+                    foodies: Some("Frites, \"maison\"\nSalade".to_string()),
+                    date: Some("2026-03-02".to_string()),
+                    restaurant_id: "brasserie-triolet".to_string(),
+                    status: None,
+                }]),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_json_and_jsonl_round_trip() {
+        let captures = captures();
+        for format in [OutputFormat::Json, OutputFormat::Jsonl] {
+            let mut out = Vec::new();
+            write_to(&mut out, format, &captures).unwrap();
+            let path = std::env::temp_dir().join(format!(
+                "htc-captures-{}.{}",
+                std::process::id(),
+                format
+            ));
+            std::fs::write(&path, out).unwrap();
+            let read = read(&path);
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(
+                serde_json::to_value(read.unwrap()).unwrap(),
+                serde_json::to_value(&captures).unwrap(),
+                "{}",
+                format
+            );
+        }
+    }
+
+    #[test]
+    fn test_csv_escapes_fields() {
+        let csv = csv(&captures()[1..]).unwrap();
+        assert_eq!(
+            csv,
+            "region,date,foodies,meal_type,restaurant_id,status\n\
+             Montpellier,2026-03-02,\"Frites, \"\"maison\"\"\nSalade\",Déjeuner,brasserie-triolet,\n"
+        );
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("plain"), "plain");
+    }
+}
@@ -0,0 +1,145 @@
+use std::path::PathBuf;
+
+use chrono::Local;
+use color_print::ceprintln;
+use htc::{
+    client::{ClientError, HTCClient},
+    models::meals::MealSchema,
+    regions::CrousRegion,
+    validation::{ValidationRules, validate_meals, validate_restaurants},
+};
+use tabled::{
+    Table,
+    settings::{Style, Width, object::Columns},
+};
+
+use crate::actions::{
+    Executable, ExecutionResult,
+    output::{self, Capture, Payload},
+    report::{CollectReport, check},
+};
+
+/// Uploads the captures of a dry run written with `--output json` or
+/// `jsonl`, validated like a collection would before.
+pub struct PushAction {
+    pub from: PathBuf,
+
+    pub client: HTCClient,
+}
+
+impl Executable for PushAction {
+    fn execute(
+        &self,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ExecutionResult>> + Send + '_>>
+    {
+        Box::pin(async move {
+            let captures = output::read(&self.from).map_err(ExecutionResult::Failure)?;
+            let mut report = CollectReport::default();
+            for capture in captures {
+                let label = label(&capture);
+                // an empty payload has no restaurant to check meals against
+                if capture.payload.is_empty() {
+                    ceprintln!("⏭️ <yellow>{} is empty, nothing to push</yellow>", label);
+                    report.skipped += 1;
+                    continue;
+                }
+                match self.push(capture).await {
+                    Ok(Pushed::Uploaded) => report.succeeded += 1,
+                    Ok(Pushed::Skipped) => {
+                        ceprintln!("⏭️ <yellow>{} unchanged, sync skipped</yellow>", label);
+                        report.skipped += 1;
+                    }
+                    Err(e) => report.fail(label, e),
+                }
+            }
+
+            ceprintln!(
+                "📤 <bold>{} payload(s) pushed, {} skipped, {} failed</bold>",
+                report.succeeded,
+                report.skipped,
+                report.failures.len()
+            );
+            if !report.failures.is_empty() {
+                let mut table = Table::new(&report.failures);
+                table.with(Style::modern());
+                table.modify(Columns::last(), Width::wrap(60));
+                eprintln!("{}", table);
+            }
+            report.into_result()
+        })
+    }
+}
+
+impl PushAction {
+    pub fn new(from: PathBuf, client: HTCClient) -> Self {
+        Self { from, client }
+    }
+
+    async fn push(&self, capture: Capture) -> Result<Pushed, String> {
+        let region: CrousRegion = capture.region.parse()?;
+        let result = match capture.payload {
+            Payload::Restaurants(restaurants) => {
+                let current = self
+                    .client
+                    .get_restaurants(region)
+                    .await
+                    .unwrap_or_default();
+                check(&validate_restaurants(
+                    region,
+                    &restaurants,
+                    &current,
+                    &ValidationRules::default(),
+                ))?;
+                self.client.put_restaurants(restaurants, region).await
+            }
+            Payload::Meals(meals) => {
+                let restaurant_id = meals
+                    .first()
+                    .map(|meal| meal.restaurant_id.clone())
+                    .unwrap_or_default();
+                let current: Vec<MealSchema> = self
+                    .client
+                    .get_meals(region, &restaurant_id)
+                    .await
+                    .unwrap_or_default()
+                    .into_iter()
+                    .flat_map(|menu| menu.into_meals(&restaurant_id))
+                    .collect();
+                check(&validate_meals(
+                    &meals,
+                    &current,
+                    Local::now().date_naive(),
+                    &ValidationRules::default(),
+                ))?;
+                self.client.put_meals(meals, region).await
+            }
+        };
+        match result {
+            Ok(()) => Ok(Pushed::Uploaded),
+            Err(ClientError::SyncSkipped) => Ok(Pushed::Skipped),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+enum Pushed {
+    Uploaded,
+    /// The API already had this batch
+    Skipped,
+}
+
+/// Names a capture in the failures, `<region>/meals/<restaurant id>` for
+/// meals.
+fn label(capture: &Capture) -> String {
+    match &capture.payload {
+        Payload::Restaurants(_) => format!("{}/restaurants", capture.region),
+        Payload::Meals(meals) => format!(
+            "{}/meals/{}",
+            capture.region,
+            meals
+                .first()
+                .map(|meal| meal.restaurant_id.as_str())
+                .unwrap_or_default()
+        ),
+    }
+}
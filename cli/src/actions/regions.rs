@@ -0,0 +1,411 @@
+use std::{fmt::Display, path::PathBuf, str::FromStr, sync::Arc};
+
+use color_print::ceprintln;
+use crawler::{fetcher::Fetcher, profile::SelectorProfiles};
+use futures::{StreamExt, stream};
+use htc::{client::HTCClient, regions::CrousRegion};
+use serde::Serialize;
+use tabled::{
+    Table, Tabled,
+    settings::{Alignment, Style, Width, object::Columns},
+};
+
+use crate::actions::{
+    Executable, ExecutionResult,
+    meals::MealsAction,
+    output::{self, OutputFormat},
+    report::{CollectReport, PageFailure},
+    restaurants::RestaurantsAction,
+};
+
+/// A `--target` value, either a region or `all` of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RegionTarget {
+    All,
+    Region(CrousRegion),
+}
+
+impl FromStr for RegionTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("all") {
+            return Ok(RegionTarget::All);
+        }
+        s.parse().map(RegionTarget::Region)
+    }
+}
+
+impl RegionTarget {
+    /// Regions of the targets in the order given, each once.
+    pub fn expand(targets: &[RegionTarget]) -> Vec<CrousRegion> {
+        let mut regions: Vec<CrousRegion> = Vec::new();
+        for target in targets {
+            let expanded = match target {
+                RegionTarget::All => CrousRegion::all(),
+                RegionTarget::Region(region) => std::slice::from_ref(region),
+            };
+            for region in expanded {
+                if !regions.contains(region) {
+                    regions.push(*region);
+                }
+            }
+        }
+        regions
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Collection {
+    Restaurants,
+    Meals,
+}
+
+impl Display for Collection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Collection::Restaurants => f.write_str("restaurants"),
+            Collection::Meals => f.write_str("meals"),
+        }
+    }
+}
+
+/// Runs the restaurants or meals collection of several regions side by
+/// side, at most `jobs` at a time, and sums them up.
+pub struct RegionsAction {
+    pub collection: Collection,
+    pub targets: Vec<CrousRegion>,
+    pub dry_run: bool,
+    /// How the dry run prints what it would have uploaded
+    pub output: OutputFormat,
+    /// Regions collected at the same time
+    pub jobs: usize,
+    /// Where to write the JSON report of the run
+    pub report: Option<PathBuf>,
+
+    pub client: HTCClient,
+    pub fetcher: Arc<dyn Fetcher>,
+    pub profiles: Arc<SelectorProfiles>,
+}
+
+/// Outcome of one region, as written in the JSON report.
+#[derive(Debug, Serialize)]
+pub struct RegionReport {
+    pub region: String,
+    /// Set when the region couldn't be collected at all
+    pub error: Option<String>,
+    #[serde(flatten)]
+    pub report: CollectReport,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RunReport {
+    pub collection: Collection,
+    pub dry_run: bool,
+    pub regions: Vec<RegionReport>,
+}
+
+#[derive(Tabled)]
+pub struct DisplayableRegionReport {
+    pub region: String,
+    pub restaurants: usize,
+    pub dishes: usize,
+    pub skipped: usize,
+    pub failures: usize,
+    /// Pages some selectors matched nothing on
+    pub unhealthy: usize,
+    pub error: String,
+}
+
+impl From<&RegionReport> for DisplayableRegionReport {
+    fn from(region: &RegionReport) -> Self {
+        DisplayableRegionReport {
+            region: region.region.clone(),
+            restaurants: region.report.found,
+            dishes: region.report.dishes,
+            skipped: region.report.skipped,
+            failures: region.report.failures.len(),
+            unhealthy: region.report.diagnostics.len(),
+            error: region.error.clone().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Tabled)]
+pub struct DisplayableRegionFailure {
+    pub region: String,
+    #[tabled(inline)]
+    pub failure: PageFailure,
+}
+
+impl Executable for RegionsAction {
+    fn execute(
+        &self,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ExecutionResult>> + Send + '_>>
+    {
+        Box::pin(async move {
+            let mut regions: Vec<RegionReport> = stream::iter(self.targets.iter().copied())
+                .map(|target| self.run(target))
+                .buffer_unordered(self.jobs.max(1))
+                .collect()
+                .await;
+            sort_by_targets(&mut regions, &self.targets);
+
+            let captures: Vec<_> = regions
+                .iter_mut()
+                .flat_map(|region| std::mem::take(&mut region.report.captures))
+                .collect();
+            if self.dry_run {
+                output::write(self.output, &captures).map_err(ExecutionResult::Failure)?;
+            }
+
+            if let [region] = regions.as_slice() {
+                if region.error.is_none() {
+                    region.report.print(&self.collection.to_string());
+                }
+            } else {
+                print_summary(&regions);
+            }
+
+            let result = summarize(&regions);
+            if let Some(path) = &self.report {
+                let report = RunReport {
+                    collection: self.collection,
+                    dry_run: self.dry_run,
+                    regions,
+                };
+                let json = serde_json::to_string_pretty(&report)
+                    .map_err(|e| ExecutionResult::Failure(e.to_string()))?;
+                std::fs::write(path, json).map_err(|e| {
+                    ExecutionResult::Failure(format!(
+                        "Couldn't write the report to {} : {}",
+                        path.display(),
+                        e
+                    ))
+                })?;
+                ceprintln!("📝 <green>Report written to {}</green>", path.display());
+            }
+            result
+        })
+    }
+}
+
+impl RegionsAction {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        collection: Collection,
+        targets: Vec<CrousRegion>,
+        dry_run: bool,
+        output: OutputFormat,
+        jobs: usize,
+        report: Option<PathBuf>,
+        client: HTCClient,
+        fetcher: Arc<dyn Fetcher>,
+        profiles: Arc<SelectorProfiles>,
+    ) -> Self {
+        Self {
+            collection,
+            targets,
+            dry_run,
+            output,
+            jobs,
+            report,
+            client,
+            fetcher,
+            profiles,
+        }
+    }
+
+    async fn run(&self, target: CrousRegion) -> RegionReport {
+        let profile = self.profiles.for_region(&target.to_string());
+        let client = self.client.clone();
+        let fetcher = self.fetcher.clone();
+        // a progress bar would end up in the machine readable output
+        let progress = self.output == OutputFormat::Table;
+        let result = match self.collection {
+            Collection::Restaurants => {
+                RestaurantsAction::new(target, self.dry_run, client, fetcher, profile)
+                    .with_progress(progress)
+                    .run()
+                    .await
+            }
+            Collection::Meals => {
+                MealsAction::new(target, self.dry_run, client, fetcher, profile)
+                    .run()
+                    .await
+            }
+        };
+        match result {
+            Ok(report) => RegionReport {
+                region: target.to_string(),
+                error: None,
+                report,
+            },
+            Err(e) => RegionReport {
+                region: target.to_string(),
+                error: Some(match e {
+                    ExecutionResult::Failure(error) => error,
+                    e => e.to_string(),
+                }),
+                report: CollectReport::default(),
+            },
+        }
+    }
+}
+
+/// Puts the reports back in the order the regions were given, they finish
+/// in any order.
+fn sort_by_targets(regions: &mut [RegionReport], targets: &[CrousRegion]) {
+    regions.sort_by_key(|region| {
+        targets
+            .iter()
+            .position(|target| target.to_string() == region.region)
+    });
+}
+
+fn print_summary(regions: &[RegionReport]) {
+    let mut table = Table::new(regions.iter().map(DisplayableRegionReport::from));
+    table.with(Style::modern());
+    table.modify(Columns::new(1..6), Alignment::right());
+    eprintln!("{}", table);
+
+    let failures: Vec<DisplayableRegionFailure> = regions
+        .iter()
+        .flat_map(|region| {
+            region
+                .report
+                .failures
+                .iter()
+                .map(|failure| DisplayableRegionFailure {
+                    region: region.region.clone(),
+                    failure: failure.clone(),
+                })
+        })
+        .collect();
+    if !failures.is_empty() {
+        let mut table = Table::new(failures);
+        table.with(Style::modern());
+        table.modify(Columns::last(), Width::wrap(60));
+        eprintln!("{}", table);
+    }
+}
+
+/// A region that failed as a whole fails the run, failed restaurants only
+/// make it partial.
+fn summarize(regions: &[RegionReport]) -> Result<(), ExecutionResult> {
+    let failed: Vec<&str> = regions
+        .iter()
+        .filter(|region| region.error.is_some())
+        .map(|region| region.region.as_str())
+        .collect();
+    if let [region] = regions
+        && let Some(error) = &region.error
+    {
+        return Err(ExecutionResult::Failure(error.clone()));
+    }
+    if !failed.is_empty() {
+        return Err(ExecutionResult::Failure(format!(
+            "Couldn't collect {}",
+            failed.join(", ")
+        )));
+    }
+    let failures: usize = regions
+        .iter()
+        .map(|region| region.report.failures.len())
+        .sum();
+    if failures > 0 {
+        return Err(ExecutionResult::PartialFailure(failures));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(region: CrousRegion, error: Option<&str>, failures: usize) -> RegionReport {
+        let mut report = CollectReport::default();
+        for i in 0..failures {
+            report.fail(format!("https://example.com/{}", i), "timeout");
+        }
+        RegionReport {
+            region: region.to_string(),
+            error: error.map(str::to_string),
+            report,
+        }
+    }
+
+    #[test]
+    fn test_expand_targets() {
+        let lyon = RegionTarget::Region(CrousRegion::Lyon);
+        let amiens = RegionTarget::Region(CrousRegion::Amiens);
+        assert_eq!(
+            RegionTarget::expand(&[lyon, amiens, lyon]),
+            vec![CrousRegion::Lyon, CrousRegion::Amiens]
+        );
+
+        // `all` keeps the regions given before it first
+        let expanded = RegionTarget::expand(&[lyon, RegionTarget::All, amiens]);
+        assert_eq!(expanded.len(), CrousRegion::all().len());
+        assert_eq!(expanded[0], CrousRegion::Lyon);
+        assert_eq!(
+            expanded
+                .iter()
+                .filter(|r| **r == CrousRegion::Amiens)
+                .count(),
+            1
+        );
+
+        assert_eq!("ALL".parse(), Ok(RegionTarget::All));
+        assert_eq!("Lyon".parse(), Ok(lyon));
+        assert!("Atlantis".parse::<RegionTarget>().is_err());
+    }
+
+    #[test]
+    fn test_summarize() {
+        assert!(summarize(&[region(CrousRegion::Lyon, None, 0)]).is_ok());
+        assert!(matches!(
+            summarize(&[
+                region(CrousRegion::Lyon, None, 2),
+                region(CrousRegion::Amiens, None, 1)
+            ]),
+            Err(ExecutionResult::PartialFailure(3))
+        ));
+        // a single region keeps its own error
+        assert!(matches!(
+            summarize(&[region(CrousRegion::Lyon, Some("unreachable"), 0)]),
+            Err(ExecutionResult::Failure(e)) if e == "unreachable"
+        ));
+        // a failed region outweighs failed restaurants elsewhere
+        assert!(matches!(
+            summarize(&[
+                region(CrousRegion::Lyon, None, 2),
+                region(CrousRegion::Amiens, Some("unreachable"), 0)
+            ]),
+            Err(ExecutionResult::Failure(e)) if e == "Couldn't collect Amiens"
+        ));
+    }
+
+    #[test]
+    fn test_reports_follow_the_targets() {
+        let targets = [CrousRegion::Lyon, CrousRegion::Amiens, CrousRegion::Corse];
+        let mut regions = vec![
+            region(CrousRegion::Corse, None, 0),
+            region(CrousRegion::Lyon, None, 0),
+            region(CrousRegion::Amiens, None, 0),
+        ];
+        sort_by_targets(&mut regions, &targets);
+        let order: Vec<&str> = regions.iter().map(|r| r.region.as_str()).collect();
+        assert_eq!(order, vec!["Lyon", "Amiens", "Corse"]);
+    }
+
+    #[test]
+    fn test_collection_name() {
+        assert_eq!(Collection::Meals.to_string(), "meals");
+        assert_eq!(
+            serde_json::to_value(Collection::Restaurants).unwrap(),
+            Collection::Restaurants.to_string()
+        );
+    }
+}
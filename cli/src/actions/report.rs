@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use color_print::ceprintln;
+use crawler::diagnostics::ParseDiagnostics;
+use futures::future::join_all;
+use htc::validation::ValidationReport;
+use serde::Serialize;
+use tabled::{
+    Table, Tabled,
+    settings::{Style, Width, object::Columns},
+};
+
+use crate::actions::{ExecutionResult, output::Capture};
+
+/// Passes over the restaurants of a region, the ones failing on the first
+/// pass are tried again at the end.
+pub const COLLECT_PASSES: usize = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Serialize, Tabled)]
+pub struct PageFailure {
+    pub url: String,
+    pub error: String,
+}
+
+/// Outcome of a collection run, restaurant by restaurant.
+#[derive(Debug, Default, Serialize)]
+pub struct CollectReport {
+    /// Restaurants listed, whether they could be collected or not
+    pub found: usize,
+    /// Dishes scraped from the menus, notices left out
+    pub dishes: usize,
+    pub succeeded: usize,
+    pub unchanged: usize,
+    /// Uploads the API skipped as they matched its current batch
+    pub skipped: usize,
+    pub failures: Vec<PageFailure>,
+    /// Pages some selectors matched nothing on, `crousctl doctor` tells
+    /// whether the layout drifted
+    pub diagnostics: Vec<ParseDiagnostics>,
+    /// What a dry run would have uploaded
+    #[serde(skip)]
+    pub captures: Vec<Capture>,
+}
+
+impl CollectReport {
+    pub fn fail(&mut self, url: impl Into<String>, error: impl ToString) {
+        self.failures.push(PageFailure {
+            url: url.into(),
+            error: error.to_string(),
+        });
+    }
+
+    /// Keeps the diagnostics of the page unless it's healthy.
+    pub fn diagnose(&mut self, diagnostics: ParseDiagnostics) {
+        if !diagnostics.is_healthy() {
+            self.diagnostics.push(diagnostics);
+        }
+    }
+
+    pub fn print(&self, entity: &str) {
+        ceprintln!(
+            "📋 <bold>{} {} collected, {} unchanged, {} failed</bold>",
+            self.succeeded,
+            entity,
+            self.unchanged,
+            self.failures.len()
+        );
+        for diagnostics in &self.diagnostics {
+            ceprintln!(
+                "🩺 <yellow>{} : nothing matched [{}], unknown sections [{}]</yellow>",
+                diagnostics.url,
+                diagnostics.missing().join(", "),
+                diagnostics.unknown_sections.join(", ")
+            );
+        }
+        if !self.failures.is_empty() {
+            let mut table = Table::new(&self.failures);
+            table.with(Style::modern());
+            table.modify(Columns::last(), Width::wrap(60));
+            eprintln!("{}", table);
+        }
+    }
+
+    /// `PartialFailure` as soon as one restaurant failed.
+    pub fn into_result(self) -> Result<(), ExecutionResult> {
+        if self.failures.is_empty() {
+            Ok(())
+        } else {
+            Err(ExecutionResult::PartialFailure(self.failures.len()))
+        }
+    }
+}
+
+/// Prints what the validation found, `Err` when the batch mustn't be
+/// uploaded.
+pub fn check(validation: &ValidationReport) -> Result<(), String> {
+    for warning in validation.warnings() {
+        ceprintln!("⚠️ <yellow>{}</yellow>", warning);
+    }
+    if validation.is_blocking() {
+        for error in validation.errors() {
+            ceprintln!("🚫 <red>{}</red>", error);
+        }
+        return Err(format!("Batch failed validation : {}", validation));
+    }
+    Ok(())
+}
+
+/// Runs `attempt` on every item, then again on the ones that failed, up to
+/// `passes` times. Returns what succeeded and the last error of each item
+/// that never did.
+pub async fn with_retries<I, T, F, Fut>(
+    items: Vec<I>,
+    passes: usize,
+    attempt: F,
+) -> (Vec<T>, Vec<(I, String)>)
+where
+    I: Clone,
+    F: Fn(I) -> Fut,
+    Fut: Future<Output = Result<T, String>>,
+{
+    let mut succeeded = Vec::new();
+    let mut failed: Vec<(I, String)> = Vec::new();
+    let mut pending = items;
+    for pass in 0..passes.max(1) {
+        if pending.is_empty() {
+            break;
+        }
+        if pass > 0 {
+            tokio::time::sleep(RETRY_DELAY).await;
+        }
+        let results = join_all(pending.iter().cloned().map(&attempt)).await;
+        failed.clear();
+        for (item, result) in pending.into_iter().zip(results) {
+            match result {
+                Ok(value) => succeeded.push(value),
+                Err(e) => failed.push((item, e)),
+            }
+        }
+        pending = failed.iter().map(|(item, _)| item.clone()).collect();
+    }
+    (succeeded, failed)
+}
@@ -3,15 +3,17 @@ use std::sync::{
     atomic::{AtomicUsize, Ordering},
 };
 
-use futures::future::join_all;
+use color_print::ceprintln;
+use crawler::{
+    Scraper, diagnostics::ParseDiagnostics, fetcher::Fetcher, profile::SelectorProfile,
+    restaurant_list::RestaurantListScraper, restaurant_page::RestaurantPageScraper,
+};
 use htc::{
-    client::HTCClient,
+    client::{ClientError, HTCClient},
     models::restaurants::RestaurantSchema,
-    regions::{CrousRegion, CrousUrl},
+    regions::CrousRegion,
     sources::restaurants::RestaurantScrapedData,
-};
-use crawler::{
-    Scraper, restaurant_list::RestaurantListScraper, restaurant_page::RestaurantPageScraper,
+    validation::{ValidationRules, validate_restaurants},
 };
 use tabled::{
     Table, Tabled,
@@ -19,13 +21,25 @@ use tabled::{
 };
 use zenity::progress::{Frames, ProgressBar};
 
-use crate::actions::{Executable, ExecutionResult};
+use crate::actions::{
+    Executable, ExecutionResult,
+    output::{self, Capture, OutputFormat, Payload},
+    report::{COLLECT_PASSES, CollectReport, check, with_retries},
+};
+
+/// Name the restaurants collection acknowledges its pages under in the
+/// page cache, the meals collection keeps its own.
+const CACHE_CONSUMER: &str = "restaurants";
 
 pub struct RestaurantsAction {
     pub target: CrousRegion,
     pub dry_run: bool,
+    /// Draw a progress bar while the pages are scraped
+    pub progress: bool,
 
     pub client: HTCClient,
+    pub fetcher: Arc<dyn Fetcher>,
+    pub profile: Arc<SelectorProfile>,
 }
 
 impl Executable for RestaurantsAction {
@@ -34,87 +48,166 @@ impl Executable for RestaurantsAction {
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ExecutionResult>> + Send + '_>>
     {
         Box::pin(async move {
-            let restaurants = self.collect().await.map_err(|e| {
-                ExecutionResult::Failure(format!("Failed to collect restaurant data: {:?}", e))
-            })?;
-
-            if self.dry_run {
-                let table_data = restaurants.iter().map(|restaurant| {
-                    let restaurant: RestaurantSchema = restaurant.clone();
-                    DisplayableRestaurant {
-                        name: restaurant.name,
-                        url: restaurant.url,
-                        city: restaurant.city.unwrap_or_else(|| "N/A".to_string()),
-                        coordinates: restaurant.coordinates.unwrap_or_else(|| "N/A".to_string()),
-                        opening_hours: restaurant
-                            .opening_hours
-                            .unwrap_or_else(|| "N/A".to_string()),
-                    }
-                });
-                let mut table = Table::new(table_data);
-                table.with(Style::modern());
-                table.modify(Columns::first(), Alignment::right());
-                println!("{}", table);
-            } else {
-                let _ = self.client.put_restaurants(restaurants, self.target).await;
-            }
-            Ok(())
+            let report = self.run().await?;
+            output::write(OutputFormat::Table, &report.captures)
+                .map_err(ExecutionResult::Failure)?;
+            report.print("restaurants");
+            report.into_result()
         })
     }
 }
 
 impl RestaurantsAction {
-    pub fn new(target: CrousRegion, dry_run: bool, client: HTCClient) -> Self {
+    pub fn new(
+        target: CrousRegion,
+        dry_run: bool,
+        client: HTCClient,
+        fetcher: Arc<dyn Fetcher>,
+        profile: Arc<SelectorProfile>,
+    ) -> Self {
         Self {
             target,
             dry_run,
+            progress: true,
             client,
+            fetcher,
+            profile,
+        }
+    }
+
+    pub fn with_progress(mut self, progress: bool) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Collects the restaurants of the region then captures them on dry
+    /// runs or uploads them, `Err` when the region as a whole failed.
+    pub async fn run(&self) -> Result<CollectReport, ExecutionResult> {
+        let (restaurants, mut report) = self.collect().await.map_err(|e| {
+            ExecutionResult::Failure(format!("Failed to collect restaurant data: {:?}", e))
+        })?;
+        report.succeeded = restaurants.len();
+
+        if self.dry_run {
+            report.captures.push(Capture {
+                region: self.target.to_string(),
+                payload: Payload::Restaurants(restaurants),
+            });
+            return Ok(report);
         }
+
+        // the list is only acknowledged once every restaurant made it
+        let urls: Vec<String> = report
+            .failures
+            .is_empty()
+            .then(|| self.list_url())
+            .into_iter()
+            .chain(restaurants.iter().map(|restaurant| restaurant.url.clone()))
+            .collect();
+        if restaurants.is_empty() {
+            ceprintln!("⏭️ <yellow>No restaurant collected, nothing to upload</yellow>");
+        } else if !urls
+            .iter()
+            .any(|url| self.fetcher.changed(url, CACHE_CONSUMER))
+        {
+            ceprintln!("⏭️ <yellow>Restaurant pages unchanged, nothing to upload</yellow>");
+            report.unchanged = report.succeeded;
+            report.succeeded = 0;
+        } else {
+            let current = self
+                .client
+                .get_restaurants(self.target)
+                .await
+                .unwrap_or_default();
+            check(&validate_restaurants(
+                self.target,
+                &restaurants,
+                &current,
+                &ValidationRules::default(),
+            ))
+            .map_err(ExecutionResult::Failure)?;
+            match self.client.put_restaurants(restaurants, self.target).await {
+                Err(ClientError::SyncSkipped) => {
+                    ceprintln!("⏭️ <yellow>Restaurants unchanged, sync skipped</yellow>");
+                    report.skipped += 1;
+                }
+                result => {
+                    result.map_err(|e| ExecutionResult::Failure(e.to_string()))?;
+                }
+            }
+            for url in urls {
+                if let Err(e) = self.fetcher.acknowledge(&url, CACHE_CONSUMER) {
+                    ceprintln!("⚠️ <yellow>Couldn't update the page cache : {}</yellow>", e);
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    fn list_url(&self) -> String {
+        self.profile.list_url(self.target.url())
     }
 
-    pub async fn collect(&self) -> Result<Vec<RestaurantSchema>, ExecutionResult> {
-        let url = CrousUrl(self.target.url().to_string()).to_list_url();
+    /// Restaurants of the region, a restaurant whose page can't be scraped
+    /// is retried and then reported instead of failing the whole region.
+    pub async fn collect(&self) -> Result<(Vec<RestaurantSchema>, CollectReport), ExecutionResult> {
+        let url = self.list_url();
 
-        let mut restaurants: Vec<RestaurantSchema> = Vec::new();
-        let list_data = RestaurantListScraper::new(url.to_string())
+        let list_data = RestaurantListScraper::new(url.to_string(), self.fetcher.clone())
+            .with_profile(self.profile.clone())
             .scrape()
             .await
             .map_err(|e| {
                 ExecutionResult::Failure(format!("Failed to scrape restaurant list: {}", e))
             })?;
 
-        let progress = Arc::new(ProgressBar::new(Frames::rect().set_goal(list_data.len())));
-        let uid = progress.get_last();
-        progress.run_all();
+        let progress = self.progress.then(|| {
+            let progress = Arc::new(ProgressBar::new(Frames::rect().set_goal(list_data.len())));
+            let uid = progress.get_last();
+            progress.run_all();
+            (progress, uid)
+        });
 
         let counter = Arc::new(AtomicUsize::new(0));
-        let futures = list_data
-            .into_iter()
-            .map(|data| Self::collect_restaurant(data, progress.clone(), uid, counter.clone()));
-        let results = join_all(futures).await;
-        for result in results {
-            restaurants.push(result?);
-        }
+        let (collected, failures) = with_retries(list_data, COLLECT_PASSES, |data| {
+            Self::collect_restaurant(
+                data,
+                self.fetcher.clone(),
+                self.profile.clone(),
+                progress.clone(),
+                counter.clone(),
+            )
+        })
+        .await;
 
-        Ok(restaurants)
+        let mut report = CollectReport {
+            found: collected.len() + failures.len(),
+            ..Default::default()
+        };
+        for (data, error) in failures {
+            report.fail(data.crous_url, error);
+        }
+        let mut restaurants = Vec::with_capacity(collected.len());
+        for (restaurant, diagnostics) in collected {
+            report.diagnose(diagnostics);
+            restaurants.push(restaurant);
+        }
+        Ok((restaurants, report))
     }
 
     async fn collect_restaurant(
         restaurant_desc: crawler::restaurant_list::RestaurantData,
-        progress_bar: Arc<ProgressBar>,
-        uid: usize,
+        fetcher: Arc<dyn Fetcher>,
+        profile: Arc<SelectorProfile>,
+        progress: Option<(Arc<ProgressBar>, usize)>,
         counter: Arc<AtomicUsize>,
-    ) -> Result<RestaurantSchema, ExecutionResult> {
+    ) -> Result<(RestaurantSchema, ParseDiagnostics), String> {
         let url = &restaurant_desc.crous_url;
-        let page_data = RestaurantPageScraper::new(url.to_string())
-            .scrape()
+        let (page_data, diagnostics) = RestaurantPageScraper::new(url.to_string(), fetcher)
+            .with_profile(profile)
+            .scrape_diagnosed()
             .await
-            .map_err(|e| {
-                ExecutionResult::Failure(format!(
-                    "Failed to scrape restaurant page at {}: {}",
-                    url, e
-                ))
-            })?;
+            .map_err(|e| e.to_string())?;
 
         let scraped_data = RestaurantScrapedData {
             page: page_data,
@@ -122,12 +215,34 @@ impl RestaurantsAction {
         };
 
         let completed = counter.fetch_add(1, Ordering::Relaxed) + 1;
-        progress_bar.set(&uid, &completed);
+        if let Some((progress_bar, uid)) = progress {
+            progress_bar.set(&uid, &completed);
+        }
 
-        Ok(scraped_data.into())
+        Ok((scraped_data.into(), diagnostics))
     }
 }
 
+pub fn table(restaurants: &[&RestaurantSchema]) -> Table {
+    let table_data = restaurants.iter().map(|restaurant| DisplayableRestaurant {
+        name: restaurant.name.clone(),
+        url: restaurant.url.clone(),
+        city: restaurant.city.clone().unwrap_or_else(|| "N/A".to_string()),
+        coordinates: restaurant
+            .coordinates
+            .clone()
+            .unwrap_or_else(|| "N/A".to_string()),
+        opening_hours: restaurant
+            .opening_hours
+            .clone()
+            .unwrap_or_else(|| "N/A".to_string()),
+    });
+    let mut table = Table::new(table_data);
+    table.with(Style::modern());
+    table.modify(Columns::first(), Alignment::right());
+    table
+}
+
 #[derive(Tabled)]
 pub struct DisplayableRestaurant {
     pub name: String,
@@ -136,3 +251,50 @@ pub struct DisplayableRestaurant {
     pub coordinates: String,
     pub opening_hours: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use crawler::fetcher::FixtureFetcher;
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_dry_run_against_fixtures() {
+        let fetcher: Arc<dyn Fetcher> =
+            Arc::new(FixtureFetcher::new("../crawler/src/stubs").unwrap());
+        // never reached on a dry run
+        let client = HTCClient::new(
+            "http://localhost:9".to_string(),
+            String::new(),
+            String::new(),
+        )
+        .unwrap();
+        let report = RestaurantsAction::new(
+            CrousRegion::Montpellier,
+            true,
+            client,
+            fetcher,
+            Arc::default(),
+        )
+        .with_progress(false)
+        .run()
+        .await
+        .unwrap();
+
+        // only the Triolet page is in the fixtures
+        assert_eq!(report.succeeded, 1);
+        assert_eq!(report.found, report.failures.len() + 1);
+        let [capture] = report.captures.as_slice() else {
+            panic!("expected one capture, got {}", report.captures.len());
+        };
+        assert_eq!(capture.region, "Montpellier");
+        let Payload::Restaurants(restaurants) = &capture.payload else {
+            panic!("expected restaurants, got {:?}", capture.payload);
+        };
+        assert_eq!(restaurants.len(), 1);
+        assert_eq!(
+            restaurants[0].url,
+            "https://www.crous-montpellier.fr/restaurant/brasserie-triolet/"
+        );
+    }
+}
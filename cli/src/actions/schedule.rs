@@ -1,6 +1,8 @@
 use std::sync::Arc;
 
 use chrono::Utc;
+use color_print::cprintln;
+use crawler::{fetcher::Fetcher, profile::SelectorProfiles};
 use cron_parser::parse;
 use futures::future::join_all;
 use htc::{client::HTCClient, regions::CrousRegion};
@@ -21,6 +23,8 @@ impl ScheduleAction {
     pub fn try_from_config(
         cron: CronConfig,
         client: HTCClient,
+        fetcher: Arc<dyn Fetcher>,
+        profiles: &SelectorProfiles,
     ) -> Result<Self, ConfigError<'static>> {
         let mut restaurants = Vec::new();
         let mut meals = Vec::new();
@@ -30,7 +34,13 @@ impl ScheduleAction {
                     .parse()
                     .map_err(|_| ConfigError::UnknownRegion(target))?;
                 println!("Scheduling restaurant crawl job for {}", region);
-                let action = RestaurantsAction::new(region, false, client.clone());
+                let action = RestaurantsAction::new(
+                    region,
+                    false,
+                    client.clone(),
+                    fetcher.clone(),
+                    profiles.for_region(&region.to_string()),
+                );
                 restaurants.push(Arc::new(SchedulableAction::new(
                     action,
                     restaurants_config.schedule.clone(),
@@ -44,7 +54,13 @@ impl ScheduleAction {
                     .parse()
                     .map_err(|_| ConfigError::UnknownRegion(target))?;
                 println!("Scheduling meals crawl job for {}", region);
-                let action = MealsAction::new(region, false, client.clone());
+                let action = MealsAction::new(
+                    region,
+                    false,
+                    client.clone(),
+                    fetcher.clone(),
+                    profiles.for_region(&region.to_string()),
+                );
                 meals.push(Arc::new(SchedulableAction::new(
                     action,
                     meals_config.schedule.clone(),
@@ -106,7 +122,10 @@ where
                     _ = tokio::signal::ctrl_c() => { return Ok(()); }
                 }
             }
-            self.executable.execute().await?;
+            // a failed run is reported and the next one still happens
+            if let Err(e) = self.executable.execute().await {
+                cprintln!("💣 <red>Scheduled run failed : {}</red>", e);
+            }
         }
     }
 }
@@ -0,0 +1,128 @@
+use std::path::PathBuf;
+
+use color_print::{ceprintln, cprintln};
+use htc::verifiable::{SignedPayload, key_fingerprint, payload_digest, serialize_payload};
+use serde_json::Value;
+
+use crate::actions::{Executable, ExecutionResult};
+
+/// Signs a JSON file exactly like `HTCClient` does before a push.
+pub struct SignAction {
+    pub input: PathBuf,
+    pub output: Option<PathBuf>,
+    pub private_key: String,
+    pub author: String,
+}
+
+/// Verifies a captured `SignedPayload` like the API does on a push.
+pub struct VerifyAction {
+    pub input: PathBuf,
+    pub public_key: String,
+    pub show_payload: bool,
+}
+
+fn read_json(path: &PathBuf) -> Result<Value, ExecutionResult> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        ExecutionResult::Failure(format!("Couldn't read {} : {}", path.display(), e))
+    })?;
+    serde_json::from_str(&content).map_err(|e| {
+        ExecutionResult::Failure(format!("{} isn't valid JSON : {}", path.display(), e))
+    })
+}
+
+impl SignAction {
+    pub fn new(
+        input: PathBuf,
+        output: Option<PathBuf>,
+        private_key: String,
+        author: String,
+    ) -> Self {
+        Self {
+            input,
+            output,
+            private_key,
+            author,
+        }
+    }
+
+    async fn execute_inner(&self) -> Result<(), ExecutionResult> {
+        let payload = read_json(&self.input)?;
+        let digest = payload_digest(&payload);
+        let signed = SignedPayload::<Value>::sign(payload, &self.private_key, &self.author)
+            .map_err(|e| ExecutionResult::Failure(format!("Couldn't sign payload : {}", e)))?;
+        let signed = serde_json::to_string_pretty(&signed)
+            .map_err(|e| ExecutionResult::Failure(e.to_string()))?;
+
+        match &self.output {
+            Some(output) => {
+                std::fs::write(output, signed).map_err(|e| {
+                    ExecutionResult::Failure(format!("Couldn't write {} : {}", output.display(), e))
+                })?;
+                ceprintln!(
+                    "✍️ <green>Signed payload wrote at {}</green>",
+                    output.display()
+                );
+            }
+            None => println!("{}", signed),
+        }
+        ceprintln!("<bold>author</bold> : {}", self.author);
+        ceprintln!("<bold>digest</bold> : {}", digest);
+        Ok(())
+    }
+}
+
+impl VerifyAction {
+    pub fn new(input: PathBuf, public_key: String, show_payload: bool) -> Self {
+        Self {
+            input,
+            public_key,
+            show_payload,
+        }
+    }
+
+    async fn execute_inner(&self) -> Result<(), ExecutionResult> {
+        let signed: SignedPayload<Value> = serde_json::from_value(read_json(&self.input)?)
+            .map_err(|e| {
+                ExecutionResult::Failure(format!(
+                    "{} isn't a signed payload (payload, author, signature) : {}",
+                    self.input.display(),
+                    e
+                ))
+            })?;
+
+        cprintln!("<bold>author</bold> : {}", signed.author);
+        cprintln!(
+            "<bold>key</bold>    : {}",
+            key_fingerprint(&self.public_key)
+        );
+        cprintln!("<bold>digest</bold> : {}", payload_digest(&signed.payload));
+        if self.show_payload {
+            cprintln!("<bold>signed string</bold> :");
+            println!("{}", serialize_payload(&signed.payload));
+        }
+
+        signed
+            .verify(&self.public_key)
+            .map_err(|e| ExecutionResult::Failure(format!("Verification failed : {}", e)))?;
+        cprintln!("✅ <green>Signature is valid</green>");
+        Ok(())
+    }
+}
+
+impl Executable for SignAction {
+    fn execute(
+        &self,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ExecutionResult>> + Send + '_>>
+    {
+        Box::pin(async move { self.execute_inner().await })
+    }
+}
+
+impl Executable for VerifyAction {
+    fn execute(
+        &self,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ExecutionResult>> + Send + '_>>
+    {
+        Box::pin(async move { self.execute_inner().await })
+    }
+}
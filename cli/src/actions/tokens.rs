@@ -0,0 +1,97 @@
+use chrono::{Duration, Utc};
+use clap::Subcommand;
+use color_print::cprintln;
+use htc::{
+    client::HTCClient,
+    models::tokens::{TokenRequestSchema, TokenRevocationSchema},
+    regions::CrousRegion,
+};
+use uuid::Uuid;
+
+use crate::actions::{Executable, ExecutionResult};
+
+#[derive(Debug, Subcommand, PartialEq, Eq, Hash)]
+pub enum TokensCommand {
+    /// Issue a read-only subscriber token for the /events endpoint
+    Create {
+        #[clap(long, short = 'n')]
+        name: String,
+        /// Restrict the token to a region, can be repeated
+        #[clap(long = "region", short = 'r')]
+        regions: Vec<CrousRegion>,
+        /// Restrict the token to an entity (restaurants, meals, schools), can be repeated
+        #[clap(long = "entity", short = 'e')]
+        entities: Vec<String>,
+        /// Number of days before the token expires, never expires if omitted
+        #[clap(long)]
+        expires_in_days: Option<i64>,
+    },
+    /// Revoke a subscriber token
+    Revoke {
+        #[clap(long, short = 'i')]
+        token_id: Uuid,
+    },
+}
+
+pub struct TokensAction {
+    pub command: TokensCommand,
+
+    pub client: HTCClient,
+}
+
+impl TokensAction {
+    pub fn new(command: TokensCommand, client: HTCClient) -> Self {
+        Self { command, client }
+    }
+
+    async fn execute_inner(&self) -> Result<(), ExecutionResult> {
+        match &self.command {
+            TokensCommand::Create {
+                name,
+                regions,
+                entities,
+                expires_in_days,
+            } => {
+                let request = TokenRequestSchema {
+                    name: name.clone(),
+                    regions: regions.iter().map(|region| region.to_string()).collect(),
+                    entities: entities.clone(),
+                    expires_at: expires_in_days
+                        .map(|days| Utc::now().naive_utc() + Duration::days(days)),
+                    issued_at: Utc::now().timestamp(),
+                };
+                let token = self
+                    .client
+                    .issue_token(request)
+                    .await
+                    .map_err(|e| ExecutionResult::Failure(e.to_string()))?;
+                cprintln!(
+                    "🔑 <green>Token {} issued ({})</green>",
+                    token.name,
+                    token.token_id
+                );
+                cprintln!("<yellow>It won't be displayed again :</yellow>");
+                println!("{}", token.token);
+            }
+            TokensCommand::Revoke { token_id } => {
+                self.client
+                    .revoke_token(TokenRevocationSchema {
+                        token_id: *token_id,
+                    })
+                    .await
+                    .map_err(|e| ExecutionResult::Failure(e.to_string()))?;
+                cprintln!("🗑️ <green>Token {} revoked</green>", token_id);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Executable for TokensAction {
+    fn execute(
+        &self,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ExecutionResult>> + Send + '_>>
+    {
+        Box::pin(async move { self.execute_inner().await })
+    }
+}
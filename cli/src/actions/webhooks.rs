@@ -0,0 +1,212 @@
+use chrono::Utc;
+use clap::Subcommand;
+use color_print::cprintln;
+use htc::{
+    client::HTCClient,
+    models::webhooks::{
+        DeliveryQuerySchema, WebhookAction, WebhookDeliverySchema, WebhookQuerySchema,
+        WebhookRequestSchema, WebhookSchema, WebhookTargetSchema,
+    },
+    regions::CrousRegion,
+};
+use tabled::{
+    Table, Tabled,
+    settings::{Style, Width, object::Columns},
+};
+use uuid::Uuid;
+
+use crate::actions::{Executable, ExecutionResult};
+
+#[derive(Debug, Subcommand, PartialEq, Eq, Hash)]
+pub enum WebhooksCommand {
+    /// Register a webhook notified of every new scrape batch
+    Create {
+        #[clap(long, short = 'n')]
+        name: String,
+        #[clap(long, short = 'u')]
+        url: String,
+        /// Only notify batches of a region, can be repeated
+        #[clap(long = "region", short = 'r')]
+        regions: Vec<CrousRegion>,
+        /// Only notify batches of an entity (restaurants, meals, schools), can be repeated
+        #[clap(long = "entity", short = 'e')]
+        entities: Vec<String>,
+    },
+    /// List the registered webhooks
+    List {
+        /// Only list the webhooks disabled after repeated failures
+        #[clap(long)]
+        disabled: bool,
+    },
+    /// Delete a webhook and its delivery log
+    Delete {
+        #[clap(long, short = 'i')]
+        webhook_id: Uuid,
+    },
+    /// Enable a webhook again after it was disabled
+    Enable {
+        #[clap(long, short = 'i')]
+        webhook_id: Uuid,
+    },
+    /// Show the last delivery attempts of a webhook
+    Deliveries {
+        #[clap(long, short = 'i')]
+        webhook_id: Uuid,
+        #[clap(long, short = 'l', default_value = "50")]
+        limit: i64,
+    },
+}
+
+pub struct WebhooksAction {
+    pub command: WebhooksCommand,
+
+    pub client: HTCClient,
+}
+
+impl WebhooksAction {
+    pub fn new(command: WebhooksCommand, client: HTCClient) -> Self {
+        Self { command, client }
+    }
+
+    async fn execute_inner(&self) -> Result<(), ExecutionResult> {
+        match &self.command {
+            WebhooksCommand::Create {
+                name,
+                url,
+                regions,
+                entities,
+            } => {
+                let request = WebhookRequestSchema {
+                    name: name.clone(),
+                    url: url.clone(),
+                    regions: regions.iter().map(|region| region.to_string()).collect(),
+                    entities: entities.clone(),
+                    issued_at: Utc::now().timestamp(),
+                };
+                let webhook = self
+                    .client
+                    .register_webhook(request)
+                    .await
+                    .map_err(|e| ExecutionResult::Failure(e.to_string()))?;
+                cprintln!(
+                    "🪝 <green>Webhook {} registered ({})</green>",
+                    webhook.name,
+                    webhook.webhook_id
+                );
+                cprintln!("<yellow>Its signing secret won't be displayed again :</yellow>");
+                println!("{}", webhook.secret);
+            }
+            WebhooksCommand::List { disabled } => {
+                let webhooks = self
+                    .client
+                    .get_webhooks(WebhookQuerySchema {
+                        active: disabled.then_some(false),
+                    })
+                    .await
+                    .map_err(|e| ExecutionResult::Failure(e.to_string()))?;
+                let mut table = Table::new(webhooks.into_iter().map(DisplayableWebhook::from));
+                table.with(Style::modern());
+                table.modify(Columns::last(), Width::wrap(40));
+                println!("{}", table);
+            }
+            WebhooksCommand::Delete { webhook_id } => {
+                self.client
+                    .delete_webhook(WebhookTargetSchema::new(*webhook_id, WebhookAction::Delete))
+                    .await
+                    .map_err(|e| ExecutionResult::Failure(e.to_string()))?;
+                cprintln!("🗑️ <green>Webhook {} deleted</green>", webhook_id);
+            }
+            WebhooksCommand::Enable { webhook_id } => {
+                self.client
+                    .enable_webhook(WebhookTargetSchema::new(*webhook_id, WebhookAction::Enable))
+                    .await
+                    .map_err(|e| ExecutionResult::Failure(e.to_string()))?;
+                cprintln!("✅ <green>Webhook {} enabled</green>", webhook_id);
+            }
+            WebhooksCommand::Deliveries { webhook_id, limit } => {
+                let deliveries = self
+                    .client
+                    .get_webhook_deliveries(DeliveryQuerySchema {
+                        webhook_id: *webhook_id,
+                        limit: Some(*limit),
+                    })
+                    .await
+                    .map_err(|e| ExecutionResult::Failure(e.to_string()))?;
+                let mut table = Table::new(deliveries.into_iter().map(DisplayableDelivery::from));
+                table.with(Style::modern());
+                table.modify(Columns::last(), Width::wrap(40));
+                println!("{}", table);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Executable for WebhooksAction {
+    fn execute(
+        &self,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ExecutionResult>> + Send + '_>>
+    {
+        Box::pin(async move { self.execute_inner().await })
+    }
+}
+
+#[derive(Tabled)]
+pub struct DisplayableWebhook {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    pub regions: String,
+    pub entities: String,
+    pub active: bool,
+    pub failures: i32,
+    pub disabled: String,
+}
+
+impl From<WebhookSchema> for DisplayableWebhook {
+    fn from(webhook: WebhookSchema) -> Self {
+        DisplayableWebhook {
+            id: webhook.webhook_id.to_string(),
+            name: webhook.name,
+            url: webhook.url,
+            regions: webhook.regions.join(", "),
+            entities: webhook.entities.join(", "),
+            active: webhook.active,
+            failures: webhook.consecutive_failures,
+            disabled: webhook.disabled_reason.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Tabled)]
+pub struct DisplayableDelivery {
+    pub at: String,
+    pub delivery: String,
+    pub batch: String,
+    pub attempt: i32,
+    pub status: String,
+    pub success: bool,
+    pub duration_ms: i64,
+    pub error: String,
+}
+
+impl From<WebhookDeliverySchema> for DisplayableDelivery {
+    fn from(delivery: WebhookDeliverySchema) -> Self {
+        DisplayableDelivery {
+            at: delivery
+                .delivered_at
+                .map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default(),
+            delivery: delivery.delivery_id.to_string(),
+            batch: delivery.batch_id.to_string(),
+            attempt: delivery.attempt,
+            status: delivery
+                .status_code
+                .map(|status| status.to_string())
+                .unwrap_or_default(),
+            success: delivery.success,
+            duration_ms: delivery.duration_ms,
+            error: delivery.error.unwrap_or_default(),
+        }
+    }
+}
@@ -1,4 +1,10 @@
-use std::path::PathBuf;
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
+use crawler::{
+    profile::{ProfileError, SelectorProfiles},
+    scheduler::CrawlPolicy,
+};
+use htc::client::HTCClientBuilder;
 
 use serde::{Deserialize, Serialize};
 
@@ -13,6 +19,83 @@ pub struct Config {
     pub public_key_data: String,
     pub user: String,
     pub schedule: Option<CronConfig>,
+    pub client: Option<ClientConfig>,
+    pub crawler: Option<CrawlerConfig>,
+}
+
+/// Tuning of the HTTP client used to talk to the API, every field is optional.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct ClientConfig {
+    pub timeout_secs: Option<u64>,
+    pub connect_timeout_secs: Option<u64>,
+    pub max_retries: Option<u32>,
+}
+
+impl ClientConfig {
+    pub fn apply(&self, mut builder: HTCClientBuilder) -> HTCClientBuilder {
+        if let Some(timeout) = self.timeout_secs {
+            builder = builder.timeout(Duration::from_secs(timeout));
+        }
+        if let Some(connect_timeout) = self.connect_timeout_secs {
+            builder = builder.connect_timeout(Duration::from_secs(connect_timeout));
+        }
+        if let Some(max_retries) = self.max_retries {
+            builder = builder.max_retries(max_retries);
+        }
+        builder
+    }
+}
+
+/// How politely the CROUS sites are crawled, every field is optional.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct CrawlerConfig {
+    pub user_agent: Option<String>,
+    pub max_concurrency_per_host: Option<usize>,
+    pub requests_per_second: Option<f64>,
+    pub respect_robots_txt: Option<bool>,
+    pub max_retries: Option<u32>,
+    pub max_backoff_secs: Option<u64>,
+    /// Where crawled pages are cached between runs, `~/.cache/htc/pages` by default.
+    pub cache_dir: Option<PathBuf>,
+    /// Directory of selector profiles (TOML or JSON) loaded over the builtin ones
+    pub profiles_dir: Option<PathBuf>,
+    /// Profile name by region, overriding the regions listed in the profiles
+    pub profiles: Option<BTreeMap<String, String>>,
+}
+
+impl CrawlerConfig {
+    pub fn apply(&self, mut policy: CrawlPolicy) -> CrawlPolicy {
+        if let Some(user_agent) = &self.user_agent {
+            policy.user_agent = user_agent.clone();
+        }
+        if let Some(max_concurrency) = self.max_concurrency_per_host {
+            policy.max_concurrency_per_host = max_concurrency;
+        }
+        if let Some(requests_per_second) = self.requests_per_second {
+            policy.requests_per_second = requests_per_second;
+        }
+        if let Some(respect_robots_txt) = self.respect_robots_txt {
+            policy.respect_robots_txt = respect_robots_txt;
+        }
+        if let Some(max_retries) = self.max_retries {
+            policy.max_retries = max_retries;
+        }
+        if let Some(max_backoff) = self.max_backoff_secs {
+            policy.max_delay = Duration::from_secs(max_backoff);
+        }
+        policy
+    }
+
+    pub fn profiles(&self) -> Result<SelectorProfiles, ProfileError> {
+        let mut profiles = SelectorProfiles::default();
+        if let Some(dir) = &self.profiles_dir {
+            profiles.load_dir(dir)?;
+        }
+        for (region, profile) in self.profiles.iter().flatten() {
+            profiles.assign(region, profile)?;
+        }
+        Ok(profiles)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -58,6 +141,8 @@ impl Config {
             public_key_data: deserialized_config.public_key_data,
             user: deserialized_config.user,
             schedule: deserialized_config.schedule,
+            client: deserialized_config.client,
+            crawler: deserialized_config.crawler,
         })
     }
 
@@ -71,6 +156,8 @@ impl Config {
             public_key_data: certificates.certificate,
             user: user.to_string(),
             schedule: None,
+            client: None,
+            crawler: None,
         })
     }
 
@@ -1,14 +1,35 @@
-use std::{path::PathBuf, process::exit};
+use std::{path::PathBuf, process::exit, sync::Arc};
 
+use base64::prelude::*;
+use chrono::{NaiveDate, Utc};
 use clap::{Parser, Subcommand};
-use color_print::cprintln;
-use htc::{client::HTCClient, regions::CrousRegion};
+use color_print::{ceprintln, cprintln};
+use crawler::{
+    cache::HttpCache,
+    fetcher::{CassetteFetcher, Fetcher, FixtureFetcher, LiveFetcher},
+    profile::SelectorProfiles,
+    scheduler::{CrawlPolicy, CrawlScheduler},
+};
+use htc::{
+    client::HTCClient,
+    models::audit::{AuditOutcome, AuditQuerySchema},
+    regions::CrousRegion,
+};
 
 use crate::{
     actions::{
-        Executable, meals::MealsAction, restaurants::RestaurantsAction, schedule::ScheduleAction,
+        Executable, ExecutionResult,
+        audit::AuditAction,
+        doctor::DoctorAction,
+        output::OutputFormat,
+        push::PushAction,
+        regions::{Collection, RegionTarget, RegionsAction},
+        schedule::ScheduleAction,
+        signing::{SignAction, VerifyAction},
+        tokens::{TokensAction, TokensCommand},
+        webhooks::{WebhooksAction, WebhooksCommand},
     },
-    config::Config,
+    config::{Config, CrawlerConfig},
 };
 
 pub mod actions;
@@ -25,22 +46,131 @@ struct Crousctl {
     pub command: Command,
     #[clap(long, short = 'c')]
     pub config: Option<PathBuf>,
+    /// Save every crawled page in a cassette file while crawling live
+    #[clap(long, global = true, conflicts_with_all = ["replay", "fixtures"])]
+    pub record: Option<PathBuf>,
+    /// Crawl the pages of a cassette file instead of the live sites
+    #[clap(long, global = true, conflicts_with = "fixtures")]
+    pub replay: Option<PathBuf>,
+    /// Crawl the pages of a fixtures directory instead of the live sites
+    #[clap(long, global = true)]
+    pub fixtures: Option<PathBuf>,
+    /// Download every page in full and process it even if it didn't change
+    #[clap(long, global = true)]
+    pub no_cache: bool,
+}
+
+fn build_fetcher(
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
+    fixtures: Option<PathBuf>,
+    policy: CrawlPolicy,
+    cache_dir: Option<PathBuf>,
+) -> Result<Arc<dyn Fetcher>, crawler::fetcher::FetchError> {
+    let live: Arc<dyn Fetcher> = Arc::new(CrawlScheduler::new(
+        Arc::new(LiveFetcher::with_user_agent(&policy.user_agent)),
+        policy,
+    ));
+    Ok(match (record, replay, fixtures) {
+        (Some(path), _, _) => Arc::new(CassetteFetcher::record(path, live)?),
+        (_, Some(path), _) => Arc::new(CassetteFetcher::replay(path)?),
+        (_, _, Some(root)) => Arc::new(FixtureFetcher::new(root)?),
+        _ => match cache_dir {
+            Some(cache_dir) => Arc::new(HttpCache::open(cache_dir, live)?),
+            None => live,
+        },
+    })
+}
+
+/// The fetcher and the selector profiles, only built by the commands that
+/// crawl so a bad cache or profiles dir doesn't break the others.
+fn crawl_setup(
+    crawler_config: &CrawlerConfig,
+    no_cache: bool,
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
+    fixtures: Option<PathBuf>,
+) -> (Arc<dyn Fetcher>, Arc<SelectorProfiles>) {
+    let cache_dir = (!no_cache).then(|| {
+        crawler_config.cache_dir.clone().unwrap_or_else(|| {
+            std::env::var_os("HOME")
+                .map(|home| PathBuf::from(home).join(".cache/htc/pages"))
+                .unwrap_or_else(|| std::env::temp_dir().join("htc/pages"))
+        })
+    });
+    let policy = crawler_config.apply(CrawlPolicy::default());
+    let profiles = match crawler_config.profiles() {
+        Ok(profiles) => Arc::new(profiles),
+        Err(e) => {
+            cprintln!("💣 <red>{}</red>", e);
+            exit(1)
+        }
+    };
+    let fetcher = match build_fetcher(record, replay, fixtures, policy, cache_dir) {
+        Ok(fetcher) => fetcher,
+        Err(e) => {
+            cprintln!("💣 <red>{}</red>", e);
+            exit(1)
+        }
+    };
+    (fetcher, profiles)
+}
+
+/// Exits with `1` when a collection failed and `2` when only some of its
+/// restaurants did.
+fn exit_with(result: Result<(), ExecutionResult>) {
+    match result {
+        Ok(()) => {
+            ceprintln!("✅ <green>Successfully collected and stored restaurant data.</green>");
+        }
+        Err(ExecutionResult::PartialFailure(failed)) => {
+            ceprintln!(
+                "⚠️ <yellow>Collected and stored restaurant data, except for {} restaurant(s).</yellow>",
+                failed
+            );
+            exit(2)
+        }
+        Err(e) => {
+            ceprintln!("💣 <red>Failed to collect restaurant data: {}</red>", e);
+            exit(1)
+        }
+    }
 }
 
 #[derive(Debug, Subcommand, PartialEq, Eq, Hash)]
 pub enum Command {
     Status,
     Restaurants {
-        #[clap(long, short = 't')]
-        target: CrousRegion,
+        /// Region to collect, repeat it or pass `all` for several
+        #[clap(long, short = 't', required = true)]
+        target: Vec<RegionTarget>,
         #[clap(long, short = 'd')]
         dry_run: bool,
+        /// Print the dry run as a table, or its payloads as json, jsonl or csv
+        #[clap(long, short = 'o', default_value = "table", requires = "dry_run")]
+        output: OutputFormat,
+        /// Regions collected at the same time
+        #[clap(long, short = 'j', default_value = "4")]
+        jobs: usize,
+        /// Write a JSON report of the run to this file
+        #[clap(long, short = 'r')]
+        report: Option<PathBuf>,
     },
     Meals {
-        #[clap(long, short = 't')]
-        target: CrousRegion,
+        /// Region to collect, repeat it or pass `all` for several
+        #[clap(long, short = 't', required = true)]
+        target: Vec<RegionTarget>,
         #[clap(long, short = 'd')]
         dry_run: bool,
+        /// Print the dry run as a table, or its payloads as json, jsonl or csv
+        #[clap(long, short = 'o', default_value = "table", requires = "dry_run")]
+        output: OutputFormat,
+        /// Regions collected at the same time
+        #[clap(long, short = 'j', default_value = "4")]
+        jobs: usize,
+        /// Write a JSON report of the run to this file
+        #[clap(long, short = 'r')]
+        report: Option<PathBuf>,
     },
     Schools {
         #[clap(long, short = 't')]
@@ -49,6 +179,70 @@ pub enum Command {
         dry_run: bool,
     },
     Schedule {},
+    /// Upload the payloads of a dry run captured with `--output json`
+    Push {
+        #[clap(long, short = 'f')]
+        from: PathBuf,
+    },
+    Tokens {
+        #[clap(subcommand)]
+        command: TokensCommand,
+    },
+    /// Manage the webhooks notified of new scrape batches
+    Webhooks {
+        #[clap(subcommand)]
+        command: WebhooksCommand,
+    },
+    /// Query the audit log of writes and admin actions
+    Audit {
+        #[clap(long, short = 'a')]
+        author: Option<String>,
+        #[clap(long)]
+        action: Option<String>,
+        /// success, sync_skipped, unauthorized, rejected or failed
+        #[clap(long, short = 'o')]
+        outcome: Option<AuditOutcome>,
+        #[clap(long, short = 't')]
+        target: Option<CrousRegion>,
+        /// Only show entries recorded since this date (YYYY-MM-DD)
+        #[clap(long, short = 's')]
+        since: Option<NaiveDate>,
+        #[clap(long, short = 'l', default_value = "50")]
+        limit: i64,
+    },
+    /// Check a sample of pages per region for scraper layout drift and
+    /// push the findings as the region health
+    Doctor {
+        #[clap(long, short = 't', required = true)]
+        target: Vec<CrousRegion>,
+        /// Restaurant pages checked per region, on top of the list
+        #[clap(long, short = 's', default_value = "5")]
+        sample: usize,
+        #[clap(long, short = 'd')]
+        dry_run: bool,
+    },
+    /// Sign a JSON file with the configured key, like a push would
+    Sign {
+        file: PathBuf,
+        #[clap(long, short = 'o')]
+        output: Option<PathBuf>,
+        /// Author to sign as, defaults to the configured user
+        #[clap(long, short = 'a')]
+        author: Option<String>,
+    },
+    /// Verify a captured signed payload against a public key, like the API would
+    Verify {
+        file: PathBuf,
+        /// Base64 encoded PEM public key, defaults to the configured one
+        #[clap(long, short = 'k', conflicts_with = "public_key_file")]
+        public_key: Option<String>,
+        /// PEM public key file
+        #[clap(long, short = 'f')]
+        public_key_file: Option<PathBuf>,
+        /// Print the exact string the signature covers
+        #[clap(long, short = 'p')]
+        show_payload: bool,
+    },
     Generate {
         #[clap(long, short = 'u')]
         user: String,
@@ -68,6 +262,18 @@ async fn main() {
 
     let Ok(config) = Config::from(&config_path) else {
         match args.command {
+            Command::Verify {
+                file,
+                public_key,
+                public_key_file,
+                show_payload,
+            } if public_key.is_some() || public_key_file.is_some() => {
+                let Ok(Some(public_key)) = read_public_key(public_key, public_key_file) else {
+                    exit(1)
+                };
+                run_verify(VerifyAction::new(file, public_key, show_payload)).await;
+                return;
+            }
             Command::Generate { user, dry_run } => {
                 let new_config = Config::generate(&user).expect("Couldn't generate config");
                 if dry_run {
@@ -87,36 +293,91 @@ async fn main() {
         }
     };
 
+    let crawler_config = config.crawler.clone().unwrap_or_default();
+    let crawl = move || {
+        crawl_setup(
+            &crawler_config,
+            args.no_cache,
+            args.record,
+            args.replay,
+            args.fixtures,
+        )
+    };
+
     let cron_config = config.schedule;
-    let client = HTCClient::new(config.server, config.client_key_data, config.user);
+    let client = config
+        .client
+        .unwrap_or_default()
+        .apply(HTCClient::builder(config.server).credentials(config.client_key_data, config.user));
+    let client = match client.build() {
+        Ok(client) => client,
+        Err(e) => {
+            cprintln!("💣 <red>{}</red>", e);
+            exit(1)
+        }
+    };
 
     match args.command {
         Command::Status => {
             println!("Crousctl is running and ready to execute commands.");
         }
-        Command::Restaurants { target, dry_run } => {
-            let action = RestaurantsAction::new(target, dry_run, client);
-            match action.execute().await {
-                Ok(()) => {
-                    cprintln!(
-                        "✅ <green>Successfully collected and stored restaurant data.</green>"
-                    );
-                }
-                Err(e) => {
-                    cprintln!("💣 <red>Failed to collect restaurant data: {}</red>", e);
-                }
-            }
+        Command::Restaurants {
+            target,
+            dry_run,
+            output,
+            jobs,
+            report,
+        } => {
+            let (fetcher, profiles) = crawl();
+            let action = RegionsAction::new(
+                Collection::Restaurants,
+                RegionTarget::expand(&target),
+                dry_run,
+                output,
+                jobs,
+                report,
+                client,
+                fetcher,
+                profiles,
+            );
+            exit_with(action.execute().await);
         }
-        Command::Meals { target, dry_run } => {
-            let action = MealsAction::new(target, dry_run, client);
-            match action.execute().await {
-                Ok(()) => {
-                    cprintln!(
-                        "✅ <green>Successfully collected and stored restaurant data.</green>"
-                    );
-                }
-                Err(e) => {
-                    cprintln!("💣 <red>Failed to collect restaurant data: {}</red>", e);
+        Command::Meals {
+            target,
+            dry_run,
+            output,
+            jobs,
+            report,
+        } => {
+            let (fetcher, profiles) = crawl();
+            let action = RegionsAction::new(
+                Collection::Meals,
+                RegionTarget::expand(&target),
+                dry_run,
+                output,
+                jobs,
+                report,
+                client,
+                fetcher,
+                profiles,
+            );
+            exit_with(action.execute().await);
+        }
+        Command::Push { from } => {
+            let action = PushAction::new(from, client);
+            if let Err(e) = action.execute().await {
+                match e {
+                    ExecutionResult::PartialFailure(failed) => {
+                        ceprintln!(
+                            "⚠️ <yellow>{} payload(s) couldn't be pushed</yellow>",
+                            failed
+                        );
+                        exit(2)
+                    }
+                    e => {
+                        ceprintln!("💣 <red>{}</red>", e);
+                        exit(1)
+                    }
                 }
             }
         }
@@ -128,7 +389,8 @@ async fn main() {
         }
         Command::Schedule {} => match cron_config {
             Some(config) => {
-                let schedule = ScheduleAction::try_from_config(config, client)
+                let (fetcher, profiles) = crawl();
+                let schedule = ScheduleAction::try_from_config(config, client, fetcher, &profiles)
                     .map_err(|e| {
                         cprintln!("💣 <red>{}</red>", e.to_string());
                     })
@@ -139,9 +401,114 @@ async fn main() {
                 cprintln!("💣 <red>No schedule config</red>");
             }
         },
+        Command::Tokens { command } => {
+            let action = TokensAction::new(command, client);
+            if let Err(e) = action.execute().await {
+                cprintln!("💣 <red>{}</red>", e);
+                exit(1);
+            }
+        }
+        Command::Webhooks { command } => {
+            let action = WebhooksAction::new(command, client);
+            if let Err(e) = action.execute().await {
+                cprintln!("💣 <red>{}</red>", e);
+                exit(1);
+            }
+        }
+        Command::Audit {
+            author,
+            action,
+            outcome,
+            target,
+            since,
+            limit,
+        } => {
+            let query = AuditQuerySchema {
+                author,
+                action,
+                outcome,
+                region: target.map(|region| region.to_string()),
+                since: since.and_then(|date| date.and_hms_opt(0, 0, 0)),
+                limit: Some(limit),
+                issued_at: Utc::now().timestamp(),
+            };
+            let action = AuditAction::new(query, client);
+            if let Err(e) = action.execute().await {
+                cprintln!("💣 <red>{}</red>", e);
+                exit(1);
+            }
+        }
+        Command::Doctor {
+            target,
+            sample,
+            dry_run,
+        } => {
+            let (fetcher, profiles) = crawl();
+            let action = DoctorAction::new(target, sample, dry_run, client, fetcher, profiles);
+            if let Err(e) = action.execute().await {
+                cprintln!("💣 <red>{}</red>", e);
+                exit(1);
+            }
+        }
+        Command::Sign {
+            file,
+            output,
+            author,
+        } => {
+            let action = SignAction::new(
+                file,
+                output,
+                client.private_key.clone(),
+                author.unwrap_or(client.author.clone()),
+            );
+            if let Err(e) = action.execute().await {
+                cprintln!("💣 <red>{}</red>", e);
+                exit(1);
+            }
+        }
+        Command::Verify {
+            file,
+            public_key,
+            public_key_file,
+            show_payload,
+        } => {
+            // an unreadable key file fails rather than verifying with the
+            // configured key instead
+            let Ok(public_key) = read_public_key(public_key, public_key_file) else {
+                exit(1)
+            };
+            let public_key = public_key.unwrap_or(config.public_key_data);
+            run_verify(VerifyAction::new(file, public_key, show_payload)).await;
+        }
         Command::Generate {
             user: _user,
             dry_run: _dry_run,
         } => {}
     }
 }
+
+/// The key given on the command line, `Ok(None)` when there is none and
+/// `Err` once the error is printed when the key file can't be read.
+fn read_public_key(
+    public_key: Option<String>,
+    public_key_file: Option<PathBuf>,
+) -> Result<Option<String>, ()> {
+    match (public_key, public_key_file) {
+        (Some(public_key), _) => Ok(Some(public_key)),
+        (None, Some(path)) => match std::fs::read(&path) {
+            Ok(pem) => Ok(Some(BASE64_STANDARD.encode(pem))),
+            Err(e) => {
+                cprintln!("💣 <red>Couldn't read {} : {}</red>", path.display(), e);
+                Err(())
+            }
+        },
+        (None, None) => Ok(None),
+    }
+}
+
+async fn run_verify(action: VerifyAction) {
+    if let Err(e) = action.execute().await {
+        cprintln!("💣 <red>{}</red>", e);
+        exit(1);
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::fetcher::{Conditional, FetchError, FetchFuture, Fetcher, Validators, slug};
+
+const INDEX: &str = "cache.json";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    file: String,
+    #[serde(flatten)]
+    validators: Validators,
+    content_hash: String,
+    /// Hash of the content when each consumer last acknowledged it.
+    #[serde(default)]
+    acknowledged: BTreeMap<String, String>,
+}
+
+/// Keeps the pages fetched by another `Fetcher` in a directory, next to
+/// their `ETag`/`Last-Modified` and a hash of their content, and revalidates
+/// them with conditional requests. A page answered with a 304 is served from
+/// the directory.
+///
+/// Pages are stored under their `slug`, so the directory can also be used as
+/// fixtures.
+pub struct HttpCache {
+    root: PathBuf,
+    inner: Arc<dyn Fetcher>,
+    entries: Mutex<BTreeMap<String, CacheEntry>>,
+}
+
+impl HttpCache {
+    pub fn open(root: impl Into<PathBuf>, inner: Arc<dyn Fetcher>) -> Result<Self, FetchError> {
+        let root = root.into();
+        fs::create_dir_all(&root).map_err(|e| FetchError::Io(e.to_string()))?;
+        let index_path = root.join(INDEX);
+        let entries = if index_path.exists() {
+            let index =
+                fs::read_to_string(&index_path).map_err(|e| FetchError::Io(e.to_string()))?;
+            // a corrupted index only costs a full crawl
+            serde_json::from_str(&index).unwrap_or_default()
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self {
+            root,
+            inner,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    fn save(&self, entries: &BTreeMap<String, CacheEntry>) -> Result<(), FetchError> {
+        let index =
+            serde_json::to_string_pretty(entries).map_err(|e| FetchError::Io(e.to_string()))?;
+        fs::write(self.root.join(INDEX), index).map_err(|e| FetchError::Io(e.to_string()))
+    }
+
+    fn entry(&self, url: &str) -> Result<Option<CacheEntry>, FetchError> {
+        Ok(self
+            .entries
+            .lock()
+            .map_err(|e| FetchError::Io(e.to_string()))?
+            .get(url)
+            .cloned())
+    }
+
+    async fn fetch_cached(&self, url: &str) -> Result<String, FetchError> {
+        let cached = self.entry(url)?.and_then(|entry| {
+            let body = fs::read_to_string(self.root.join(&entry.file)).ok()?;
+            Some((entry, body))
+        });
+        let validators = cached
+            .as_ref()
+            .map(|(entry, _)| entry.validators.clone())
+            .unwrap_or_default();
+
+        let (body, validators) = match self.inner.fetch_conditional(url, &validators).await? {
+            Conditional::Modified { body, validators } => (body, validators),
+            Conditional::NotModified => match cached {
+                Some((_, body)) => return Ok(body),
+                None => return Err(FetchError::Status(304)),
+            },
+        };
+
+        let entry = CacheEntry {
+            file: slug(url),
+            validators,
+            content_hash: hex::encode(Sha256::digest(body.as_bytes())),
+            acknowledged: cached
+                .map(|(entry, _)| entry.acknowledged)
+                .unwrap_or_default(),
+        };
+        fs::write(self.root.join(&entry.file), &body).map_err(|e| FetchError::Io(e.to_string()))?;
+        let mut entries = self
+            .entries
+            .lock()
+            .map_err(|e| FetchError::Io(e.to_string()))?;
+        entries.insert(url.to_string(), entry);
+        self.save(&entries)?;
+        Ok(body)
+    }
+}
+
+impl Fetcher for HttpCache {
+    fn fetch<'a>(&'a self, url: &'a str) -> FetchFuture<'a> {
+        Box::pin(self.fetch_cached(url))
+    }
+
+    fn changed(&self, url: &str, consumer: &str) -> bool {
+        self.entry(url)
+            .ok()
+            .flatten()
+            .is_none_or(|entry| entry.acknowledged.get(consumer) != Some(&entry.content_hash))
+    }
+
+    fn acknowledge(&self, url: &str, consumer: &str) -> Result<(), FetchError> {
+        let mut entries = self
+            .entries
+            .lock()
+            .map_err(|e| FetchError::Io(e.to_string()))?;
+        let Some(entry) = entries.get_mut(url) else {
+            return Ok(());
+        };
+        entry
+            .acknowledged
+            .insert(consumer.to_string(), entry.content_hash.clone());
+        self.save(&entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// Serves `body` with its length as ETag and honours `If-None-Match`.
+    #[derive(Default)]
+    struct Origin {
+        body: Mutex<String>,
+        downloads: AtomicUsize,
+    }
+
+    impl Fetcher for Origin {
+        fn fetch<'a>(&'a self, _url: &'a str) -> FetchFuture<'a> {
+            Box::pin(async move { Ok(self.body.lock().unwrap().clone()) })
+        }
+
+        fn fetch_conditional<'a>(
+            &'a self,
+            _url: &'a str,
+            validators: &'a Validators,
+        ) -> FetchFuture<'a, Conditional> {
+            Box::pin(async move {
+                let body = self.body.lock().unwrap().clone();
+                let etag = format!("\"{}\"", body.len());
+                if validators.etag.as_ref() == Some(&etag) {
+                    return Ok(Conditional::NotModified);
+                }
+                self.downloads.fetch_add(1, Ordering::SeqCst);
+                Ok(Conditional::Modified {
+                    body,
+                    validators: Validators {
+                        etag: Some(etag),
+                        last_modified: None,
+                    },
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_revalidates_and_tracks_changes() {
+        let root = std::env::temp_dir().join(format!("htc-cache-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let url = "https://www.crous-bfc.fr/restaurant/resto-u-sevenans/";
+        let origin = Arc::new(Origin::default());
+        *origin.body.lock().unwrap() = "<html>lundi</html>".to_string();
+
+        let cache = HttpCache::open(&root, origin.clone()).unwrap();
+        assert_eq!(cache.fetch(url).await.unwrap(), "<html>lundi</html>");
+        assert!(cache.changed(url, "meals"));
+        cache.acknowledge(url, "meals").unwrap();
+        assert!(!cache.changed(url, "meals"));
+
+        // served from the directory after a 304, even by a new cache
+        let cache = HttpCache::open(&root, origin.clone()).unwrap();
+        assert_eq!(cache.fetch(url).await.unwrap(), "<html>lundi</html>");
+        assert_eq!(origin.downloads.load(Ordering::SeqCst), 1);
+        assert!(!cache.changed(url, "meals"));
+
+        *origin.body.lock().unwrap() = "<html>mardi!</html>".to_string();
+        assert_eq!(cache.fetch(url).await.unwrap(), "<html>mardi!</html>");
+        assert_eq!(origin.downloads.load(Ordering::SeqCst), 2);
+        assert!(cache.changed(url, "meals"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_consumers_acknowledge_independently() {
+        let root = std::env::temp_dir().join(format!("htc-cache-consumers-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let url = "https://www.crous-bfc.fr/restaurant/resto-u-montmuzard/";
+        let origin = Arc::new(Origin::default());
+        *origin.body.lock().unwrap() = "<html>lundi</html>".to_string();
+
+        let cache = HttpCache::open(&root, origin.clone()).unwrap();
+        cache.fetch(url).await.unwrap();
+        cache.acknowledge(url, "restaurants").unwrap();
+        assert!(!cache.changed(url, "restaurants"));
+        assert!(cache.changed(url, "meals"));
+
+        cache.acknowledge(url, "meals").unwrap();
+        *origin.body.lock().unwrap() = "<html>mardi!</html>".to_string();
+        cache.fetch(url).await.unwrap();
+        cache.acknowledge(url, "meals").unwrap();
+        assert!(!cache.changed(url, "meals"));
+        // kept across a reopen
+        let cache = HttpCache::open(&root, origin.clone()).unwrap();
+        assert!(cache.changed(url, "restaurants"));
+        assert!(!cache.changed(url, "meals"));
+        let _ = fs::remove_dir_all(&root);
+    }
+}
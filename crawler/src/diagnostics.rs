@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+
+/// What a scraper's selectors found on a page, to notice when the CROUS
+/// layout changes under us instead of silently scraping nothing.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseDiagnostics {
+    pub url: String,
+    /// Number of nodes matched by each selector.
+    pub selectors: BTreeMap<String, usize>,
+    /// Classes of the `<section>`s the scraper doesn't know about.
+    pub unknown_sections: Vec<String>,
+}
+
+impl ParseDiagnostics {
+    /// Counts the matches of `selectors` in `document` and lists its classed
+    /// sections whose class isn't one of `known_sections`.
+    pub fn inspect(
+        url: &str,
+        document: &Html,
+        selectors: &[impl AsRef<str>],
+        known_sections: &[impl AsRef<str>],
+    ) -> Self {
+        let selectors = selectors
+            .iter()
+            .map(|selector| {
+                let selector = selector.as_ref();
+                // an invalid selector is as good as one matching nothing
+                let count = Selector::parse(selector)
+                    .map(|parsed| document.select(&parsed).count())
+                    .unwrap_or(0);
+                (selector.to_string(), count)
+            })
+            .collect();
+
+        let section_selector = Selector::parse("section[class]").expect("valid selector");
+        let mut unknown_sections: Vec<String> = document
+            .select(&section_selector)
+            .filter_map(|section| section.value().classes().next())
+            .filter(|class| !known_sections.iter().any(|known| known.as_ref() == *class))
+            .map(str::to_string)
+            .collect();
+        unknown_sections.sort();
+        unknown_sections.dedup();
+
+        Self {
+            url: url.to_string(),
+            selectors,
+            unknown_sections,
+        }
+    }
+
+    /// Selectors that matched nothing.
+    pub fn missing(&self) -> Vec<&str> {
+        self.selectors
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(selector, _)| selector.as_str())
+            .collect()
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.missing().is_empty() && self.unknown_sections.is_empty()
+    }
+}
@@ -0,0 +1,418 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use reqwest::{StatusCode, header};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::scheduler::DEFAULT_USER_AGENT;
+
+#[derive(Debug, Error)]
+pub enum FetchError {
+    #[error("Request failed: {0}")]
+    RequestFailed(String),
+    #[error("Unexpected status {0}")]
+    Status(u16),
+    #[error("Throttled with status {0}")]
+    Throttled(u16, Option<Duration>),
+    #[error("Disallowed by robots.txt: {0}")]
+    Disallowed(String),
+    #[error("No recording for {0}")]
+    NotRecorded(String),
+    #[error("IO error: {0}")]
+    Io(String),
+}
+
+pub type FetchFuture<'a, T = String> =
+    Pin<Box<dyn Future<Output = Result<T, FetchError>> + Send + 'a>>;
+
+/// What a previous response said about a page, sent back to make a
+/// request conditional.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum Conditional {
+    Modified {
+        body: String,
+        validators: Validators,
+    },
+    NotModified,
+}
+
+/// Where the scrapers get their pages from, injected so they can run
+/// against the live sites, a recorded cassette or a fixtures directory.
+pub trait Fetcher: Send + Sync {
+    fn fetch<'a>(&'a self, url: &'a str) -> FetchFuture<'a>;
+
+    /// Fetches `url` unless it still matches `validators`. Sources that
+    /// can't tell always answer `Modified`.
+    fn fetch_conditional<'a>(
+        &'a self,
+        url: &'a str,
+        _validators: &'a Validators,
+    ) -> FetchFuture<'a, Conditional> {
+        Box::pin(async move {
+            let body = self.fetch(url).await?;
+            Ok(Conditional::Modified {
+                body,
+                validators: Validators::default(),
+            })
+        })
+    }
+
+    /// Whether the page last fetched at `url` differs from the one last
+    /// acknowledged by `consumer`, e.g. `meals`. Sources without memory
+    /// always say it does.
+    fn changed(&self, _url: &str, _consumer: &str) -> bool {
+        true
+    }
+
+    /// Marks the page last fetched at `url` as processed by `consumer`, e.g.
+    /// once its meals were uploaded. Other consumers of the same page still
+    /// see it as changed.
+    fn acknowledge(&self, _url: &str, _consumer: &str) -> Result<(), FetchError> {
+        Ok(())
+    }
+}
+
+/// File name of a URL in a fixtures directory or a cassette, e.g.
+/// `www.crous-bfc.fr_restaurant_resto-u-sevenans`.
+pub fn slug(url: &str) -> String {
+    let url = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .unwrap_or(url);
+    url.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>()
+        .trim_matches('_')
+        .to_string()
+}
+
+/// Fetches over HTTP.
+#[derive(Clone)]
+pub struct LiveFetcher {
+    client: reqwest::Client,
+}
+
+impl Default for LiveFetcher {
+    fn default() -> Self {
+        Self::with_user_agent(DEFAULT_USER_AGENT)
+    }
+}
+
+impl LiveFetcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_user_agent(user_agent: &str) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(user_agent)
+            .build()
+            .unwrap_or_default();
+        Self { client }
+    }
+
+    pub fn with_client(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+impl LiveFetcher {
+    async fn get(&self, url: &str, validators: &Validators) -> Result<Conditional, FetchError> {
+        let mut request = self.client.get(url);
+        if let Some(etag) = &validators.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| FetchError::RequestFailed(e.to_string()))?;
+        let status = response.status();
+        if status == StatusCode::NOT_MODIFIED {
+            return Ok(Conditional::NotModified);
+        }
+        if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE {
+            let retry_after = response
+                .headers()
+                .get(header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .map(Duration::from_secs);
+            return Err(FetchError::Throttled(status.as_u16(), retry_after));
+        }
+        if !status.is_success() {
+            return Err(FetchError::Status(status.as_u16()));
+        }
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let validators = Validators {
+            etag: header(header::ETAG),
+            last_modified: header(header::LAST_MODIFIED),
+        };
+        let body = response
+            .text()
+            .await
+            .map_err(|e| FetchError::RequestFailed(e.to_string()))?;
+        Ok(Conditional::Modified { body, validators })
+    }
+}
+
+impl Fetcher for LiveFetcher {
+    fn fetch<'a>(&'a self, url: &'a str) -> FetchFuture<'a> {
+        Box::pin(async move {
+            match self.get(url, &Validators::default()).await? {
+                Conditional::Modified { body, .. } => Ok(body),
+                // can't happen without validators, but a server may still say so
+                Conditional::NotModified => Err(FetchError::Status(304)),
+            }
+        })
+    }
+
+    fn fetch_conditional<'a>(
+        &'a self,
+        url: &'a str,
+        validators: &'a Validators,
+    ) -> FetchFuture<'a, Conditional> {
+        Box::pin(self.get(url, validators))
+    }
+}
+
+/// Serves pages from a directory. A URL is looked up in the optional
+/// `index.json` (`{"<url>": "<file>"}`), then by its `slug` with or
+/// without an `.html`/`.json` extension. Anything that isn't an http(s) URL
+/// is read as a path relative to the directory.
+#[derive(Clone, Debug)]
+pub struct FixtureFetcher {
+    root: PathBuf,
+    index: HashMap<String, String>,
+}
+
+impl FixtureFetcher {
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self, FetchError> {
+        let root = root.into();
+        let index_path = root.join("index.json");
+        let index = if index_path.exists() {
+            let index =
+                fs::read_to_string(&index_path).map_err(|e| FetchError::Io(e.to_string()))?;
+            serde_json::from_str(&index).map_err(|e| FetchError::Io(e.to_string()))?
+        } else {
+            HashMap::new()
+        };
+        Ok(Self { root, index })
+    }
+
+    fn resolve(&self, url: &str) -> Option<PathBuf> {
+        if let Some(file) = self.index.get(url) {
+            return Some(self.root.join(file));
+        }
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Some(self.root.join(url));
+        }
+        let slug = slug(url);
+        ["", ".html", ".json"]
+            .iter()
+            .map(|extension| self.root.join(format!("{}{}", slug, extension)))
+            .find(|path| path.is_file())
+    }
+}
+
+impl Fetcher for FixtureFetcher {
+    fn fetch<'a>(&'a self, url: &'a str) -> FetchFuture<'a> {
+        Box::pin(async move {
+            let path = self
+                .resolve(url)
+                .ok_or_else(|| FetchError::NotRecorded(url.to_string()))?;
+            fs::read_to_string(&path).map_err(|e| FetchError::Io(format!("{:?}: {}", path, e)))
+        })
+    }
+}
+
+enum CassetteMode {
+    Record(Arc<dyn Fetcher>),
+    Replay,
+}
+
+/// Records the pages fetched by another `Fetcher` into a JSON file
+/// (`{"<url>": "<body>"}`) and replays them later without network.
+pub struct CassetteFetcher {
+    path: PathBuf,
+    mode: CassetteMode,
+    entries: Mutex<BTreeMap<String, String>>,
+    /// Pages were recorded since the cassette was last written
+    dirty: AtomicBool,
+    /// Held while the cassette is written so writes don't overlap
+    saving: tokio::sync::Mutex<()>,
+}
+
+impl CassetteFetcher {
+    /// Fetches through `inner` and saves every page, pages already in an
+    /// existing cassette are kept.
+    pub fn record(path: impl Into<PathBuf>, inner: Arc<dyn Fetcher>) -> Result<Self, FetchError> {
+        let path = path.into();
+        let entries = if path.exists() {
+            Self::load(&path)?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self {
+            path,
+            mode: CassetteMode::Record(inner),
+            entries: Mutex::new(entries),
+            dirty: AtomicBool::new(false),
+            saving: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self, FetchError> {
+        let path = path.into();
+        let entries = Self::load(&path)?;
+        Ok(Self {
+            path,
+            mode: CassetteMode::Replay,
+            entries: Mutex::new(entries),
+            dirty: AtomicBool::new(false),
+            saving: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    fn load(path: &Path) -> Result<BTreeMap<String, String>, FetchError> {
+        let cassette = fs::read_to_string(path).map_err(|e| FetchError::Io(e.to_string()))?;
+        serde_json::from_str(&cassette).map_err(|e| FetchError::Io(e.to_string()))
+    }
+
+    /// Writes the cassette off the async workers. Pages recorded while a
+    /// write is running are all written by the next one.
+    async fn save(&self) -> Result<(), FetchError> {
+        let _saving = self.saving.lock().await;
+        if !self.dirty.swap(false, Ordering::AcqRel) {
+            return Ok(());
+        }
+        let entries = self
+            .entries
+            .lock()
+            .map_err(|e| FetchError::Io(e.to_string()))?
+            .clone();
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || {
+            let cassette = serde_json::to_string_pretty(&entries)
+                .map_err(|e| FetchError::Io(e.to_string()))?;
+            fs::write(&path, cassette).map_err(|e| FetchError::Io(e.to_string()))
+        })
+        .await
+        .map_err(|e| FetchError::Io(e.to_string()))?
+    }
+}
+
+impl Fetcher for CassetteFetcher {
+    fn fetch<'a>(&'a self, url: &'a str) -> FetchFuture<'a> {
+        Box::pin(async move {
+            match &self.mode {
+                CassetteMode::Replay => self
+                    .entries
+                    .lock()
+                    .map_err(|e| FetchError::Io(e.to_string()))?
+                    .get(url)
+                    .cloned()
+                    .ok_or_else(|| FetchError::NotRecorded(url.to_string())),
+                CassetteMode::Record(inner) => {
+                    let body = inner.fetch(url).await?;
+                    self.entries
+                        .lock()
+                        .map_err(|e| FetchError::Io(e.to_string()))?
+                        .insert(url.to_string(), body.clone());
+                    self.dirty.store(true, Ordering::Release);
+                    // saved on every page so an interrupted run keeps what it got
+                    self.save().await?;
+                    Ok(body)
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Scraper, restaurant_list::RestaurantListScraper, restaurant_page::RestaurantPageScraper,
+    };
+
+    #[test]
+    fn test_slug() {
+        assert_eq!(
+            slug("https://www.crous-bfc.fr/restaurant/resto-u-sevenans/"),
+            "www.crous-bfc.fr_restaurant_resto-u-sevenans"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cassette_record_and_replay() {
+        let path = std::env::temp_dir().join(format!("htc-cassette-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let url = "https://www.crous-bfc.fr/restaurant/resto-u-sevenans/";
+
+        let fixtures: Arc<dyn Fetcher> = Arc::new(FixtureFetcher::new("./src/stubs").unwrap());
+        let recorder = CassetteFetcher::record(&path, fixtures).unwrap();
+        let recorded = recorder.fetch(url).await.unwrap();
+
+        let player = CassetteFetcher::replay(&path).unwrap();
+        assert_eq!(player.fetch(url).await.unwrap(), recorded);
+        assert!(matches!(
+            player.fetch("https://www.crous-bfc.fr/").await,
+            Err(FetchError::NotRecorded(_))
+        ));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_offline_restaurants_pipeline() {
+        let fetcher: Arc<dyn Fetcher> = Arc::new(FixtureFetcher::new("./src/stubs").unwrap());
+        let restaurants = RestaurantListScraper::new(
+            "https://www.crous-bfc.fr/se-restaurer/ou-manger/".to_string(),
+            fetcher.clone(),
+        )
+        .scrape()
+        .await
+        .unwrap();
+        let sevenans = restaurants
+            .iter()
+            .find(|restaurant| restaurant.crous_url.contains("resto-u-sevenans"))
+            .unwrap();
+
+        let page = RestaurantPageScraper::new(sevenans.crous_url.clone(), fetcher)
+            .scrape()
+            .await
+            .unwrap();
+        assert!(!page.menus.is_empty());
+    }
+}
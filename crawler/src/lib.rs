@@ -1,30 +1,33 @@
+use std::sync::Arc;
+
+pub mod cache;
+pub mod diagnostics;
+pub mod fetcher;
+pub mod profile;
 pub mod restaurant_list;
 pub mod restaurant_page;
+pub mod scheduler;
 pub mod school_api;
 
-pub enum ScraperError {
-    RequestFailed,
-    ParsingFailed(String),
-}
+use crate::fetcher::{FetchError, Fetcher};
 
-#[cfg(not(test))]
-pub async fn get(url: &str) -> Result<scraper::Html, ScraperError> {
-    let response = reqwest::get(url)
-        .await
-        .map_err(|_| ScraperError::RequestFailed)?;
-    let body = response
-        .text()
-        .await
-        .map_err(|_| ScraperError::RequestFailed)?;
+pub async fn get(fetcher: &Arc<dyn Fetcher>, url: &str) -> Result<scraper::Html, FetchError> {
+    let body = fetcher.fetch(url).await?;
     Ok(scraper::Html::parse_document(&body))
 }
 
-#[cfg(test)]
-pub async fn get(_url: &str) -> Result<scraper::Html, ScraperError> {
-    use std::fs;
-
-    let html = fs::read_to_string(_url).map_err(|_| ScraperError::RequestFailed)?;
-    Ok(scraper::Html::parse_document(&html))
+/// Like `get`, but `None` without parsing when the fetcher knows the page
+/// didn't change since `consumer` acknowledged it.
+pub async fn get_if_changed(
+    fetcher: &Arc<dyn Fetcher>,
+    url: &str,
+    consumer: &str,
+) -> Result<Option<scraper::Html>, FetchError> {
+    let body = fetcher.fetch(url).await?;
+    if !fetcher.changed(url, consumer) {
+        return Ok(None);
+    }
+    Ok(Some(scraper::Html::parse_document(&body)))
 }
 
 pub trait Scraper<T> {
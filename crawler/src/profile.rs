@@ -0,0 +1,419 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Latest profile format understood by this build, profiles written for a
+/// newer one are refused instead of being half understood.
+pub const PROFILE_VERSION: u32 = 1;
+
+const STANDARD: &str = "standard";
+const BUILTIN_PROFILES: &[(&str, &str)] = &[
+    ("standard.toml", include_str!("../profiles/standard.toml")),
+    ("paris.toml", include_str!("../profiles/paris.toml")),
+];
+
+#[derive(Debug, Error)]
+pub enum ProfileError {
+    #[error("Couldn't read profile {0} : {1}")]
+    Io(String, String),
+    #[error("Invalid profile {0} : {1}")]
+    Invalid(String, String),
+    #[error("Profile {0} is version {1}, this build only knows up to {PROFILE_VERSION}")]
+    UnsupportedVersion(String, u32),
+    #[error("Unknown profile {0}")]
+    Unknown(String),
+}
+
+/// How a CROUS site lists its restaurants.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListLayout {
+    /// A list of links to the restaurant pages
+    #[default]
+    Links,
+    /// A map whose markers are embedded in the page, as JSON in a script or
+    /// as elements carrying their coordinates
+    Map,
+}
+
+/// Where the restaurants are listed on a CROUS site and how to read them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListProfile {
+    #[serde(default)]
+    pub layout: ListLayout,
+    /// Path of the list page, relative to the site root
+    pub path: String,
+    /// Map layout only, scripts holding the markers as JSON
+    #[serde(default)]
+    pub markers: Option<String>,
+    /// Variable of the markers script holding them, e.g. `restaurantsMap`,
+    /// either an array or an object with a `markers` array
+    #[serde(default)]
+    pub markers_variable: Option<String>,
+    /// A link to a restaurant, or a marker element on the map layout
+    pub restaurant: String,
+    pub title: String,
+    pub city: String,
+    /// Relative to a restaurant, its type as labelled by the list when it
+    /// says, markers carry it in their `type` field or `data-type`
+    #[serde(default)]
+    pub venue_type: Option<String>,
+    /// Sections of the page, scraped or not
+    #[serde(default)]
+    pub sections: Vec<String>,
+}
+
+impl ListProfile {
+    pub fn selectors(&self) -> Vec<&str> {
+        match (self.layout, &self.markers) {
+            (ListLayout::Map, Some(markers)) => vec![markers],
+            _ => vec![&self.restaurant, &self.title, &self.city],
+        }
+    }
+}
+
+/// How to read a restaurant page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageProfile {
+    /// Name of the restaurant, which often tells its type
+    #[serde(default = "default_title")]
+    pub title: String,
+    pub menu: String,
+    pub date: String,
+    pub meal: String,
+    pub meal_title: String,
+    pub category: String,
+    /// Relative to a category
+    pub dish: String,
+    pub info: String,
+    pub info_title: String,
+    /// Relative to an info block
+    pub info_text: String,
+    /// Text of the title of the info block holding the opening hours
+    pub hours_title: String,
+    /// Titles of the other info blocks, matched like `hours_title`
+    #[serde(default)]
+    pub infos: InfoTitles,
+    /// Photos of the restaurant, their `src` or `data-src` is kept
+    #[serde(default = "default_photo")]
+    pub photo: String,
+    /// Free text on where the restaurant is, used when no info block tells
+    /// how to get there
+    #[serde(default = "default_description")]
+    pub description: String,
+    /// Banners of the page, such as `Fermé pour travaux`
+    #[serde(default = "default_notice")]
+    pub notice: String,
+    /// Relative to a menu, notices standing in for its meals such as
+    /// `Menu non communiqué`, written under its date
+    #[serde(default = "default_menu_notice")]
+    pub menu_notice: String,
+    pub map: String,
+    /// Sections of the page, scraped or not
+    #[serde(default)]
+    pub sections: Vec<String>,
+}
+
+/// Texts of the titles of the info blocks, a block matches when its title
+/// contains one of them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InfoTitles {
+    pub address: String,
+    pub phone: String,
+    pub payment: String,
+    /// Services of the restaurant, accessibility included
+    pub amenities: String,
+    /// Access and transport directions
+    pub access: Vec<String>,
+}
+
+impl Default for InfoTitles {
+    fn default() -> Self {
+        Self {
+            address: "Adresse".to_string(),
+            phone: "Téléphone".to_string(),
+            payment: "Paiement".to_string(),
+            amenities: "Pratique".to_string(),
+            access: vec!["Accès".to_string(), "Transport".to_string()],
+        }
+    }
+}
+
+fn default_title() -> String {
+    "h1.post_title".to_string()
+}
+
+fn default_photo() -> String {
+    "section.infos div.photo img".to_string()
+}
+
+fn default_description() -> String {
+    "section.localisation .localisation_content".to_string()
+}
+
+fn default_notice() -> String {
+    ".restaurant_notice, div.notice, div.alert, section.menus > p".to_string()
+}
+
+fn default_menu_notice() -> String {
+    ".menu_date ~ p, .menu_date_title ~ p, .meal_notice".to_string()
+}
+
+impl PageProfile {
+    /// Selectors expected to match on any page, the relative ones are left
+    /// out as they are only meaningful inside another match.
+    pub fn selectors(&self) -> Vec<&str> {
+        vec![
+            &self.menu,
+            &self.date,
+            &self.meal,
+            &self.meal_title,
+            &self.category,
+            &self.info,
+            &self.info_title,
+            &self.map,
+        ]
+    }
+
+    /// Selectors of the menus, which a closed restaurant shows a notice
+    /// instead of.
+    pub fn menu_selectors(&self) -> Vec<&str> {
+        vec![
+            &self.menu,
+            &self.date,
+            &self.meal,
+            &self.meal_title,
+            &self.category,
+        ]
+    }
+}
+
+/// Selectors of a CROUS site template, loaded from TOML or JSON so a layout
+/// change can be fixed by shipping a profile instead of a new binary.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelectorProfile {
+    pub name: String,
+    pub version: u32,
+    /// Regions using this profile unless told otherwise
+    #[serde(default)]
+    pub regions: Vec<String>,
+    pub list: ListProfile,
+    pub page: PageProfile,
+}
+
+impl Default for SelectorProfile {
+    fn default() -> Self {
+        let (file, content) = BUILTIN_PROFILES[0];
+        Self::parse(file, content).expect("valid builtin profile")
+    }
+}
+
+impl SelectorProfile {
+    /// Parses a profile, as JSON when `file` ends with `.json` and as TOML
+    /// otherwise.
+    pub fn parse(file: &str, content: &str) -> Result<Self, ProfileError> {
+        let profile: Self = if file.ends_with(".json") {
+            serde_json::from_str(content)
+                .map_err(|e| ProfileError::Invalid(file.to_string(), e.to_string()))?
+        } else {
+            toml::from_str(content)
+                .map_err(|e| ProfileError::Invalid(file.to_string(), e.to_string()))?
+        };
+        if profile.version > PROFILE_VERSION {
+            return Err(ProfileError::UnsupportedVersion(
+                profile.name,
+                profile.version,
+            ));
+        }
+        Ok(profile)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ProfileError> {
+        let file = path.display().to_string();
+        let content =
+            fs::read_to_string(path).map_err(|e| ProfileError::Io(file.clone(), e.to_string()))?;
+        Self::parse(&file, &content)
+    }
+
+    /// URL of the restaurant list of the site at `site_url`.
+    pub fn list_url(&self, site_url: &str) -> String {
+        format!("{}{}", site_url, self.list.path)
+    }
+}
+
+/// The profiles known to the crawler and which region uses which.
+#[derive(Debug, Clone)]
+pub struct SelectorProfiles {
+    /// Profiles by name, with the order they were added in so the ones
+    /// loaded from a directory come after the builtin ones
+    profiles: BTreeMap<String, (usize, Arc<SelectorProfile>)>,
+    assignments: BTreeMap<String, String>,
+}
+
+impl Default for SelectorProfiles {
+    fn default() -> Self {
+        let mut profiles = Self {
+            profiles: BTreeMap::new(),
+            assignments: BTreeMap::new(),
+        };
+        for (file, content) in BUILTIN_PROFILES {
+            profiles.insert(SelectorProfile::parse(file, content).expect("valid builtin profile"));
+        }
+        profiles
+    }
+}
+
+impl SelectorProfiles {
+    /// Adds a profile, replacing the one with the same name.
+    pub fn insert(&mut self, profile: SelectorProfile) {
+        let order = self
+            .profiles
+            .values()
+            .map(|(order, _)| order + 1)
+            .max()
+            .unwrap_or_default();
+        self.profiles
+            .insert(profile.name.clone(), (order, Arc::new(profile)));
+    }
+
+    /// Loads every `.toml` and `.json` profile of `dir` on top of the known
+    /// ones.
+    pub fn load_dir(&mut self, dir: &Path) -> Result<(), ProfileError> {
+        let entries = fs::read_dir(dir)
+            .map_err(|e| ProfileError::Io(dir.display().to_string(), e.to_string()))?;
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "toml" || extension == "json")
+            })
+            .collect();
+        paths.sort();
+        for path in paths {
+            self.insert(SelectorProfile::load(&path)?);
+        }
+        Ok(())
+    }
+
+    /// Makes `region` use the profile named `profile`, whatever the profiles
+    /// say.
+    pub fn assign(&mut self, region: &str, profile: &str) -> Result<(), ProfileError> {
+        if !self.profiles.contains_key(profile) {
+            return Err(ProfileError::Unknown(profile.to_string()));
+        }
+        self.assignments
+            .insert(region.to_lowercase(), profile.to_string());
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<SelectorProfile>> {
+        self.profiles.get(name).map(|(_, profile)| profile.clone())
+    }
+
+    /// Profile assigned to the region, else the one listing it with the
+    /// highest version, the last added winning a tie so a loaded profile
+    /// overrides a builtin one, else the standard one.
+    pub fn for_region(&self, region: &str) -> Arc<SelectorProfile> {
+        self.assignments
+            .get(&region.to_lowercase())
+            .and_then(|name| self.get(name))
+            .or_else(|| {
+                self.profiles
+                    .values()
+                    .filter(|(_, profile)| {
+                        profile
+                            .regions
+                            .iter()
+                            .any(|listed| listed.eq_ignore_ascii_case(region))
+                    })
+                    .max_by_key(|(order, profile)| (profile.version, *order))
+                    .map(|(_, profile)| profile.clone())
+            })
+            .or_else(|| self.get(STANDARD))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_profiles() {
+        let profiles = SelectorProfiles::default();
+        let paris = profiles.for_region("Paris");
+        assert_eq!(paris.name, "paris");
+        assert_eq!(
+            paris.list_url("https://www.crous-paris.fr/"),
+            "https://www.crous-paris.fr/se-restaurer/ou-manger/carte"
+        );
+        assert_eq!(
+            profiles
+                .for_region("Bourgogne")
+                .list_url("https://www.crous-bfc.fr/"),
+            "https://www.crous-bfc.fr/se-restaurer/ou-manger/"
+        );
+        assert_eq!(*profiles.for_region("Lyon"), SelectorProfile::default());
+    }
+
+    #[test]
+    fn test_load_and_assign() {
+        let mut profile = SelectorProfile {
+            name: "lyon-2026".to_string(),
+            ..Default::default()
+        };
+        profile.page.menu = "section.carte div.menu".to_string();
+        let json = serde_json::to_string(&profile).unwrap();
+        assert_eq!(SelectorProfile::parse("lyon.json", &json).unwrap(), profile);
+
+        let mut profiles = SelectorProfiles::default();
+        profiles.insert(profile.clone());
+        assert!(matches!(
+            profiles.assign("Lyon", "nope"),
+            Err(ProfileError::Unknown(_))
+        ));
+        profiles.assign("Lyon", "lyon-2026").unwrap();
+        assert_eq!(*profiles.for_region("lyon"), profile);
+
+        profile.version = PROFILE_VERSION + 1;
+        let json = serde_json::to_string(&profile).unwrap();
+        assert!(matches!(
+            SelectorProfile::parse("lyon.json", &json),
+            Err(ProfileError::UnsupportedVersion(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_loaded_profiles_override_builtins() {
+        let dir = std::env::temp_dir().join(format!("htc-profiles-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut profile = (*SelectorProfiles::default().for_region("Paris")).clone();
+        // sorts before the builtin `paris` by name
+        profile.name = "a-paris-hotfix".to_string();
+        profile.list.path = "se-restaurer/carte".to_string();
+        fs::write(
+            dir.join("paris-hotfix.json"),
+            serde_json::to_string(&profile).unwrap(),
+        )
+        .unwrap();
+
+        let mut profiles = SelectorProfiles::default();
+        profiles.load_dir(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(*profiles.for_region("Paris"), profile);
+
+        // a lower version gives way whatever the order
+        let mut older = profile.clone();
+        older.name = "z-paris-older".to_string();
+        older.version = 0;
+        profiles.insert(older);
+        assert_eq!(profiles.for_region("Paris").name, "a-paris-hotfix");
+    }
+}
@@ -1,11 +1,20 @@
-use scraper::Selector;
+use std::sync::Arc;
+
+use scraper::{ElementRef, Html, Selector};
+use serde_json::Value;
 use thiserror::Error;
 
-use crate::Scraper;
+use crate::{
+    Scraper,
+    diagnostics::ParseDiagnostics,
+    fetcher::Fetcher,
+    profile::{ListLayout, ListProfile, SelectorProfile},
+};
 
-#[derive(Debug)]
 pub struct RestaurantListScraper {
     url: String,
+    fetcher: Arc<dyn Fetcher>,
+    profile: Arc<SelectorProfile>,
 }
 
 #[derive(Debug, Clone)]
@@ -13,19 +22,48 @@ pub struct RestaurantData {
     pub name: String,
     pub city: String,
     pub crous_url: String,
+    /// Type label of the list, e.g. `Cafétéria`, when it has one
+    pub venue_type: Option<String>,
 }
 
 #[derive(Debug, Error)]
 pub enum RestaurantListScraperError {
-    #[error("Failed to perform HTTP request")]
-    RequestFailed,
+    #[error("Failed to perform HTTP request: {0}")]
+    RequestFailed(String),
     #[error("Failed to parse HTML: {0}")]
     ParsingFailed(String),
 }
 
 impl RestaurantListScraper {
-    pub fn new(url: String) -> Self {
-        Self { url }
+    pub fn new(url: String, fetcher: Arc<dyn Fetcher>) -> Self {
+        Self {
+            url,
+            fetcher,
+            profile: Arc::default(),
+        }
+    }
+
+    /// Reads the list with `profile` instead of the standard selectors.
+    pub fn with_profile(mut self, profile: Arc<SelectorProfile>) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Fetches the list and reports what the selectors found on it.
+    pub async fn diagnose(&self) -> Result<ParseDiagnostics, RestaurantListScraperError> {
+        let document = crate::get(&self.fetcher, &self.url)
+            .await
+            .map_err(|e| RestaurantListScraperError::RequestFailed(e.to_string()))?;
+        Ok(Self::diagnostics(&self.profile, &self.url, &document))
+    }
+
+    pub fn diagnostics(profile: &SelectorProfile, url: &str, document: &Html) -> ParseDiagnostics {
+        ParseDiagnostics::inspect(
+            url,
+            document,
+            &profile.list.selectors(),
+            &profile.list.sections,
+        )
     }
 }
 
@@ -33,23 +71,39 @@ impl Scraper<Vec<RestaurantData>> for RestaurantListScraper {
     type Failure = RestaurantListScraperError;
 
     async fn scrape(&self) -> Result<Vec<RestaurantData>, Self::Failure> {
-        let document = crate::get(&self.url)
+        let document = crate::get(&self.fetcher, &self.url)
             .await
-            .map_err(|_| RestaurantListScraperError::RequestFailed)?;
+            .map_err(|e| RestaurantListScraperError::RequestFailed(e.to_string()))?;
 
-        let restaurant_list_selector = Selector::parse(".vc_restaurants > ul:nth-child(3) a")
-            .map_err(|_| {
-                RestaurantListScraperError::ParsingFailed(
-                    "Couldn't get .vc_restaurants > ul:nth-child(3) a".to_string(),
-                )
-            })?;
+        Self::parse(&self.profile.list, &document)
+    }
+}
+
+fn selector(selector: &str) -> Result<Selector, RestaurantListScraperError> {
+    Selector::parse(selector).map_err(|_| {
+        RestaurantListScraperError::ParsingFailed(format!("Couldn't get {}", selector))
+    })
+}
+
+impl RestaurantListScraper {
+    fn parse(
+        profile: &ListProfile,
+        document: &Html,
+    ) -> Result<Vec<RestaurantData>, RestaurantListScraperError> {
+        match profile.layout {
+            ListLayout::Links => Self::parse_links(profile, document),
+            ListLayout::Map => Self::parse_map(profile, document),
+        }
+    }
 
-        let title_selector = Selector::parse(".restaurant_title").map_err(|_| {
-            RestaurantListScraperError::ParsingFailed("Couldn't get .restaurant_title".to_string())
-        })?;
-        let city_selector = Selector::parse(".restaurant_area").map_err(|_| {
-            RestaurantListScraperError::ParsingFailed("Couldn't get .restaurant_area".to_string())
-        })?;
+    fn parse_links(
+        profile: &ListProfile,
+        document: &Html,
+    ) -> Result<Vec<RestaurantData>, RestaurantListScraperError> {
+        let restaurant_list_selector = selector(&profile.restaurant)?;
+        let title_selector = selector(&profile.title)?;
+        let city_selector = selector(&profile.city)?;
+        let venue_type_selector = profile.venue_type.as_deref().map(selector).transpose()?;
 
         let mut restaurants = Vec::new();
 
@@ -68,11 +122,83 @@ impl Scraper<Vec<RestaurantData>> for RestaurantListScraper {
                 .map(|e| e.inner_html())
                 .unwrap_or("N/A".to_string());
 
+            let venue_type = venue_type(restaurant_list_element, venue_type_selector.as_ref());
+
             if !title.is_empty() && !city.is_empty() && !link_target.is_empty() {
                 restaurants.push(RestaurantData {
                     name: title,
                     city,
                     crous_url: link_target.to_string(),
+                    venue_type,
+                });
+            }
+        }
+
+        Ok(restaurants)
+    }
+
+    /// Reads the markers embedded as JSON, or the marker elements when
+    /// there's no such script.
+    fn parse_map(
+        profile: &ListProfile,
+        document: &Html,
+    ) -> Result<Vec<RestaurantData>, RestaurantListScraperError> {
+        if let Some(markers) = &profile.markers {
+            let restaurants: Vec<RestaurantData> = document
+                .select(&selector(markers)?)
+                .flat_map(|script| {
+                    markers_json(
+                        &script.text().collect::<String>(),
+                        profile.markers_variable.as_deref(),
+                    )
+                })
+                .collect();
+            if !restaurants.is_empty() {
+                return Ok(restaurants);
+            }
+        }
+
+        let marker_selector = selector(&profile.restaurant)?;
+        let title_selector = selector(&profile.title)?;
+        let city_selector = selector(&profile.city)?;
+        let venue_type_selector = profile.venue_type.as_deref().map(selector).transpose()?;
+        let link_selector = selector("a[href]")?;
+
+        let mut restaurants = Vec::new();
+
+        for marker in document.select(&marker_selector) {
+            let attributes = marker.value();
+            let link_target = attributes
+                .attr("href")
+                .or(attributes.attr("data-url"))
+                .or_else(|| {
+                    marker
+                        .select(&link_selector)
+                        .next()
+                        .and_then(|link| link.value().attr("href"))
+                })
+                .unwrap_or_default();
+
+            let title = marker
+                .select(&title_selector)
+                .next()
+                .map(|e| e.inner_html())
+                .or(attributes.attr("data-title").map(str::to_string))
+                .unwrap_or_default();
+
+            let city = marker
+                .select(&city_selector)
+                .next()
+                .map(|e| e.inner_html())
+                .or(attributes.attr("data-zone").map(str::to_string))
+                .unwrap_or("N/A".to_string());
+
+            if !title.is_empty() && !link_target.is_empty() {
+                restaurants.push(RestaurantData {
+                    name: title,
+                    city,
+                    crous_url: link_target.to_string(),
+                    venue_type: venue_type(marker, venue_type_selector.as_ref()),
                 });
             }
         }
@@ -81,13 +207,96 @@ impl Scraper<Vec<RestaurantData>> for RestaurantListScraper {
     }
 }
 
+/// Type label of a list entry, from the profile's selector or else its
+/// `data-type`.
+fn venue_type(element: ElementRef, selector: Option<&Selector>) -> Option<String> {
+    selector
+        .and_then(|selector| element.select(selector).next())
+        .map(|e| e.text().collect::<String>())
+        .or(element.value().attr("data-type").map(str::to_string))
+        .map(|label| label.trim().to_string())
+        .filter(|label| !label.is_empty())
+}
+
+/// Restaurants of a script embedding the markers in `variable`, or in its
+/// first JSON value when the profile doesn't name one, either as a bare
+/// array or as the `markers` field of an object, e.g.
+/// `var restaurantsMap = {"markers": [...]};`.
+fn markers_json(script: &str, variable: Option<&str>) -> Vec<RestaurantData> {
+    let start = match variable {
+        Some(variable) => assignment(script, variable),
+        None => script.find(['{', '[']),
+    };
+    // only the value itself is read, whatever statements follow it
+    let Some(Ok(value)) = start.and_then(|start| {
+        serde_json::Deserializer::from_str(&script[start..])
+            .into_iter::<Value>()
+            .next()
+    }) else {
+        return Vec::new();
+    };
+    let markers = match &value {
+        Value::Array(markers) => markers,
+        Value::Object(fields) => match fields.get("markers").and_then(Value::as_array) {
+            Some(markers) => markers,
+            None => return Vec::new(),
+        },
+        _ => return Vec::new(),
+    };
+
+    markers
+        .iter()
+        .filter_map(|marker| {
+            let field = |keys: &[&str]| {
+                keys.iter()
+                    .find_map(|key| marker.get(*key)?.as_str())
+                    .map(str::trim)
+                    .filter(|value| !value.is_empty())
+                    .map(str::to_string)
+            };
+            Some(RestaurantData {
+                name: field(&["title", "name"])?,
+                city: field(&["zone", "area", "city"]).unwrap_or("N/A".to_string()),
+                crous_url: field(&["url", "link", "permalink"])?,
+                venue_type: field(&["type", "category"]),
+            })
+        })
+        .collect()
+}
+
+/// Offset of the value assigned to `variable` in `script`.
+fn assignment(script: &str, variable: &str) -> Option<usize> {
+    let is_identifier = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    script.match_indices(variable).find_map(|(index, _)| {
+        if script[..index]
+            .chars()
+            .next_back()
+            .is_some_and(is_identifier)
+        {
+            return None;
+        }
+        let after = &script[index + variable.len()..];
+        let value = after.trim_start().strip_prefix('=')?;
+        if value.starts_with('=') {
+            return None;
+        }
+        let value = value.trim_start();
+        Some(script.len() - value.len())
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{fetcher::FixtureFetcher, profile::SelectorProfiles};
+
+    fn stubs() -> Arc<dyn Fetcher> {
+        Arc::new(FixtureFetcher::new("./src/stubs").unwrap())
+    }
 
     #[tokio::test]
     async fn test_restaurant_list_scraper() {
-        let scraper = RestaurantListScraper::new("./src/stubs/ou-manger.html".to_string());
+        let scraper = RestaurantListScraper::new("ou-manger.html".to_string(), stubs());
         let result = scraper.scrape().await;
 
         insta::assert_debug_snapshot!(result);
@@ -95,9 +304,87 @@ mod tests {
 
     #[tokio::test]
     async fn test_restaurant_list_scraper_bfc() {
-        let scraper = RestaurantListScraper::new("./src/stubs/ou-manger-bfc.html".to_string());
+        let scraper = RestaurantListScraper::new("ou-manger-bfc.html".to_string(), stubs());
+        let result = scraper.scrape().await;
+
+        insta::assert_debug_snapshot!(result);
+    }
+
+    #[tokio::test]
+    async fn test_restaurant_list_scraper_paris_carte() {
+        let paris = SelectorProfiles::default().for_region("Paris");
+        let scraper = RestaurantListScraper::new(
+            "https://www.crous-paris.fr/se-restaurer/ou-manger/carte".to_string(),
+            stubs(),
+        )
+        .with_profile(paris);
         let result = scraper.scrape().await;
 
         insta::assert_debug_snapshot!(result);
     }
+
+    #[test]
+    fn test_restaurant_list_scraper_map_elements() {
+        let html = r#"<section class="vc_restaurants_map"><div class="restaurants_map">
+            <div class="marker" data-lat="48.84" data-lon="2.33" data-zone="Paris 5e">
+                <a href="https://www.crous-paris.fr/restaurant/restaurant-bullier/">
+                    <div class="restaurant_title">Restaurant Bullier</div>
+                </a>
+            </div>
+            <div class="marker" data-lat="48.85" data-lon="2.33" data-title="Restaurant Mabillon" data-type="Cafétéria"
+                data-url="https://www.crous-paris.fr/restaurant/restaurant-mabillon/">
+                <span class="restaurant_area">Paris 6e</span>
+            </div>
+        </div></section>"#;
+        let paris = SelectorProfiles::default().for_region("Paris");
+        let restaurants =
+            RestaurantListScraper::parse(&paris.list, &Html::parse_document(html)).unwrap();
+
+        let restaurants: Vec<(&str, &str, Option<&str>)> = restaurants
+            .iter()
+            .map(|restaurant| {
+                (
+                    restaurant.name.as_str(),
+                    restaurant.city.as_str(),
+                    restaurant.venue_type.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            restaurants,
+            vec![
+                ("Restaurant Bullier", "Paris 5e", None),
+                ("Restaurant Mabillon", "Paris 6e", Some("Cafétéria"))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_markers_json_reads_the_profile_variable() {
+        let script = r#"
+            var restaurantsMapL10n = {"zones":[{"title":"Paris 5e","url":"https://www.crous-paris.fr/zone/paris-5/"}]};
+            var restaurantsMap = {"icon":"marker.svg","markers":[{"title":"Restaurant Bullier","url":"https://www.crous-paris.fr/restaurant/restaurant-bullier/","zone":"Paris 5e","type":"Restaurant"}]};
+            if (window.restaurantsMap == null) { console.log({"markers": []}); }
+        "#;
+
+        let restaurants = markers_json(script, Some("restaurantsMap"));
+        let names: Vec<(&str, &str)> = restaurants
+            .iter()
+            .map(|restaurant| (restaurant.name.as_str(), restaurant.city.as_str()))
+            .collect();
+        assert_eq!(names, vec![("Restaurant Bullier", "Paris 5e")]);
+
+        assert!(markers_json(script, Some("restaurantsMarkers")).is_empty());
+        // without a variable, the first value has no markers field
+        assert!(markers_json(script, None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_restaurant_list_diagnostics() {
+        let scraper = RestaurantListScraper::new("ou-manger.html".to_string(), stubs());
+        let diagnostics = scraper.diagnose().await.unwrap();
+
+        assert!(diagnostics.is_healthy(), "{:?}", diagnostics);
+        assert!(diagnostics.selectors[&SelectorProfile::default().list.restaurant] > 0);
+    }
 }
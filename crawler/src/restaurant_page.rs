@@ -1,24 +1,46 @@
+use std::sync::Arc;
+
 use scraper::{Html, Selector};
 use thiserror::Error;
 
-use crate::Scraper;
+use crate::{
+    Scraper,
+    diagnostics::ParseDiagnostics,
+    fetcher::Fetcher,
+    profile::{PageProfile, SelectorProfile},
+};
 
-#[derive(Debug)]
 pub struct RestaurantPageScraper {
     url: String,
+    fetcher: Arc<dyn Fetcher>,
+    profile: Arc<SelectorProfile>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct RestaurantPageData {
+    /// Name of the restaurant as the page writes it
+    pub title: Option<String>,
     pub menus: Vec<MenuData>,
     pub hours: String,
     pub coordinates: (f64, f64),
+    pub address: Option<String>,
+    pub phone: Option<String>,
+    pub payment_methods: Vec<String>,
+    /// Services listed by the page, accessibility included
+    pub amenities: Vec<String>,
+    /// How to get there, from the access blocks or else the description
+    pub access: Option<String>,
+    pub photos: Vec<String>,
+    /// Texts of the banners of the page, closures and the like
+    pub notices: Vec<String>,
 }
 
 #[derive(Debug)]
 pub struct MenuData {
     pub date: String,
     pub meals: Vec<MealData>,
+    /// Texts standing in for dishes, e.g. `Menu non communiqué` or `Fermé`
+    pub notices: Vec<String>,
 }
 
 #[derive(Debug)]
@@ -35,54 +57,155 @@ pub struct FoodCategory {
 
 #[derive(Debug, Error)]
 pub enum RestaurantPageScraperError {
-    #[error("Failed to perform HTTP request")]
-    RequestFailed,
+    #[error("Failed to perform HTTP request: {0}")]
+    RequestFailed(String),
     #[error("Failed to parse HTML: {0}")]
     ParsingFailed(String),
 }
 
 impl RestaurantPageScraper {
-    pub fn new(url: String) -> Self {
-        Self { url }
+    pub fn new(url: String, fetcher: Arc<dyn Fetcher>) -> Self {
+        Self {
+            url,
+            fetcher,
+            profile: Arc::default(),
+        }
+    }
+
+    /// Reads the page with `profile` instead of the standard selectors.
+    pub fn with_profile(mut self, profile: Arc<SelectorProfile>) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Scrapes the page unless it didn't change since `consumer`
+    /// acknowledged it, along with what the selectors found on it.
+    pub async fn scrape_if_changed(
+        &self,
+        consumer: &str,
+    ) -> Result<Option<(RestaurantPageData, ParseDiagnostics)>, RestaurantPageScraperError> {
+        let document = crate::get_if_changed(&self.fetcher, &self.url, consumer)
+            .await
+            .map_err(|e| RestaurantPageScraperError::RequestFailed(e.to_string()))?;
+        document
+            .map(|document| self.parse_diagnosed(&document))
+            .transpose()
+    }
+
+    /// Scrapes the page along with what the selectors found on it.
+    pub async fn scrape_diagnosed(
+        &self,
+    ) -> Result<(RestaurantPageData, ParseDiagnostics), RestaurantPageScraperError> {
+        let document = crate::get(&self.fetcher, &self.url)
+            .await
+            .map_err(|e| RestaurantPageScraperError::RequestFailed(e.to_string()))?;
+        self.parse_diagnosed(&document)
+    }
+
+    fn parse_diagnosed(
+        &self,
+        document: &Html,
+    ) -> Result<(RestaurantPageData, ParseDiagnostics), RestaurantPageScraperError> {
+        let data = Self::parse(&self.profile.page, document)?;
+        Ok((data, Self::diagnostics(&self.profile, &self.url, document)))
+    }
+
+    /// Fetches the page and reports what the selectors found on it.
+    pub async fn diagnose(&self) -> Result<ParseDiagnostics, RestaurantPageScraperError> {
+        let document = crate::get(&self.fetcher, &self.url)
+            .await
+            .map_err(|e| RestaurantPageScraperError::RequestFailed(e.to_string()))?;
+        Ok(Self::diagnostics(&self.profile, &self.url, &document))
     }
 
-    fn parse(document: &Html) -> Result<RestaurantPageData, RestaurantPageScraperError> {
-        let menu_selector = Selector::parse("section.menus div.menu").map_err(|_| {
+    /// Menu selectors matching nothing aren't reported when a notice stands
+    /// in for the menus, as on a restaurant closed for the season.
+    pub fn diagnostics(profile: &SelectorProfile, url: &str, document: &Html) -> ParseDiagnostics {
+        let mut diagnostics = ParseDiagnostics::inspect(
+            url,
+            document,
+            &profile.page.selectors(),
+            &profile.page.sections,
+        );
+        let noticed = [&profile.page.notice, &profile.page.menu_notice]
+            .into_iter()
+            .filter_map(|notice| Selector::parse(notice).ok())
+            .any(|notice| document.select(&notice).next().is_some());
+        if noticed {
+            for selector in profile.page.menu_selectors() {
+                if diagnostics.selectors.get(selector) == Some(&0) {
+                    diagnostics.selectors.remove(selector);
+                }
+            }
+        }
+        diagnostics
+    }
+
+    fn parse(
+        profile: &PageProfile,
+        document: &Html,
+    ) -> Result<RestaurantPageData, RestaurantPageScraperError> {
+        let title_selector = Selector::parse(&profile.title).map_err(|_| {
+            RestaurantPageScraperError::ParsingFailed("Couldn't parse title selector".to_string())
+        })?;
+        let menu_selector = Selector::parse(&profile.menu).map_err(|_| {
             RestaurantPageScraperError::ParsingFailed("Couldn't parse menu selector".to_string())
         })?;
-        let date_selector = Selector::parse("time.menu_date_title").map_err(|_| {
+        let date_selector = Selector::parse(&profile.date).map_err(|_| {
             RestaurantPageScraperError::ParsingFailed("Couldn't parse date selector".to_string())
         })?;
-        let meal_selector = Selector::parse("div.meal").map_err(|_| {
+        let meal_selector = Selector::parse(&profile.meal).map_err(|_| {
             RestaurantPageScraperError::ParsingFailed("Couldn't parse meal selector".to_string())
         })?;
-        let meal_title_selector = Selector::parse("div.meal_title").map_err(|_| {
+        let meal_title_selector = Selector::parse(&profile.meal_title).map_err(|_| {
             RestaurantPageScraperError::ParsingFailed(
                 "Couldn't parse meal_title selector".to_string(),
             )
         })?;
-        let category_selector = Selector::parse("ul.meal_foodies > li").map_err(|_| {
+        let category_selector = Selector::parse(&profile.category).map_err(|_| {
             RestaurantPageScraperError::ParsingFailed(
                 "Couldn't parse category selector".to_string(),
             )
         })?;
-        let dish_selector = Selector::parse("ul > li").map_err(|_| {
+        let dish_selector = Selector::parse(&profile.dish).map_err(|_| {
             RestaurantPageScraperError::ParsingFailed("Couldn't parse dish selector".to_string())
         })?;
-        let info_selector = Selector::parse("section.infos div.info").map_err(|_| {
+        let info_selector = Selector::parse(&profile.info).map_err(|_| {
             RestaurantPageScraperError::ParsingFailed("Couldn't parse info selector".to_string())
         })?;
-        let info_title_selector = Selector::parse("div.info_title").map_err(|_| {
+        let info_title_selector = Selector::parse(&profile.info_title).map_err(|_| {
             RestaurantPageScraperError::ParsingFailed(
                 "Couldn't parse info_title selector".to_string(),
             )
         })?;
-        let info_p_selector = Selector::parse("p").map_err(|_| {
+        let info_p_selector = Selector::parse(&profile.info_text).map_err(|_| {
             RestaurantPageScraperError::ParsingFailed("Couldn't parse info p selector".to_string())
         })?;
-        let map_selector = Selector::parse("div#map").map_err(|_| {
+        let map_selector = Selector::parse(&profile.map).map_err(|_| {
             RestaurantPageScraperError::ParsingFailed("Couldn't parse map selector".to_string())
         })?;
+        let photo_selector = Selector::parse(&profile.photo).map_err(|_| {
+            RestaurantPageScraperError::ParsingFailed("Couldn't parse photo selector".to_string())
+        })?;
+        let description_selector = Selector::parse(&profile.description).map_err(|_| {
+            RestaurantPageScraperError::ParsingFailed(
+                "Couldn't parse description selector".to_string(),
+            )
+        })?;
+        let notice_selector = Selector::parse(&profile.notice).map_err(|_| {
+            RestaurantPageScraperError::ParsingFailed("Couldn't parse notice selector".to_string())
+        })?;
+        let menu_notice_selector = Selector::parse(&profile.menu_notice).map_err(|_| {
+            RestaurantPageScraperError::ParsingFailed(
+                "Couldn't parse menu_notice selector".to_string(),
+            )
+        })?;
+
+        let title = document
+            .select(&title_selector)
+            .next()
+            .map(|e| text_of(e.text()))
+            .filter(|title| !title.is_empty());
 
         let mut menus = Vec::new();
 
@@ -94,6 +217,11 @@ impl RestaurantPageScraper {
                 .unwrap_or_default();
 
             let mut meals = Vec::new();
+            let mut notices: Vec<String> = menu_el
+                .select(&menu_notice_selector)
+                .map(|e| text_of(e.text()))
+                .filter(|notice| !notice.is_empty())
+                .collect();
 
             for meal_el in menu_el.select(&meal_selector) {
                 let title = meal_el
@@ -112,15 +240,20 @@ impl RestaurantPageScraper {
                         .trim()
                         .to_string();
 
-                    let dishes = category_el
+                    let dishes: Vec<String> = category_el
                         .select(&dish_selector)
                         .map(|d| d.inner_html().trim().to_string())
                         .filter(|d| !d.is_empty())
                         .collect();
 
-                    if !name.is_empty() {
-                        categories.push(FoodCategory { name, dishes });
+                    if name.is_empty() {
+                        continue;
+                    }
+                    // a category without dishes is most likely a notice
+                    if dishes.is_empty() && !notices.contains(&name) {
+                        notices.push(name.clone());
                     }
+                    categories.push(FoodCategory { name, dishes });
                 }
 
                 if !title.is_empty() {
@@ -129,7 +262,11 @@ impl RestaurantPageScraper {
             }
 
             if !date.is_empty() {
-                menus.push(MenuData { date, meals });
+                menus.push(MenuData {
+                    date,
+                    meals,
+                    notices,
+                });
             }
         }
 
@@ -138,7 +275,7 @@ impl RestaurantPageScraper {
                 .select(&info_title_selector)
                 .next()
                 .map(|e| e.text().collect::<String>())?;
-            if title.contains("Horaires") {
+            if title.contains(&profile.hours_title) {
                 info_el
                     .select(&info_p_selector)
                     .next()
@@ -148,6 +285,76 @@ impl RestaurantPageScraper {
             }
         });
 
+        // title of each info block with the lines of its text, split on <br>
+        let infos: Vec<(String, Vec<String>)> = document
+            .select(&info_selector)
+            .filter_map(|info_el| {
+                let title = info_el
+                    .select(&info_title_selector)
+                    .next()
+                    .map(|e| e.text().collect::<String>())?;
+                let lines = info_el
+                    .select(&info_p_selector)
+                    .flat_map(|p| p.text())
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                Some((title, lines))
+            })
+            .collect();
+        let info_lines = |titles: &[&str]| -> Vec<String> {
+            infos
+                .iter()
+                .filter(|(title, _)| titles.iter().any(|wanted| title.contains(wanted)))
+                .flat_map(|(_, lines)| lines.iter().cloned())
+                .collect()
+        };
+        let joined = |lines: Vec<String>, separator: &str| {
+            Some(lines.join(separator)).filter(|text| !text.is_empty())
+        };
+
+        let address = joined(info_lines(&[&profile.infos.address]), ", ");
+        let phone = joined(info_lines(&[&profile.infos.phone]), " / ");
+        let payment_methods = info_lines(&[&profile.infos.payment]);
+        let amenities = info_lines(&[&profile.infos.amenities]);
+        let access_titles: Vec<&str> = profile.infos.access.iter().map(String::as_str).collect();
+        let access = joined(info_lines(&access_titles), "\n").or_else(|| {
+            document.select(&description_selector).next().and_then(|e| {
+                let lines: Vec<String> = e
+                    .text()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                joined(lines, "\n")
+            })
+        });
+
+        let mut photos: Vec<String> = Vec::new();
+        for photo in document.select(&photo_selector).filter_map(|img| {
+            img.value()
+                .attr("src")
+                .or(img.value().attr("data-src"))
+                .map(str::trim)
+                .filter(|src| !src.is_empty())
+        }) {
+            if !photos.iter().any(|known| known == photo) {
+                photos.push(photo.to_string());
+            }
+        }
+
+        let mut notices: Vec<String> = Vec::new();
+        for notice in document
+            .select(&notice_selector)
+            .map(|e| text_of(e.text()))
+            .filter(|notice| !notice.is_empty())
+        {
+            if !notices.contains(&notice) {
+                notices.push(notice);
+            }
+        }
+
         let coordinates = document.select(&map_selector).next().and_then(|map_el| {
             let lat = map_el.value().attr("data-lat")?.parse::<f64>().ok()?;
             let lon = map_el.value().attr("data-lon")?.parse::<f64>().ok()?;
@@ -159,22 +366,37 @@ impl RestaurantPageScraper {
         let coordinates = coordinates.unwrap_or((0.0, 0.0));
 
         Ok(RestaurantPageData {
+            title,
             menus,
             hours,
             coordinates,
+            address,
+            phone,
+            payment_methods,
+            amenities,
+            access,
+            photos,
+            notices,
         })
     }
 }
 
+/// Text nodes trimmed and joined by single spaces.
+fn text_of<'a>(text: impl Iterator<Item = &'a str>) -> String {
+    text.flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 impl Scraper<RestaurantPageData> for RestaurantPageScraper {
     type Failure = RestaurantPageScraperError;
 
     async fn scrape(&self) -> Result<RestaurantPageData, Self::Failure> {
-        let document = crate::get(&self.url)
+        let document = crate::get(&self.fetcher, &self.url)
             .await
-            .map_err(|_| RestaurantPageScraperError::RequestFailed)?;
+            .map_err(|e| RestaurantPageScraperError::RequestFailed(e.to_string()))?;
 
-        Self::parse(&document)
+        Self::parse(&self.profile.page, &document)
     }
 }
 
@@ -186,7 +408,7 @@ mod tests {
     fn test_restaurant_page_scraper() {
         let html = include_str!("stubs/brasserie-triolet.html");
         let document = Html::parse_document(html);
-        let result = RestaurantPageScraper::parse(&document);
+        let result = RestaurantPageScraper::parse(&SelectorProfile::default().page, &document);
         insta::assert_debug_snapshot!(result);
     }
 
@@ -194,7 +416,108 @@ mod tests {
     fn test_restaurant_page_scraper_sevenans() {
         let html = include_str!("stubs/sevenans.html");
         let document = Html::parse_document(html);
-        let result = RestaurantPageScraper::parse(&document);
+        let result = RestaurantPageScraper::parse(&SelectorProfile::default().page, &document);
         insta::assert_debug_snapshot!(result);
     }
+
+    #[test]
+    fn test_restaurant_page_access_and_lazy_photos() {
+        let html = r#"<section class="infos">
+            <div class="info"><div class="info_title">Accès</div><p>Entrée rue Bullier<br/>Porte B</p></div>
+            <div class="info"><div class="info_title">Transports</div><p>Tram 1, arrêt Universités</p></div>
+            <div class="photo"><img data-src="https://ephoto.nuonet.fr/link/a.jpeg" /></div>
+            <div class="photo"><img src="https://ephoto.nuonet.fr/link/a.jpeg" /></div>
+        </section>
+        <section class="localisation"><div class="localisation_content"><p>Campus</p></div></section>"#;
+        let page = RestaurantPageScraper::parse(
+            &SelectorProfile::default().page,
+            &Html::parse_document(html),
+        )
+        .unwrap();
+        assert_eq!(
+            page.access.as_deref(),
+            Some("Entrée rue Bullier\nPorte B\nTram 1, arrêt Universités")
+        );
+        assert_eq!(page.photos, vec!["https://ephoto.nuonet.fr/link/a.jpeg"]);
+        assert_eq!(page.address, None);
+        assert!(page.payment_methods.is_empty());
+    }
+
+    #[test]
+    fn test_restaurant_page_notices() {
+        let html = r#"<div class="restaurant_notice"><strong>Fermé</strong> pour travaux
+            du 2 au 13 mars 2026</div>
+        <section class="menus">
+            <div class="menu">
+                <time class="menu_date_title">Menu du lundi 2 mars 2026</time>
+                <p>Menu non communiqué</p>
+            </div>
+            <div class="menu">
+                <time class="menu_date_title">Menu du mardi 3 mars 2026</time>
+                <div class="meal"><div class="meal_title">Déjeuner</div>
+                    <ul class="meal_foodies"><li>Fermé</li></ul>
+                </div>
+            </div>
+        </section>"#;
+        let page = RestaurantPageScraper::parse(
+            &SelectorProfile::default().page,
+            &Html::parse_document(html),
+        )
+        .unwrap();
+        assert_eq!(
+            page.notices,
+            vec!["Fermé pour travaux du 2 au 13 mars 2026"]
+        );
+        assert_eq!(page.menus[0].notices, vec!["Menu non communiqué"]);
+        assert_eq!(page.menus[1].notices, vec!["Fermé"]);
+    }
+
+    #[test]
+    fn test_restaurant_page_diagnostics() {
+        let html = include_str!("stubs/brasserie-triolet.html");
+        let document = Html::parse_document(html);
+        let profile = SelectorProfile::default();
+        let diagnostics = RestaurantPageScraper::diagnostics(&profile, "triolet", &document);
+        assert!(diagnostics.is_healthy(), "{:?}", diagnostics);
+        assert!(diagnostics.selectors[&profile.page.menu] > 0);
+    }
+
+    #[test]
+    fn test_restaurant_page_diagnostics_drift() {
+        let html = include_str!("stubs/brasserie-triolet.html")
+            .replace("class=\"menus\"", "class=\"carte\"")
+            .replace("meal_title", "repas_titre");
+        let document = Html::parse_document(&html);
+        let profile = SelectorProfile::default();
+        let diagnostics = RestaurantPageScraper::diagnostics(&profile, "triolet", &document);
+        assert_eq!(
+            diagnostics.missing(),
+            vec![profile.page.meal_title.as_str(), profile.page.menu.as_str()]
+        );
+        assert_eq!(diagnostics.unknown_sections, vec!["carte"]);
+    }
+
+    #[test]
+    fn test_restaurant_page_diagnostics_closed_season() {
+        let html = include_str!("stubs/brasserie-triolet.html");
+        let start = html.find("<section class=\"menus\">").unwrap();
+        let end = start + html[start..].find("</section>").unwrap();
+        let html = format!(
+            "{}<section class=\"menus\"><p>Fermé pour l'été, réouverture le 1er septembre</p>{}",
+            &html[..start],
+            &html[end..]
+        );
+        let document = Html::parse_document(&html);
+        let profile = SelectorProfile::default();
+        let diagnostics = RestaurantPageScraper::diagnostics(&profile, "triolet", &document);
+        assert!(diagnostics.is_healthy(), "{:?}", diagnostics);
+        assert!(!diagnostics.selectors.contains_key(&profile.page.menu));
+        assert!(diagnostics.selectors[&profile.page.map] > 0);
+
+        // without the notice the missing menus are still reported
+        let html = html.replace("<p>Fermé pour l'été, réouverture le 1er septembre</p>", "");
+        let document = Html::parse_document(&html);
+        let diagnostics = RestaurantPageScraper::diagnostics(&profile, "triolet", &document);
+        assert!(diagnostics.missing().contains(&profile.page.menu.as_str()));
+    }
 }
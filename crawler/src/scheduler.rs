@@ -0,0 +1,580 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use reqwest::Url;
+use tokio::{
+    sync::{OnceCell, Semaphore},
+    time::Instant,
+};
+
+use crate::fetcher::{Conditional, FetchError, FetchFuture, Fetcher, Validators};
+
+pub const DEFAULT_USER_AGENT: &str = concat!(
+    "HackTheCrous-crawler/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://hackthecrous.com)"
+);
+
+/// How hard the crawler is allowed to hit a site.
+#[derive(Clone, Debug)]
+pub struct CrawlPolicy {
+    pub user_agent: String,
+    /// Requests in flight at once on the same host.
+    pub max_concurrency_per_host: usize,
+    /// Requests started per second on the same host, `0` disables pacing.
+    pub requests_per_second: f64,
+    pub respect_robots_txt: bool,
+    /// Retries of a request answered with a 429, a 5xx or not answered.
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for CrawlPolicy {
+    fn default() -> Self {
+        Self {
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            max_concurrency_per_host: 2,
+            requests_per_second: 1.0,
+            respect_robots_txt: true,
+            max_retries: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl CrawlPolicy {
+    /// Name matched against the `User-agent` lines of robots.txt, the
+    /// product part of the user agent.
+    fn robots_token(&self) -> &str {
+        self.user_agent
+            .split(['/', ' '])
+            .next()
+            .unwrap_or(&self.user_agent)
+    }
+
+    fn interval(&self) -> Duration {
+        if self.requests_per_second > 0.0 {
+            Duration::from_secs_f64(1.0 / self.requests_per_second)
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RobotsRule {
+    allow: bool,
+    pattern: String,
+}
+
+impl RobotsRule {
+    fn matches(&self, path: &str) -> bool {
+        let (pattern, anchored) = match self.pattern.strip_suffix('$') {
+            Some(pattern) => (pattern, true),
+            None => (self.pattern.as_str(), false),
+        };
+        let mut parts = pattern.split('*');
+        let Some(first) = parts.next() else {
+            return true;
+        };
+        let Some(mut rest) = path.strip_prefix(first) else {
+            return false;
+        };
+        let mut parts = parts.peekable();
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() && anchored {
+                return rest.ends_with(part);
+            }
+            match rest.find(part) {
+                Some(index) => rest = &rest[index + part.len()..],
+                None => return false,
+            }
+        }
+        !anchored || rest.is_empty()
+    }
+}
+
+/// The rules of a robots.txt that apply to one user agent.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Robots {
+    rules: Vec<RobotsRule>,
+    pub crawl_delay: Option<Duration>,
+}
+
+impl Robots {
+    /// Keeps the groups naming `agent`, or the `*` group when none does.
+    pub fn parse(body: &str, agent: &str) -> Self {
+        let agent = agent.to_lowercase();
+        let mut specific = Robots::default();
+        let mut wildcard = Robots::default();
+        let mut found_specific = false;
+
+        // agents of the group being read, and whether its rules started
+        let mut group: Vec<String> = Vec::new();
+        let mut in_rules = false;
+        for line in body.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim();
+
+            if key == "user-agent" {
+                if in_rules {
+                    group.clear();
+                    in_rules = false;
+                }
+                // an empty agent would be found in every name
+                if !value.is_empty() {
+                    group.push(value.to_lowercase());
+                }
+                continue;
+            }
+            in_rules = true;
+
+            let is_specific = group
+                .iter()
+                .any(|name| name != "*" && agent.contains(name.as_str()));
+            let targets = match (is_specific, group.iter().any(|name| name == "*")) {
+                (true, _) => {
+                    found_specific = true;
+                    &mut specific
+                }
+                (false, true) => &mut wildcard,
+                _ => continue,
+            };
+            match key.as_str() {
+                "allow" | "disallow" if !value.is_empty() => targets.rules.push(RobotsRule {
+                    allow: key == "allow",
+                    pattern: value.to_string(),
+                }),
+                "crawl-delay" => {
+                    targets.crawl_delay = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|delay| delay.is_finite() && *delay >= 0.0)
+                        .map(Duration::from_secs_f64)
+                }
+                _ => {}
+            }
+        }
+
+        if found_specific { specific } else { wildcard }
+    }
+
+    /// The longest matching rule wins, an `Allow` wins a tie.
+    pub fn allows(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|rule| rule.matches(path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+}
+
+struct HostState {
+    permits: Semaphore,
+    next_start: Mutex<Instant>,
+    robots: OnceCell<Robots>,
+}
+
+/// Wraps a `Fetcher` to crawl politely: caps the requests in flight and
+/// paces them per host, skips what robots.txt disallows and retries with
+/// backoff when a site is throttling or failing.
+pub struct CrawlScheduler {
+    inner: Arc<dyn Fetcher>,
+    policy: CrawlPolicy,
+    hosts: Mutex<HashMap<String, Arc<HostState>>>,
+}
+
+impl CrawlScheduler {
+    pub fn new(inner: Arc<dyn Fetcher>, policy: CrawlPolicy) -> Self {
+        Self {
+            inner,
+            policy,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn host(&self, origin: &str) -> Result<Arc<HostState>, FetchError> {
+        let mut hosts = self
+            .hosts
+            .lock()
+            .map_err(|e| FetchError::Io(e.to_string()))?;
+        Ok(hosts
+            .entry(origin.to_string())
+            .or_insert_with(|| {
+                Arc::new(HostState {
+                    permits: Semaphore::new(self.policy.max_concurrency_per_host.max(1)),
+                    next_start: Mutex::new(Instant::now()),
+                    robots: OnceCell::new(),
+                })
+            })
+            .clone())
+    }
+
+    /// Sends one request once the host has a free slot and its turn came.
+    async fn send<T>(
+        &self,
+        host: &HostState,
+        request: FetchFuture<'_, T>,
+    ) -> Result<T, FetchError> {
+        let _permit = host
+            .permits
+            .acquire()
+            .await
+            .map_err(|e| FetchError::Io(e.to_string()))?;
+        let interval = host
+            .robots
+            .get()
+            .and_then(|robots| robots.crawl_delay)
+            .map_or(self.policy.interval(), |delay| {
+                delay.max(self.policy.interval())
+            });
+        let start = {
+            let mut next_start = host
+                .next_start
+                .lock()
+                .map_err(|e| FetchError::Io(e.to_string()))?;
+            let start = (*next_start).max(Instant::now());
+            *next_start = start + interval;
+            start
+        };
+        tokio::time::sleep_until(start).await;
+        request.await
+    }
+
+    /// Follows RFC 9309: a robots.txt answered with a 4xx doesn't restrict
+    /// anything, while one that couldn't be read disallows everything until
+    /// it can, so the failure isn't kept.
+    async fn allowed(
+        &self,
+        host: &HostState,
+        origin: &str,
+        path: &str,
+    ) -> Result<bool, FetchError> {
+        let robots = host
+            .robots
+            .get_or_try_init(|| async {
+                let url = format!("{}/robots.txt", origin);
+                match self.send(host, self.inner.fetch(&url)).await {
+                    Ok(body) => Ok(Robots::parse(&body, self.policy.robots_token())),
+                    Err(FetchError::Status(400..=499)) => Ok(Robots::default()),
+                    Err(e) => Err(e),
+                }
+            })
+            .await?;
+        Ok(robots.allows(path))
+    }
+
+    async fn fetch_politely<'a, T>(
+        &'a self,
+        url: &str,
+        request: impl Fn() -> FetchFuture<'a, T>,
+    ) -> Result<T, FetchError> {
+        let Ok(parsed) = Url::parse(url) else {
+            return request().await;
+        };
+        let origin = parsed.origin().ascii_serialization();
+        let host = self.host(&origin)?;
+
+        if self.policy.respect_robots_txt {
+            let path = match parsed.query() {
+                Some(query) => format!("{}?{}", parsed.path(), query),
+                None => parsed.path().to_string(),
+            };
+            match self.allowed(&host, &origin, &path).await {
+                Ok(true) => {}
+                Ok(false) => return Err(FetchError::Disallowed(url.to_string())),
+                Err(e) => {
+                    return Err(FetchError::Disallowed(format!(
+                        "{} (robots.txt unavailable: {})",
+                        url, e
+                    )));
+                }
+            }
+        }
+
+        let mut attempt = 0;
+        loop {
+            let result = self.send(&host, request()).await;
+            let retry_after = match &result {
+                Err(FetchError::Throttled(_, retry_after)) => *retry_after,
+                Err(FetchError::Status(status)) if *status >= 500 => None,
+                Err(FetchError::RequestFailed(_)) => None,
+                _ => return result,
+            };
+            if attempt >= self.policy.max_retries {
+                return result;
+            }
+            let delay = retry_after
+                .unwrap_or_else(|| self.policy.backoff(attempt))
+                .min(self.policy.max_delay);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+impl Fetcher for CrawlScheduler {
+    fn fetch<'a>(&'a self, url: &'a str) -> FetchFuture<'a> {
+        Box::pin(self.fetch_politely(url, move || self.inner.fetch(url)))
+    }
+
+    fn fetch_conditional<'a>(
+        &'a self,
+        url: &'a str,
+        validators: &'a Validators,
+    ) -> FetchFuture<'a, Conditional> {
+        Box::pin(self.fetch_politely(url, move || self.inner.fetch_conditional(url, validators)))
+    }
+
+    fn changed(&self, url: &str, consumer: &str) -> bool {
+        self.inner.changed(url, consumer)
+    }
+
+    fn acknowledge(&self, url: &str, consumer: &str) -> Result<(), FetchError> {
+        self.inner.acknowledge(url, consumer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    const ROBOTS: &str = "
+        User-agent: *
+        Disallow: /admin
+        Crawl-delay: 5
+
+        User-agent: HackTheCrous-crawler
+        User-agent: other-bot
+        Disallow: /restaurant/private
+        Disallow: /*.pdf$
+        Allow: /restaurant/private/menu # still fine
+        Crawl-delay: 2
+    ";
+
+    #[test]
+    fn test_robots_specific_group() {
+        let robots = Robots::parse(ROBOTS, "HackTheCrous-crawler");
+        assert_eq!(robots.crawl_delay, Some(Duration::from_secs(2)));
+        assert!(robots.allows("/admin"));
+        assert!(robots.allows("/restaurant/resto-u-sevenans/"));
+        assert!(!robots.allows("/restaurant/private/"));
+        assert!(robots.allows("/restaurant/private/menu"));
+        assert!(!robots.allows("/files/menu.pdf"));
+        assert!(robots.allows("/files/menu.pdf?download"));
+    }
+
+    #[test]
+    fn test_robots_wildcard_group() {
+        let robots = Robots::parse(ROBOTS, "somebody-else");
+        assert_eq!(robots.crawl_delay, Some(Duration::from_secs(5)));
+        assert!(!robots.allows("/admin/login"));
+        assert!(robots.allows("/restaurant/private/"));
+        assert!(Robots::parse("", "somebody-else").allows("/admin"));
+    }
+
+    #[test]
+    fn test_robots_ignores_empty_agents() {
+        let robots = Robots::parse(
+            "User-agent:\nDisallow: /\n\nUser-agent: *\nDisallow: /admin",
+            "HackTheCrous-crawler",
+        );
+        assert!(robots.allows("/restaurant/resto-u-sevenans/"));
+        assert!(!robots.allows("/admin"));
+    }
+
+    /// Answers from a script of statuses per URL, `200` once it runs out.
+    #[derive(Default)]
+    struct ScriptedFetcher {
+        script: Mutex<HashMap<String, Vec<u16>>>,
+        calls: AtomicUsize,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    impl ScriptedFetcher {
+        fn with(url: &str, statuses: Vec<u16>) -> Self {
+            let fetcher = Self::default();
+            fetcher
+                .script
+                .lock()
+                .unwrap()
+                .insert(url.to_string(), statuses);
+            fetcher
+        }
+    }
+
+    impl Fetcher for ScriptedFetcher {
+        fn fetch<'a>(&'a self, url: &'a str) -> FetchFuture<'a> {
+            Box::pin(async move {
+                self.calls.fetch_add(1, Ordering::SeqCst);
+                let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                let status = self
+                    .script
+                    .lock()
+                    .unwrap()
+                    .get_mut(url)
+                    .and_then(|statuses| (!statuses.is_empty()).then(|| statuses.remove(0)))
+                    .unwrap_or(200);
+                match status {
+                    200 if url.ends_with("/robots.txt") => {
+                        Ok("User-agent: *\nDisallow: /private".to_string())
+                    }
+                    200 => Ok(url.to_string()),
+                    429 => Err(FetchError::Throttled(429, None)),
+                    status => Err(FetchError::Status(status)),
+                }
+            })
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_skips_disallowed_pages() {
+        let inner = Arc::new(ScriptedFetcher::default());
+        let scheduler = CrawlScheduler::new(inner.clone(), CrawlPolicy::default());
+        assert!(matches!(
+            scheduler.fetch("https://crous.fr/private/page").await,
+            Err(FetchError::Disallowed(_))
+        ));
+        assert!(
+            scheduler
+                .fetch("https://crous.fr/public/page")
+                .await
+                .is_ok()
+        );
+        // robots.txt is read once for the host
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_unavailable_robots_txt() {
+        let robots = "https://crous.fr/robots.txt";
+        let inner = Arc::new(ScriptedFetcher::with(robots, vec![503]));
+        let scheduler = CrawlScheduler::new(inner.clone(), CrawlPolicy::default());
+        assert!(matches!(
+            scheduler.fetch("https://crous.fr/public/page").await,
+            Err(FetchError::Disallowed(_))
+        ));
+        // the failure isn't kept, robots.txt is read again
+        assert!(
+            scheduler
+                .fetch("https://crous.fr/public/page")
+                .await
+                .is_ok()
+        );
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+
+        let inner = Arc::new(ScriptedFetcher::with(robots, vec![404]));
+        let scheduler = CrawlScheduler::new(inner.clone(), CrawlPolicy::default());
+        for _ in 0..2 {
+            assert!(
+                scheduler
+                    .fetch("https://crous.fr/private/page")
+                    .await
+                    .is_ok()
+            );
+        }
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retries_throttled_and_failing_requests() {
+        let url = "https://crous.fr/restaurant";
+        let inner = Arc::new(ScriptedFetcher::with(url, vec![429, 503]));
+        let policy = CrawlPolicy {
+            respect_robots_txt: false,
+            ..CrawlPolicy::default()
+        };
+        assert_eq!(
+            CrawlScheduler::new(inner.clone(), policy.clone())
+                .fetch(url)
+                .await
+                .unwrap(),
+            url
+        );
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+
+        let inner = Arc::new(ScriptedFetcher::with(url, vec![404]));
+        assert!(matches!(
+            CrawlScheduler::new(inner.clone(), policy.clone())
+                .fetch(url)
+                .await,
+            Err(FetchError::Status(404))
+        ));
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+
+        let inner = Arc::new(ScriptedFetcher::with(url, vec![500; 10]));
+        assert!(matches!(
+            CrawlScheduler::new(inner.clone(), policy).fetch(url).await,
+            Err(FetchError::Status(500))
+        ));
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_paces_and_caps_requests_per_host() {
+        let inner = Arc::new(ScriptedFetcher::default());
+        let scheduler = CrawlScheduler::new(
+            inner.clone(),
+            CrawlPolicy {
+                respect_robots_txt: false,
+                max_concurrency_per_host: 2,
+                requests_per_second: 0.0,
+                ..CrawlPolicy::default()
+            },
+        );
+        let urls: Vec<String> = (0..6).map(|i| format!("https://crous.fr/{}", i)).collect();
+        let started = Instant::now();
+        fetch_all(Arc::new(scheduler), &urls).await;
+        assert_eq!(inner.max_in_flight.load(Ordering::SeqCst), 2);
+        assert!(started.elapsed() >= Duration::from_millis(300));
+
+        let inner = Arc::new(ScriptedFetcher::default());
+        let scheduler = CrawlScheduler::new(
+            inner.clone(),
+            CrawlPolicy {
+                respect_robots_txt: false,
+                max_concurrency_per_host: 10,
+                requests_per_second: 2.0,
+                ..CrawlPolicy::default()
+            },
+        );
+        let started = Instant::now();
+        fetch_all(Arc::new(scheduler), &urls[..3]).await;
+        // starts at 0s, 0.5s and 1s, the last one answering 100ms later
+        assert!(started.elapsed() >= Duration::from_millis(1100));
+    }
+
+    async fn fetch_all(scheduler: Arc<CrawlScheduler>, urls: &[String]) {
+        let mut tasks = tokio::task::JoinSet::new();
+        for url in urls {
+            let url = url.clone();
+            let scheduler = scheduler.clone();
+            tasks.spawn(async move { scheduler.fetch(&url).await.map(|_| ()) });
+        }
+        while let Some(result) = tasks.join_next().await {
+            result.unwrap().unwrap();
+        }
+    }
+}
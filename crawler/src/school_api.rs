@@ -1,8 +1,8 @@
-use std::fmt::Display;
+use std::{fmt::Display, sync::Arc};
 
 use serde::{Deserialize, Serialize};
 
-use crate::Scraper;
+use crate::{Scraper, fetcher::Fetcher};
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -54,22 +54,21 @@ struct HeraultData {
     results: Vec<ApiSchool>,
 }
 
-#[derive(Debug)]
 pub struct SchoolApiScraper {
     url: String,
+    fetcher: Arc<dyn Fetcher>,
 }
 
 impl SchoolApiScraper {
-    pub fn new(url: String) -> Self {
-        Self { url }
+    pub fn new(url: String, fetcher: Arc<dyn Fetcher>) -> Self {
+        Self { url, fetcher }
     }
 
     async fn fetch_page(&self, offset: usize) -> Result<HeraultData, SchoolApiScraperError> {
         let url = format!("{}?limit=100&offset={}", self.url, offset);
-        let body = reqwest::get(&url)
-            .await
-            .map_err(|_| SchoolApiScraperError::RequestFailed)?
-            .text()
+        let body = self
+            .fetcher
+            .fetch(&url)
             .await
             .map_err(|_| SchoolApiScraperError::RequestFailed)?;
 
@@ -1,5 +1,3 @@
-use htc::client::HTCClient;
-
 use crate::{Context, Error};
 
 /// Show this help menu
@@ -28,11 +26,13 @@ pub async fn help(
 #[poise::command(prefix_command, slash_command)]
 pub async fn subscribe(
     ctx: Context<'_>,
-    #[description = "Subscribe to a restaurant"] choice: String,
+    #[description = "Subscribe to a restaurant"]
+    #[rename = "choice"]
+    _choice: String,
 ) -> Result<(), Error> {
     // Lock the Mutex in a block {} so the Mutex isn't locked across an await point
 
-    let response = format!("Not implemented yet...");
+    let response = "Not implemented yet...".to_string();
     ctx.say(response).await?;
     Ok(())
 }
@@ -47,9 +47,11 @@ pub async fn subscribe(
 #[poise::command(prefix_command, slash_command)]
 pub async fn restaurant(
     ctx: Context<'_>,
-    #[description = "Look for a restaurant meal"] restaurant: String,
+    #[description = "Look for a restaurant meal"]
+    #[rename = "restaurant"]
+    _restaurant: String,
 ) -> Result<(), Error> {
     let answer = ctx.data().client.get_restaurants(htc::regions::CrousRegion::Montpellier).await.unwrap();
-    ctx.say(format!("Restaurant : {:#?}", answer.get(0).unwrap())).await?;
+    ctx.say(format!("Restaurant : {:#?}", answer.first().unwrap())).await?;
     Ok(())
 }
@@ -1,9 +1,8 @@
 use htc::client::HTCClient;
 use poise::serenity_prelude as serenity;
 use std::{
-    collections::HashMap,
     env::var,
-    sync::{Arc, Mutex},
+    sync::Arc,
     time::Duration,
 };
 use tracing::info;
@@ -100,7 +99,7 @@ async fn main() {
                 println!("Logged in as {}", ready.user.name);
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
                 Ok(Data {
-                    client: HTCClient::new(api_url, "test".to_string(), "admin".to_string())
+                    client: HTCClient::new(api_url, "test".to_string(), "admin".to_string())?
                 })
             })
         })
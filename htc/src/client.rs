@@ -1,9 +1,35 @@
+use std::time::Duration;
+
+use futures::Stream;
+use rand::Rng;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::{Serialize, de::DeserializeOwned};
+
 use crate::{
-    models::{meals::MealSchema, restaurants::RestaurantSchema},
+    events::{self, SubscriptionEvent},
+    models::{
+        audit::{AuditEntrySchema, AuditQuerySchema},
+        health::{HealthQuerySchema, HealthReportSchema, RegionHealthSchema},
+        meals::{MealSchema, MenuSchema},
+        restaurants::{RestaurantQuerySchema, RestaurantSchema},
+        scrape_batch::{BatchQuerySchema, EventFilterSchema, ScrapeBatch},
+        tokens::{IssuedTokenSchema, TokenRequestSchema, TokenRevocationSchema},
+        webhooks::{
+            DeliveryQuerySchema, RegisteredWebhookSchema, WebhookDeliverySchema,
+            WebhookQuerySchema, WebhookRequestSchema, WebhookSchema, WebhookTargetSchema,
+        },
+    },
     regions::CrousRegion,
     verifiable::SignedPayload,
 };
-use reqwest::{Client, Response};
+
+/// Sent as the User-Agent so the API can record which client pushed a batch.
+pub const CLIENT_VERSION: &str = concat!("htc-client/", env!("CARGO_PKG_VERSION"));
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// The API sends a keep-alive comment every 15 seconds on `/events`.
+const DEFAULT_HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(45);
 
 #[derive(Clone)]
 pub struct HTCClient {
@@ -11,28 +37,293 @@ pub struct HTCClient {
     pub client: Client,
     pub private_key: String,
     pub author: String,
+    pub retry: RetryPolicy,
+    pub heartbeat_timeout: Duration,
+    // same as `client` without the total timeout, for long-lived streams
+    pub(crate) stream_client: Client,
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum ClientError {
-    #[error("Couldn't put restaurant : {0}")]
-    PutRestaurantFailed(String),
-    #[error("Couldn't put meals : {0}")]
-    PutMealsFailed(String),
+    #[error("Unauthorized : {0}")]
+    Unauthorized(String),
+    #[error("Not found : {0}")]
+    NotFound(String),
+    #[error("Sync skipped, the server already has this batch")]
+    SyncSkipped,
+    #[error("Rejected by the server ({0}) : {1}")]
+    Rejected(u16, String),
+    #[error("Server error ({0}) : {1}")]
+    ServerError(u16, String),
+    #[error("Request timed out : {0}")]
+    Timeout(String),
+    #[error("Couldn't reach the server : {0}")]
+    ConnectionFailed(String),
+    #[error("Couldn't decode response : {0}")]
+    InvalidResponse(String),
     #[error("Couldn't sign payload : {0}")]
     PayloadSigningFailed(String),
-    #[error("Couldn't get restaurants : {0}")]
-    GetRestaurantsFailed(String),
+    #[error("Invalid client configuration : {0}")]
+    InvalidConfiguration(String),
 }
 
-impl HTCClient {
-    pub fn new(url: String, private_key: String, author: String) -> Self {
-        HTCClient {
+impl ClientError {
+    /// Whether sending the same request again may succeed.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            ClientError::ServerError(_, _)
+                | ClientError::Timeout(_)
+                | ClientError::ConnectionFailed(_)
+        )
+    }
+
+    fn from_status(status: StatusCode, message: String) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ClientError::Unauthorized(message),
+            StatusCode::NOT_FOUND => ClientError::NotFound(message),
+            StatusCode::CONFLICT => ClientError::SyncSkipped,
+            status if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS => {
+                ClientError::ServerError(status.as_u16(), message)
+            }
+            status => ClientError::Rejected(status.as_u16(), message),
+        }
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            ClientError::Timeout(e.to_string())
+        } else if e.is_decode() {
+            ClientError::InvalidResponse(e.to_string())
+        } else {
+            ClientError::ConnectionFailed(e.to_string())
+        }
+    }
+}
+
+/// Exponential backoff with jitter, applied to transient failures only.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before the retry number `attempt` (starting at 0), picked
+    /// between half and all of the exponential delay.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let half = exponential / 2;
+        let jitter = rand::rng().random_range(0..=half.as_millis() as u64);
+        half + Duration::from_millis(jitter)
+    }
+}
+
+pub struct HTCClientBuilder {
+    url: String,
+    private_key: String,
+    author: String,
+    timeout: Duration,
+    connect_timeout: Duration,
+    user_agent: String,
+    retry: RetryPolicy,
+    heartbeat_timeout: Duration,
+}
+
+impl HTCClientBuilder {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            private_key: String::new(),
+            author: String::new(),
+            timeout: DEFAULT_TIMEOUT,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            user_agent: CLIENT_VERSION.to_string(),
+            retry: RetryPolicy::default(),
+            heartbeat_timeout: DEFAULT_HEARTBEAT_TIMEOUT,
+        }
+    }
+
+    /// Key and author used to sign writes. Read-only clients can skip it.
+    pub fn credentials(
+        mut self,
+        private_key: impl Into<String>,
+        author: impl Into<String>,
+    ) -> Self {
+        self.private_key = private_key.into();
+        self.author = author.into();
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.retry.max_retries = max_retries;
+        self
+    }
+
+    pub fn backoff(mut self, base_delay: Duration, max_delay: Duration) -> Self {
+        self.retry.base_delay = base_delay;
+        self.retry.max_delay = max_delay;
+        self
+    }
+
+    /// How long a subscription may stay silent before reconnecting.
+    pub fn heartbeat_timeout(mut self, heartbeat_timeout: Duration) -> Self {
+        self.heartbeat_timeout = heartbeat_timeout;
+        self
+    }
+
+    pub fn build(self) -> Result<HTCClient, ClientError> {
+        let url = self.url.trim_end_matches('/').to_string();
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(ClientError::InvalidConfiguration(format!(
+                "server url must start with http:// or https:// : {}",
+                url
+            )));
+        }
+
+        let client = Client::builder()
+            .user_agent(self.user_agent.clone())
+            .timeout(self.timeout)
+            .connect_timeout(self.connect_timeout)
+            .build()
+            .map_err(|e| ClientError::InvalidConfiguration(e.to_string()))?;
+        let stream_client = Client::builder()
+            .user_agent(self.user_agent)
+            .connect_timeout(self.connect_timeout)
+            .build()
+            .map_err(|e| ClientError::InvalidConfiguration(e.to_string()))?;
+
+        Ok(HTCClient {
             url,
-            client: reqwest::Client::new(),
-            private_key,
-            author,
+            client,
+            private_key: self.private_key,
+            author: self.author,
+            retry: self.retry,
+            heartbeat_timeout: self.heartbeat_timeout,
+            stream_client,
+        })
+    }
+}
+
+impl HTCClient {
+    /// Client with the default timeouts and retry policy, `Err` if `url`
+    /// isn't an http(s) URL.
+    pub fn new(url: String, private_key: String, author: String) -> Result<Self, ClientError> {
+        Self::builder(url).credentials(private_key, author).build()
+    }
+
+    pub fn builder(url: impl Into<String>) -> HTCClientBuilder {
+        HTCClientBuilder::new(url)
+    }
+
+    fn sign<T>(&self, payload: T) -> Result<SignedPayload<T>, ClientError>
+    where
+        T: Serialize + DeserializeOwned + std::fmt::Debug + Clone,
+    {
+        SignedPayload::<T>::sign(payload, &self.private_key, &self.author)
+            .map_err(|e| ClientError::PayloadSigningFailed(e.to_string()))
+    }
+
+    /// Sends a request, retrying transient failures when `retryable` is set,
+    /// and turns error statuses into a [`ClientError`].
+    async fn send(
+        &self,
+        request: RequestBuilder,
+        retryable: bool,
+    ) -> Result<Response, ClientError> {
+        let mut attempt = 0;
+        loop {
+            let Some(this_request) = request.try_clone() else {
+                return Self::check(request.send().await?).await;
+            };
+
+            let result = match this_request.send().await {
+                Ok(response) => Self::check(response).await,
+                Err(e) => Err(e.into()),
+            };
+
+            match result {
+                Err(e) if retryable && e.is_transient() && attempt < self.retry.max_retries => {
+                    tokio::time::sleep(self.retry.delay(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    pub(crate) async fn check(response: Response) -> Result<Response, ClientError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
         }
+        let body = response.text().await.unwrap_or_default();
+        // the API answers `{"error": "..."}`
+        let message = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|json| json["error"].as_str().map(str::to_string))
+            .unwrap_or(body);
+        Err(ClientError::from_status(status, message))
+    }
+
+    /// Streams the batches published on `/events` for the scope of `token`,
+    /// and the times the server dropped some as the subscriber lagged. The
+    /// stream reconnects on its own and only ends on a non transient error,
+    /// like a revoked token.
+    pub fn subscribe(
+        &self,
+        token: impl Into<String>,
+    ) -> impl Stream<Item = Result<SubscriptionEvent, ClientError>> + Send + 'static {
+        self.subscribe_from(token, EventFilterSchema::default(), None)
+    }
+
+    /// Same as [`HTCClient::subscribe`], only receiving the batches matching
+    /// `filter` and resuming after `last_event_id`.
+    pub fn subscribe_from(
+        &self,
+        token: impl Into<String>,
+        filter: EventFilterSchema,
+        last_event_id: Option<String>,
+    ) -> impl Stream<Item = Result<SubscriptionEvent, ClientError>> + Send + 'static {
+        events::subscribe(self.clone(), token.into(), filter, last_event_id)
     }
 
     pub async fn put_restaurants(
@@ -40,25 +331,12 @@ impl HTCClient {
         restaurants: Vec<RestaurantSchema>,
         region: CrousRegion,
     ) -> Result<(), ClientError> {
-        let client = Client::new();
-
-        let payload = SignedPayload::<Vec<RestaurantSchema>>::sign(
-            restaurants,
-            &self.private_key,
-            &self.author,
-        )
-        .map_err(|e| ClientError::PayloadSigningFailed(e.to_string()))?;
-
-        let response = client
-            .put(format!("{}/{}/restaurants", self.url, region.to_string()))
-            .json(&payload)
-            .send()
-            .await
-            .map_err(|e| ClientError::PutRestaurantFailed(e.to_string()))?;
-
-        response
-            .error_for_status()
-            .map_err(|e| ClientError::PutRestaurantFailed(e.to_string()))?;
+        let payload = self.sign(restaurants)?;
+        let request = self
+            .client
+            .put(format!("{}/{}/restaurants", self.url, region))
+            .json(&payload);
+        self.send(request, true).await?;
         Ok(())
     }
 
@@ -66,33 +344,259 @@ impl HTCClient {
         &self,
         region: CrousRegion,
     ) -> Result<Vec<RestaurantSchema>, ClientError> {
-        let client = Client::new();
-        let restaurants = client
-            .get(format!("{}/{}/restaurants", self.url, region.to_string()))
-            .send()
-            .await
-            .map_err(|e| ClientError::GetRestaurantsFailed(e.to_string()))?
-            .json::<Vec<RestaurantSchema>>()
+        self.query_restaurants(region, RestaurantQuerySchema::default())
             .await
-            .map_err(|e| ClientError::GetRestaurantsFailed(e.to_string()))?;
-        Ok(restaurants)
+    }
+
+    /// Restaurants of the region whose name or city contains `query`.
+    pub async fn search_restaurants(
+        &self,
+        region: CrousRegion,
+        query: &str,
+    ) -> Result<Vec<RestaurantSchema>, ClientError> {
+        self.query_restaurants(
+            region,
+            RestaurantQuerySchema {
+                q: Some(query.to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    pub async fn query_restaurants(
+        &self,
+        region: CrousRegion,
+        query: RestaurantQuerySchema,
+    ) -> Result<Vec<RestaurantSchema>, ClientError> {
+        let request = self
+            .client
+            .get(format!("{}/{}/restaurants", self.url, region))
+            .query(&query);
+        Ok(self.send(request, true).await?.json().await?)
+    }
+
+    pub async fn get_restaurant(
+        &self,
+        region: CrousRegion,
+        restaurant_id: &str,
+    ) -> Result<RestaurantSchema, ClientError> {
+        let request = self.client.get(format!(
+            "{}/{}/restaurants/{}",
+            self.url, region, restaurant_id
+        ));
+        Ok(self.send(request, true).await?.json().await?)
+    }
+
+    /// Menus of a restaurant, one per day.
+    pub async fn get_meals(
+        &self,
+        region: CrousRegion,
+        restaurant_id: &str,
+    ) -> Result<Vec<MenuSchema>, ClientError> {
+        let request = self
+            .client
+            .get(format!("{}/{}/meals/{}", self.url, region, restaurant_id));
+        Ok(self.send(request, true).await?.json().await?)
+    }
+
+    pub async fn get_batches(
+        &self,
+        region: CrousRegion,
+        query: BatchQuerySchema,
+    ) -> Result<Vec<ScrapeBatch>, ClientError> {
+        let request = self
+            .client
+            .get(format!("{}/{}/batches", self.url, region))
+            .query(&query);
+        Ok(self.send(request, true).await?.json().await?)
     }
 
     pub async fn put_meals(
         &self,
         meals: Vec<MealSchema>,
         region: CrousRegion,
-    ) -> Result<Response, ClientError> {
-        let client = Client::new();
-
-        let payload =
-            SignedPayload::<Vec<MealSchema>>::sign(meals, &self.private_key, &self.author)
-                .map_err(|e| ClientError::PayloadSigningFailed(e.to_string()))?;
-        client
+    ) -> Result<(), ClientError> {
+        let payload = self.sign(meals)?;
+        let request = self
+            .client
             .put(format!("{}/{}/meals", self.url, region))
-            .json(&payload)
-            .send()
-            .await
-            .map_err(|e| ClientError::PutRestaurantFailed(e.to_string()))
+            .json(&payload);
+        self.send(request, true).await?;
+        Ok(())
+    }
+
+    pub async fn issue_token(
+        &self,
+        request: TokenRequestSchema,
+    ) -> Result<IssuedTokenSchema, ClientError> {
+        let payload = self.sign(request)?;
+        let request = self
+            .client
+            .post(format!("{}/tokens", self.url))
+            .json(&payload);
+        // not idempotent, a retry could issue the token twice
+        Ok(self.send(request, false).await?.json().await?)
+    }
+
+    pub async fn revoke_token(&self, revocation: TokenRevocationSchema) -> Result<(), ClientError> {
+        let token_id = revocation.token_id;
+        let payload = self.sign(revocation)?;
+        let request = self
+            .client
+            .delete(format!("{}/tokens/{}", self.url, token_id))
+            .json(&payload);
+        // a retry after a revocation that went through would get a 404
+        self.send(request, false).await?;
+        Ok(())
+    }
+
+    pub async fn get_audit(
+        &self,
+        query: AuditQuerySchema,
+    ) -> Result<Vec<AuditEntrySchema>, ClientError> {
+        let payload = self.sign(query)?;
+        let request = self
+            .client
+            .get(format!("{}/audit", self.url))
+            .json(&payload);
+        Ok(self.send(request, true).await?.json().await?)
+    }
+
+    pub async fn register_webhook(
+        &self,
+        request: WebhookRequestSchema,
+    ) -> Result<RegisteredWebhookSchema, ClientError> {
+        let payload = self.sign(request)?;
+        let request = self
+            .client
+            .post(format!("{}/webhooks", self.url))
+            .json(&payload);
+        // not idempotent, a retry could register the webhook twice
+        Ok(self.send(request, false).await?.json().await?)
+    }
+
+    pub async fn get_webhooks(
+        &self,
+        query: WebhookQuerySchema,
+    ) -> Result<Vec<WebhookSchema>, ClientError> {
+        let payload = self.sign(query)?;
+        let request = self
+            .client
+            .get(format!("{}/webhooks", self.url))
+            .json(&payload);
+        Ok(self.send(request, true).await?.json().await?)
+    }
+
+    pub async fn delete_webhook(&self, target: WebhookTargetSchema) -> Result<(), ClientError> {
+        let webhook_id = target.webhook_id;
+        let payload = self.sign(target)?;
+        let request = self
+            .client
+            .delete(format!("{}/webhooks/{}", self.url, webhook_id))
+            .json(&payload);
+        // a retry after a deletion that went through would get a 404
+        self.send(request, false).await?;
+        Ok(())
+    }
+
+    pub async fn enable_webhook(&self, target: WebhookTargetSchema) -> Result<(), ClientError> {
+        let webhook_id = target.webhook_id;
+        let payload = self.sign(target)?;
+        let request = self
+            .client
+            .put(format!("{}/webhooks/{}/enabled", self.url, webhook_id))
+            .json(&payload);
+        // not idempotent, enabling resets the failure count and is audited
+        self.send(request, false).await?;
+        Ok(())
+    }
+
+    pub async fn get_webhook_deliveries(
+        &self,
+        query: DeliveryQuerySchema,
+    ) -> Result<Vec<WebhookDeliverySchema>, ClientError> {
+        let webhook_id = query.webhook_id;
+        let payload = self.sign(query)?;
+        let request = self
+            .client
+            .get(format!("{}/webhooks/{}/deliveries", self.url, webhook_id))
+            .json(&payload);
+        Ok(self.send(request, true).await?.json().await?)
+    }
+
+    pub async fn put_health(
+        &self,
+        report: HealthReportSchema,
+        region: CrousRegion,
+    ) -> Result<RegionHealthSchema, ClientError> {
+        let payload = self.sign(report)?;
+        let request = self
+            .client
+            .put(format!("{}/{}/health", self.url, region))
+            .json(&payload);
+        // every push is a new record, a retry would record the check twice
+        Ok(self.send(request, false).await?.json().await?)
+    }
+
+    pub async fn get_health(
+        &self,
+        region: CrousRegion,
+        query: HealthQuerySchema,
+    ) -> Result<Vec<RegionHealthSchema>, ClientError> {
+        let request = self
+            .client
+            .get(format!("{}/{}/health", self.url, region))
+            .query(&query);
+        Ok(self.send(request, true).await?.json().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_mapping() {
+        assert!(matches!(
+            ClientError::from_status(StatusCode::UNAUTHORIZED, "bad key".to_string()),
+            ClientError::Unauthorized(_)
+        ));
+        assert!(matches!(
+            ClientError::from_status(StatusCode::CONFLICT, String::new()),
+            ClientError::SyncSkipped
+        ));
+        assert!(matches!(
+            ClientError::from_status(StatusCode::NOT_FOUND, String::new()),
+            ClientError::NotFound(_)
+        ));
+        let server_error = ClientError::from_status(StatusCode::BAD_GATEWAY, String::new());
+        assert!(server_error.is_transient());
+        let rejected = ClientError::from_status(StatusCode::BAD_REQUEST, String::new());
+        assert!(!rejected.is_transient());
+    }
+
+    #[test]
+    fn test_retry_delay_is_bounded() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+        };
+        for attempt in 0..10 {
+            let delay = policy.delay(attempt);
+            let exponential = (100u64 << attempt).min(1000);
+            assert!(delay >= Duration::from_millis(exponential / 2));
+            assert!(delay <= Duration::from_millis(exponential));
+        }
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_url() {
+        assert!(HTCClient::builder("api.hackthecrous.com").build().is_err());
+        let client = HTCClient::builder("https://api.hackthecrous.com/")
+            .build()
+            .unwrap();
+        assert_eq!(client.url, "https://api.hackthecrous.com");
     }
 }
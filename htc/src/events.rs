@@ -0,0 +1,283 @@
+use std::{collections::VecDeque, time::Duration};
+
+use futures::{Stream, stream};
+use reqwest::{Response, header};
+use serde::Deserialize;
+
+use crate::{
+    client::{ClientError, HTCClient},
+    models::scrape_batch::{EventFilterSchema, ScrapeBatch},
+};
+
+/// One dispatched server-sent event.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    pub id: Option<String>,
+    pub event: Option<String>,
+    pub data: String,
+    pub retry: Option<Duration>,
+}
+
+/// What a subscription yields.
+#[derive(Debug, Clone)]
+pub enum SubscriptionEvent {
+    Batch(Box<ScrapeBatch>),
+    /// The server dropped `missed` batches the subscriber was too slow for,
+    /// the stream goes on after `last_seq`
+    Lagged {
+        missed: u64,
+        last_seq: i64,
+    },
+}
+
+#[derive(Deserialize)]
+struct Lagged {
+    missed: u64,
+    last_seq: i64,
+}
+
+impl SubscriptionEvent {
+    /// The event carried by `event`, `None` for the ones only updating the
+    /// id or retry delay and for unknown event types.
+    fn decode(event: &SseEvent) -> Option<Result<Self, ClientError>> {
+        if event.data.is_empty() {
+            return None;
+        }
+        let decoded = match event.event.as_deref() {
+            None | Some("message") => serde_json::from_str::<ScrapeBatch>(&event.data)
+                .map(|batch| SubscriptionEvent::Batch(Box::new(batch))),
+            Some("lagged") => serde_json::from_str::<Lagged>(&event.data).map(|lagged| {
+                SubscriptionEvent::Lagged {
+                    missed: lagged.missed,
+                    last_seq: lagged.last_seq,
+                }
+            }),
+            Some(_) => return None,
+        };
+        Some(decoded.map_err(|e| ClientError::InvalidResponse(e.to_string())))
+    }
+}
+
+/// Incremental `text/event-stream` parser, fed with raw chunks as they come
+/// from the socket. Lines split across chunks are kept until complete.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    current: SseEvent,
+    has_data: bool,
+}
+
+impl SseParser {
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if let Some(event) = self.process_line(line) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            let event = std::mem::take(&mut self.current);
+            let has_data = std::mem::take(&mut self.has_data);
+            // an empty block still carries the `retry` and `id` updates
+            return (has_data || event.retry.is_some() || event.id.is_some()).then_some(event);
+        }
+        if line.starts_with(':') {
+            // comment, used as heartbeat
+            return None;
+        }
+
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "data" => {
+                if self.has_data {
+                    self.current.data.push('\n');
+                }
+                self.current.data.push_str(value);
+                self.has_data = true;
+            }
+            "event" => self.current.event = Some(value.to_string()),
+            "id" if !value.contains('\0') => self.current.id = Some(value.to_string()),
+            "retry" => self.current.retry = value.parse().ok().map(Duration::from_millis),
+            _ => {}
+        }
+        None
+    }
+}
+
+/// State of a running `/events` subscription, reconnecting with
+/// `Last-Event-ID` whenever the stream drops or stays silent too long.
+struct Subscription {
+    client: HTCClient,
+    token: String,
+    filter: EventFilterSchema,
+    last_event_id: Option<String>,
+    response: Option<Response>,
+    parser: SseParser,
+    pending: VecDeque<SseEvent>,
+    attempt: u32,
+    server_retry: Option<Duration>,
+    done: bool,
+}
+
+impl Subscription {
+    async fn connect(&mut self) -> Result<Response, ClientError> {
+        let mut request = self
+            .client
+            .stream_client
+            .get(format!("{}/events", self.client.url))
+            .query(&self.filter)
+            .bearer_auth(&self.token)
+            .header(header::ACCEPT, "text/event-stream");
+        if let Some(last_event_id) = &self.last_event_id {
+            request = request.header("Last-Event-ID", last_event_id);
+        }
+        HTCClient::check(request.send().await?).await
+    }
+
+    async fn wait_before_reconnect(&mut self) {
+        let delay = self.client.retry.delay(self.attempt);
+        let delay = self.server_retry.map_or(delay, |retry| retry.max(delay));
+        self.attempt = self.attempt.saturating_add(1);
+        tokio::time::sleep(delay).await;
+    }
+
+    async fn next(&mut self) -> Option<Result<SubscriptionEvent, ClientError>> {
+        loop {
+            if self.done {
+                return None;
+            }
+
+            if let Some(event) = self.pending.pop_front() {
+                if event.id.is_some() {
+                    self.last_event_id = event.id.clone();
+                }
+                if event.retry.is_some() {
+                    self.server_retry = event.retry;
+                }
+                match SubscriptionEvent::decode(&event) {
+                    Some(decoded) => return Some(decoded),
+                    None => continue,
+                }
+            }
+
+            let Some(response) = self.response.as_mut() else {
+                match self.connect().await {
+                    Ok(response) => {
+                        self.response = Some(response);
+                        self.parser = SseParser::default();
+                    }
+                    Err(e) if e.is_transient() => self.wait_before_reconnect().await,
+                    Err(e) => {
+                        self.done = true;
+                        return Some(Err(e));
+                    }
+                }
+                continue;
+            };
+
+            match tokio::time::timeout(self.client.heartbeat_timeout, response.chunk()).await {
+                Ok(Ok(Some(chunk))) => {
+                    self.attempt = 0;
+                    self.pending.extend(self.parser.feed(&chunk));
+                }
+                // closed by the server, dropped, or no heartbeat in time
+                _ => {
+                    self.response = None;
+                    self.wait_before_reconnect().await;
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn subscribe(
+    client: HTCClient,
+    token: String,
+    filter: EventFilterSchema,
+    last_event_id: Option<String>,
+) -> impl Stream<Item = Result<SubscriptionEvent, ClientError>> + Send + 'static {
+    let subscription = Subscription {
+        client,
+        token,
+        filter,
+        last_event_id,
+        response: None,
+        parser: SseParser::default(),
+        pending: VecDeque::new(),
+        attempt: 0,
+        server_retry: None,
+        done: false,
+    };
+    stream::unfold(subscription, |mut subscription| async move {
+        subscription.next().await.map(|item| (item, subscription))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_events_split_across_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser.feed(b": keep-alive\n\nid: 4").is_empty());
+        let events = parser.feed(b"2\ndata: {\"a\":\r\ndata: 1}\n\nevent: lagged\ndata: 3\n\n");
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    id: Some("42".to_string()),
+                    event: None,
+                    data: "{\"a\":\n1}".to_string(),
+                    retry: None,
+                },
+                SseEvent {
+                    id: None,
+                    event: Some("lagged".to_string()),
+                    data: "3".to_string(),
+                    retry: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_retry_and_multibyte_split() {
+        let mut parser = SseParser::default();
+        let payload = "data: Crous de Créteil\n\n".as_bytes();
+        let (head, tail) = payload.split_at(18);
+        assert!(parser.feed(head).is_empty());
+        let events = parser.feed(tail);
+        assert_eq!(events[0].data, "Crous de Créteil");
+
+        let events = parser.feed(b"retry: 2500\n\n");
+        assert_eq!(events[0].retry, Some(Duration::from_millis(2500)));
+    }
+
+    #[test]
+    fn test_decode_lagged_events() {
+        let mut parser = SseParser::default();
+        let events = parser.feed(
+            b"event: lagged\nid: 17\ndata: {\"missed\":4,\"last_seq\":17}\n\nevent: ping\ndata: 1\n\n",
+        );
+        assert!(matches!(
+            SubscriptionEvent::decode(&events[0]),
+            Some(Ok(SubscriptionEvent::Lagged {
+                missed: 4,
+                last_seq: 17
+            }))
+        ));
+        assert!(SubscriptionEvent::decode(&events[1]).is_none());
+        assert!(SubscriptionEvent::decode(&SseEvent::default()).is_none());
+    }
+}
@@ -1,6 +1,8 @@
 pub mod client;
+pub mod events;
 pub mod id;
 pub mod models;
 pub mod regions;
 pub mod sources;
+pub mod validation;
 pub mod verifiable;
@@ -0,0 +1,227 @@
+use std::{fmt::Display, future::Future, str::FromStr};
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, types::Uuid};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ToSchema, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Success,
+    SyncSkipped,
+    Unauthorized,
+    Rejected,
+    Failed,
+}
+
+impl Display for AuditOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            AuditOutcome::Success => "success",
+            AuditOutcome::SyncSkipped => "sync_skipped",
+            AuditOutcome::Unauthorized => "unauthorized",
+            AuditOutcome::Rejected => "rejected",
+            AuditOutcome::Failed => "failed",
+        })
+    }
+}
+
+impl FromStr for AuditOutcome {
+    type Err = AuditModelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "success" => Ok(AuditOutcome::Success),
+            "sync_skipped" => Ok(AuditOutcome::SyncSkipped),
+            "unauthorized" => Ok(AuditOutcome::Unauthorized),
+            "rejected" => Ok(AuditOutcome::Rejected),
+            "failed" => Ok(AuditOutcome::Failed),
+            _ => Err(AuditModelError::UnknownOutcome(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, ToSchema, Serialize, Deserialize)]
+pub struct AuditEntrySchema {
+    #[schema(value_type = String)]
+    pub audit_id: Uuid,
+    pub action: String,
+    pub author: String,
+    pub key_fingerprint: Option<String>,
+    pub client_version: Option<String>,
+    pub source_ip: Option<String>,
+    pub region: Option<String>,
+    pub payload_digest: Option<String>,
+    pub outcome: AuditOutcome,
+    pub detail: Option<String>,
+    #[schema(value_type = Option<String>)]
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Default, ToSchema, Serialize, Deserialize)]
+pub struct AuditQuerySchema {
+    pub author: Option<String>,
+    pub action: Option<String>,
+    pub outcome: Option<AuditOutcome>,
+    pub region: Option<String>,
+    #[schema(value_type = Option<String>)]
+    pub since: Option<NaiveDateTime>,
+    pub limit: Option<i64>,
+    /// Unix timestamp the request was signed at, refused once older than
+    /// `MAX_REQUEST_AGE`
+    pub issued_at: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct AuditEntry {
+    pub audit_id: Uuid,
+    pub action: String,
+    pub author: String,
+    pub admin_id: Option<Uuid>,
+    pub key_fingerprint: Option<String>,
+    pub client_version: Option<String>,
+    pub source_ip: Option<String>,
+    pub region: Option<String>,
+    pub payload_digest: Option<String>,
+    pub outcome: AuditOutcome,
+    pub detail: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+impl From<AuditEntry> for AuditEntrySchema {
+    fn from(entry: AuditEntry) -> Self {
+        AuditEntrySchema {
+            audit_id: entry.audit_id,
+            action: entry.action,
+            author: entry.author,
+            key_fingerprint: entry.key_fingerprint,
+            client_version: entry.client_version,
+            source_ip: entry.source_ip,
+            region: entry.region,
+            payload_digest: entry.payload_digest,
+            outcome: entry.outcome,
+            detail: entry.detail,
+            created_at: entry.created_at,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum AuditModelError {
+    #[error("Unknown audit outcome : {0}")]
+    UnknownOutcome(String),
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+pub const DEFAULT_AUDIT_LIMIT: i64 = 50;
+pub const MAX_AUDIT_LIMIT: i64 = 500;
+
+pub trait AuditModel {
+    fn create_audit_entry(
+        &self,
+        entry: AuditEntry,
+    ) -> impl Future<Output = Result<(), AuditModelError>> + Send;
+    fn get_audit_entries(
+        &self,
+        query: &AuditQuerySchema,
+    ) -> impl Future<Output = Result<Vec<AuditEntry>, AuditModelError>> + Send;
+}
+
+impl AuditModel for PgPool {
+    async fn create_audit_entry(&self, entry: AuditEntry) -> Result<(), AuditModelError> {
+        sqlx::query!(
+            "INSERT INTO audit_log (audit_id, action, author, admin_id, key_fingerprint, client_version, source_ip, region, payload_digest, outcome, detail) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+            entry.audit_id,
+            entry.action,
+            entry.author,
+            entry.admin_id,
+            entry.key_fingerprint,
+            entry.client_version,
+            entry.source_ip,
+            entry.region,
+            entry.payload_digest,
+            entry.outcome.to_string(),
+            entry.detail
+        )
+        .execute(self)
+        .await
+        .map_err(|e| AuditModelError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_audit_entries(
+        &self,
+        query: &AuditQuerySchema,
+    ) -> Result<Vec<AuditEntry>, AuditModelError> {
+        let rows = sqlx::query!(
+            "SELECT audit_id, action, author, admin_id, key_fingerprint, client_version, source_ip, region, payload_digest, outcome, detail, created_at FROM audit_log
+            WHERE ($1::TEXT IS NULL OR author = $1)
+              AND ($2::TEXT IS NULL OR action = $2)
+              AND ($3::TEXT IS NULL OR outcome = $3)
+              AND ($4::TEXT IS NULL OR region = $4)
+              AND ($5::TIMESTAMP IS NULL OR created_at >= $5)
+            ORDER BY created_at DESC LIMIT $6",
+            query.author.as_deref(),
+            query.action.as_deref(),
+            query.outcome.as_ref().map(|outcome| outcome.to_string()),
+            query.region.as_deref(),
+            query.since,
+            query
+                .limit
+                .unwrap_or(DEFAULT_AUDIT_LIMIT)
+                .clamp(1, MAX_AUDIT_LIMIT)
+        )
+        .fetch_all(self)
+        .await
+        .map_err(|e| AuditModelError::DatabaseError(e.to_string()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(AuditEntry {
+                    audit_id: row.audit_id,
+                    action: row.action,
+                    author: row.author,
+                    admin_id: row.admin_id,
+                    key_fingerprint: row.key_fingerprint,
+                    client_version: row.client_version,
+                    source_ip: row.source_ip,
+                    region: row.region,
+                    payload_digest: row.payload_digest,
+                    outcome: row.outcome.parse()?,
+                    detail: row.detail,
+                    created_at: row.created_at,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcome_round_trip() {
+        for outcome in [
+            AuditOutcome::Success,
+            AuditOutcome::SyncSkipped,
+            AuditOutcome::Unauthorized,
+            AuditOutcome::Rejected,
+            AuditOutcome::Failed,
+        ] {
+            assert_eq!(
+                outcome.to_string().parse::<AuditOutcome>().unwrap(),
+                outcome
+            );
+            assert_eq!(
+                serde_json::to_string(&outcome).unwrap(),
+                format!("\"{}\"", outcome)
+            );
+        }
+        assert!("teapot".parse::<AuditOutcome>().is_err());
+    }
+}
@@ -0,0 +1,50 @@
+use std::future::Future;
+
+use chrono::NaiveDateTime;
+use sqlx::{PgPool, types::Uuid};
+use thiserror::Error;
+
+/// A notification a handler couldn't parse or process.
+#[derive(Clone, Debug)]
+pub struct DeadLetter {
+    pub dead_letter_id: Uuid,
+    pub channel: String,
+    pub handler: String,
+    pub payload: String,
+    pub error: String,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(Error, Debug)]
+pub enum DeadLetterModelError {
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+pub trait DeadLetterModel {
+    fn record_dead_letter(
+        &self,
+        dead_letter: DeadLetter,
+    ) -> impl Future<Output = Result<(), DeadLetterModelError>> + Send;
+}
+
+impl DeadLetterModel for PgPool {
+    async fn record_dead_letter(
+        &self,
+        dead_letter: DeadLetter,
+    ) -> Result<(), DeadLetterModelError> {
+        sqlx::query!(
+            "INSERT INTO event_dead_letters (dead_letter_id, channel, handler, payload, error) VALUES ($1, $2, $3, $4, $5)",
+            dead_letter.dead_letter_id,
+            dead_letter.channel,
+            dead_letter.handler,
+            dead_letter.payload,
+            dead_letter.error
+        )
+        .execute(self)
+        .await
+        .map_err(|e| DeadLetterModelError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+}
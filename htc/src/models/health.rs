@@ -0,0 +1,287 @@
+use std::{collections::BTreeMap, fmt::Display, future::Future, str::FromStr};
+
+use chrono::NaiveDateTime;
+use crawler::diagnostics::ParseDiagnostics;
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    PgPool,
+    types::{Json, Uuid},
+};
+use thiserror::Error;
+use utoipa::{IntoParams, ToSchema};
+
+pub const DEFAULT_HEALTH_LIMIT: i64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Healthy,
+    /// Some selectors match nothing on some pages, or unknown sections showed up
+    Degraded,
+    /// Some selectors match nothing on any page, the layout most likely changed
+    Drifted,
+}
+
+impl Display for HealthStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HealthStatus::Healthy => f.write_str("healthy"),
+            HealthStatus::Degraded => f.write_str("degraded"),
+            HealthStatus::Drifted => f.write_str("drifted"),
+        }
+    }
+}
+
+impl FromStr for HealthStatus {
+    type Err = HealthModelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "healthy" => Ok(HealthStatus::Healthy),
+            "degraded" => Ok(HealthStatus::Degraded),
+            "drifted" => Ok(HealthStatus::Drifted),
+            _ => Err(HealthModelError::UnknownStatus(s.to_string())),
+        }
+    }
+}
+
+/// What the selectors found on one sampled page.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct PageHealthSchema {
+    pub url: String,
+    /// Number of nodes matched by each selector
+    pub selectors: BTreeMap<String, usize>,
+    pub unknown_sections: Vec<String>,
+    /// Set when the page couldn't be fetched, `selectors` is then empty
+    pub error: Option<String>,
+}
+
+impl PageHealthSchema {
+    pub fn failed(url: impl Into<String>, error: impl ToString) -> Self {
+        Self {
+            url: url.into(),
+            error: Some(error.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<ParseDiagnostics> for PageHealthSchema {
+    fn from(diagnostics: ParseDiagnostics) -> Self {
+        Self {
+            url: diagnostics.url,
+            selectors: diagnostics.selectors,
+            unknown_sections: diagnostics.unknown_sections,
+            error: None,
+        }
+    }
+}
+
+/// Health of the scrapers on a sample of the pages of a region, pushed by
+/// `crousctl doctor`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct HealthReportSchema {
+    pub status: HealthStatus,
+    pub pages_checked: usize,
+    /// Selectors matching nothing on any of the pages they were tried on
+    pub drifted_selectors: Vec<String>,
+    /// Selectors matching nothing on some of the pages only
+    pub flaky_selectors: Vec<String>,
+    pub unknown_sections: Vec<String>,
+    pub pages: Vec<PageHealthSchema>,
+}
+
+impl HealthReportSchema {
+    pub fn from_pages(pages: Vec<PageHealthSchema>) -> Self {
+        // selector -> (pages it was tried on, pages it matched nothing on)
+        let mut tries: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        for page in pages.iter().filter(|page| page.error.is_none()) {
+            for (selector, count) in &page.selectors {
+                let entry = tries.entry(selector).or_default();
+                entry.0 += 1;
+                if *count == 0 {
+                    entry.1 += 1;
+                }
+            }
+        }
+        let drifted_selectors: Vec<String> = tries
+            .iter()
+            .filter(|(_, (tried, missed))| missed == tried)
+            .map(|(selector, _)| selector.to_string())
+            .collect();
+        let flaky_selectors: Vec<String> = tries
+            .iter()
+            .filter(|(_, (tried, missed))| *missed > 0 && missed < tried)
+            .map(|(selector, _)| selector.to_string())
+            .collect();
+        let mut unknown_sections: Vec<String> = pages
+            .iter()
+            .flat_map(|page| page.unknown_sections.iter().cloned())
+            .collect();
+        unknown_sections.sort();
+        unknown_sections.dedup();
+
+        let failed = pages.iter().any(|page| page.error.is_some());
+        let status = if !drifted_selectors.is_empty() {
+            HealthStatus::Drifted
+        } else if failed || !flaky_selectors.is_empty() || !unknown_sections.is_empty() {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Healthy
+        };
+
+        Self {
+            status,
+            pages_checked: pages.len(),
+            drifted_selectors,
+            flaky_selectors,
+            unknown_sections,
+            pages,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RegionHealthSchema {
+    #[schema(value_type = String)]
+    pub health_id: Uuid,
+    pub region: String,
+    #[schema(value_type = Option<String>)]
+    pub checked_at: Option<NaiveDateTime>,
+    pub report: HealthReportSchema,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HealthQuerySchema {
+    pub limit: Option<i64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RegionHealth {
+    pub health_id: Uuid,
+    pub region: String,
+    pub author: Uuid,
+    pub checked_at: Option<NaiveDateTime>,
+    pub report: HealthReportSchema,
+}
+
+impl From<RegionHealth> for RegionHealthSchema {
+    fn from(health: RegionHealth) -> Self {
+        RegionHealthSchema {
+            health_id: health.health_id,
+            region: health.region,
+            checked_at: health.checked_at,
+            report: health.report,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum HealthModelError {
+    #[error("Database error : {0}")]
+    DatabaseError(String),
+    #[error("Unknown health status : {0}")]
+    UnknownStatus(String),
+}
+
+pub trait HealthModel {
+    fn record_health(
+        &self,
+        health: RegionHealth,
+    ) -> impl Future<Output = Result<(), HealthModelError>> + Send;
+
+    /// Latest health reports of a region, most recent first.
+    fn get_health(
+        &self,
+        region: String,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<RegionHealth>, HealthModelError>> + Send;
+}
+
+impl HealthModel for PgPool {
+    async fn record_health(&self, health: RegionHealth) -> Result<(), HealthModelError> {
+        sqlx::query!(
+            "INSERT INTO region_health (health_id, region, status, pages_checked, report, author) VALUES ($1, $2, $3, $4, $5, $6)",
+            health.health_id,
+            health.region,
+            health.report.status.to_string(),
+            health.report.pages_checked as i32,
+            Json(&health.report) as _,
+            health.author
+        )
+        .execute(self)
+        .await
+        .map_err(|e| HealthModelError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_health(
+        &self,
+        region: String,
+        limit: i64,
+    ) -> Result<Vec<RegionHealth>, HealthModelError> {
+        let rows = sqlx::query!(
+            "SELECT health_id, region, author, checked_at, report as \"report: Json<HealthReportSchema>\" FROM region_health WHERE region = $1 ORDER BY checked_at DESC LIMIT $2",
+            region,
+            limit
+        )
+        .fetch_all(self)
+        .await
+        .map_err(|e| HealthModelError::DatabaseError(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| RegionHealth {
+                health_id: row.health_id,
+                region: row.region,
+                author: row.author,
+                checked_at: row.checked_at,
+                report: row.report.0,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(url: &str, selectors: &[(&str, usize)]) -> PageHealthSchema {
+        PageHealthSchema {
+            url: url.to_string(),
+            selectors: selectors
+                .iter()
+                .map(|(selector, count)| (selector.to_string(), *count))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_report_status() {
+        let healthy = HealthReportSchema::from_pages(vec![
+            page("a", &[("div.menu", 3), ("div#map", 1)]),
+            page("b", &[("div.menu", 2), ("div#map", 1)]),
+        ]);
+        assert_eq!(healthy.status, HealthStatus::Healthy);
+
+        let degraded = HealthReportSchema::from_pages(vec![
+            page("a", &[("div.menu", 3), ("div#map", 1)]),
+            page("b", &[("div.menu", 0), ("div#map", 1)]),
+        ]);
+        assert_eq!(degraded.status, HealthStatus::Degraded);
+        assert_eq!(degraded.flaky_selectors, vec!["div.menu"]);
+
+        // the failed page doesn't hide that the map is gone everywhere else
+        let drifted = HealthReportSchema::from_pages(vec![
+            page("a", &[("div.menu", 3), ("div#map", 0)]),
+            page("b", &[("div.menu", 2), ("div#map", 0)]),
+            PageHealthSchema::failed("c", "timeout"),
+        ]);
+        assert_eq!(drifted.status, HealthStatus::Drifted);
+        assert_eq!(drifted.drifted_selectors, vec!["div#map"]);
+        assert_eq!(drifted.pages_checked, 3);
+    }
+}
@@ -1,16 +1,135 @@
-use std::future::Future;
+use std::{collections::HashMap, future::Future};
 
 use serde::{Deserialize, Serialize};
-use sqlx::types::Uuid;
+use sqlx::types::{Json, Uuid};
 use sqlx::{PgPool, PgTransaction};
 use utoipa::ToSchema;
 
+use crate::{
+    models::{Entity, status::StatusSchema},
+    regions::CrousRegion,
+};
+
+/// Meal type of the rows carrying the notice of a menu day instead of a food.
+pub const NOTICE_MEAL_TYPE: &str = "notice";
+
 #[derive(Debug, Clone, ToSchema, Serialize, Deserialize)]
 pub struct MealSchema {
     pub meal_type: String,
     pub foodies: Option<String>,
     pub date: Option<String>,
     pub restaurant_id: String,
+    /// Set on the `notice` rows only
+    #[serde(default)]
+    pub status: Option<StatusSchema>,
+}
+
+impl MealSchema {
+    /// Row standing for the notice of a menu day, e.g. `Menu non communiqué`.
+    pub fn notice(restaurant_id: &str, date: &str, status: StatusSchema) -> Self {
+        MealSchema {
+            meal_type: NOTICE_MEAL_TYPE.to_string(),
+            foodies: None,
+            date: Some(date.to_string()),
+            restaurant_id: restaurant_id.to_string(),
+            status: Some(status),
+        }
+    }
+
+    pub fn is_notice(&self) -> bool {
+        self.meal_type == NOTICE_MEAL_TYPE
+    }
+}
+
+/// Meals of a restaurant for one day, grouped by meal type.
+#[derive(Debug, Clone, ToSchema, Serialize, Deserialize)]
+pub struct MenuSchema {
+    pub date: String,
+    pub meals: Vec<MenuSectionSchema>,
+    /// What the page says instead of, or on top of, the menu of the day
+    #[serde(default)]
+    pub status: Option<StatusSchema>,
+}
+
+#[derive(Debug, Clone, ToSchema, Serialize, Deserialize)]
+pub struct MenuSectionSchema {
+    pub meal_type: String,
+    pub foods: Vec<String>,
+}
+
+impl From<Vec<MealSchema>> for MenuSchema {
+    fn from(value: Vec<MealSchema>) -> Self {
+        let date = value
+            .first()
+            .and_then(|m| m.date.clone())
+            .unwrap_or_default();
+
+        let mut status = None;
+        let mut sections: HashMap<String, Vec<String>> = HashMap::new();
+        for meal in value {
+            if meal.is_notice() {
+                status = meal.status;
+                continue;
+            }
+            let food = meal.foodies.unwrap_or_default();
+            sections.entry(meal.meal_type).or_default().push(food);
+        }
+
+        let meals = sections
+            .into_iter()
+            .map(|(meal_type, foods)| MenuSectionSchema { meal_type, foods })
+            .collect();
+
+        MenuSchema {
+            date,
+            meals,
+            status,
+        }
+    }
+}
+
+impl MenuSchema {
+    /// Back to one `MealSchema` per food, as they are uploaded.
+    pub fn into_meals(self, restaurant_id: &str) -> Vec<MealSchema> {
+        let date = self.date;
+        let notice = self
+            .status
+            .map(|status| MealSchema::notice(restaurant_id, &date, status));
+        self.meals
+            .into_iter()
+            .flat_map(|section| {
+                let date = date.clone();
+                section.foods.into_iter().map(move |food| MealSchema {
+                    meal_type: section.meal_type.clone(),
+                    foodies: Some(food),
+                    date: Some(date.clone()),
+                    restaurant_id: restaurant_id.to_string(),
+                    status: None,
+                })
+            })
+            .chain(notice)
+            .collect()
+    }
+}
+
+/// Foods of each `<date>/<meal_type>`, sorted, used to diff two batches.
+/// The notice of a day is keyed `<date>/notice`.
+pub fn menu_index(meals: &[MealSchema]) -> HashMap<String, Vec<String>> {
+    let mut index: HashMap<String, Vec<String>> = HashMap::new();
+    for meal in meals {
+        let key = format!(
+            "{}/{}",
+            meal.date.as_deref().unwrap_or_default(),
+            meal.meal_type
+        );
+        let value = match &meal.status {
+            Some(status) => status.to_string(),
+            None => meal.foodies.clone().unwrap_or_default(),
+        };
+        index.entry(key).or_default().push(value);
+    }
+    index.values_mut().for_each(|foods| foods.sort());
+    index
 }
 
 #[derive(Clone)]
@@ -21,6 +140,7 @@ pub struct Meal {
     pub date: Option<String>,
     pub batch_id: Uuid,
     pub restaurant_id: String,
+    pub status: Option<StatusSchema>,
 }
 
 impl From<Meal> for MealSchema {
@@ -30,6 +150,7 @@ impl From<Meal> for MealSchema {
             foodies: meal.foodies,
             date: meal.date,
             restaurant_id: meal.restaurant_id.to_string(),
+            status: meal.status,
         }
     }
 }
@@ -41,6 +162,7 @@ impl From<&Meal> for MealSchema {
             foodies: meal.foodies.clone(),
             date: meal.date.clone(),
             restaurant_id: meal.restaurant_id.to_string(),
+            status: meal.status.clone(),
         }
     }
 }
@@ -68,6 +190,12 @@ pub trait MealModel {
         restaurant_name: String,
         batch_id: Uuid,
     ) -> impl Future<Output = Result<Vec<Meal>, MealModelError>> + Send;
+    /// Meals of the current batch of each restaurant, in a single query.
+    fn get_current_meals_by_restaurant_ids(
+        &self,
+        restaurant_ids: &[String],
+        region: CrousRegion,
+    ) -> impl Future<Output = Result<Vec<Meal>, MealModelError>> + Send;
 }
 
 impl MealModel for PgPool {
@@ -77,13 +205,14 @@ impl MealModel for PgPool {
         tx: &mut PgTransaction<'_>,
     ) -> Result<(), MealModelError> {
         sqlx::query!(
-            "INSERT INTO meals (meal_id, meal_type, foodies, date, restaurant_id, batch_id) VALUES ($1, $2, $3, $4, $5, $6)",
+            "INSERT INTO meals (meal_id, meal_type, foodies, date, restaurant_id, batch_id, status) VALUES ($1, $2, $3, $4, $5, $6, $7)",
             meal.meal_id,
             meal.meal_type,
             meal.foodies,
             meal.date,
             meal.restaurant_id,
-            meal.batch_id
+            meal.batch_id,
+            meal.status.map(Json) as _
         )
         .execute(&mut **tx)
         .await
@@ -101,7 +230,7 @@ impl MealModel for PgPool {
         Self: Sync,
     {
         let rows = sqlx::query!(
-            "SELECT m.meal_id, m.meal_type, m.foodies, m.date, m.restaurant_id, m.batch_id FROM meals m WHERE m.restaurant_id = $1 AND m.batch_id = $2",
+            "SELECT m.meal_id, m.meal_type, m.foodies, m.date, m.restaurant_id, m.batch_id, m.status as \"status: Json<StatusSchema>\" FROM meals m WHERE m.restaurant_id = $1 AND m.batch_id = $2",
             restaurant_name,
             batch_id
         )
@@ -118,6 +247,44 @@ impl MealModel for PgPool {
                 batch_id: row.batch_id,
                 date: row.date,
                 restaurant_id: row.restaurant_id,
+                status: row.status.map(|status| status.0),
+            })
+            .collect();
+
+        Ok(meals)
+    }
+
+    async fn get_current_meals_by_restaurant_ids(
+        &self,
+        restaurant_ids: &[String],
+        region: CrousRegion,
+    ) -> Result<Vec<Meal>, MealModelError> {
+        let entities: Vec<String> = restaurant_ids
+            .iter()
+            .map(|restaurant_id| Entity::Meals(restaurant_id.clone()).to_string())
+            .collect();
+        let rows = sqlx::query!(
+            "SELECT m.meal_id, m.meal_type, m.foodies, m.date, m.restaurant_id, m.batch_id, m.status as \"status: Json<StatusSchema>\" FROM meals m
+            JOIN (SELECT DISTINCT ON (entity) batch_id FROM scrape_batch WHERE entity = ANY($2) AND region = $3 ORDER BY entity, seq DESC) current ON current.batch_id = m.batch_id
+            WHERE m.restaurant_id = ANY($1)",
+            restaurant_ids,
+            &entities,
+            region.to_string()
+        )
+        .fetch_all(self)
+        .await
+        .map_err(|e| MealModelError::DatabaseError(e.to_string()))?;
+
+        let meals = rows
+            .into_iter()
+            .map(|row| Meal {
+                meal_id: row.meal_id,
+                meal_type: row.meal_type,
+                foodies: row.foodies,
+                batch_id: row.batch_id,
+                date: row.date,
+                restaurant_id: row.restaurant_id,
+                status: row.status.map(|status| status.0),
             })
             .collect();
 
@@ -1,14 +1,20 @@
 use std::str::FromStr;
 
 pub mod admins;
+pub mod audit;
+pub mod dead_letters;
+pub mod health;
 pub mod keywords;
 pub mod meals;
 pub mod restaurants;
 pub mod schools;
 pub mod scrape_batch;
+pub mod status;
+pub mod tokens;
+pub mod webhooks;
 
-#[derive(Clone, serde::Serialize, serde::Deserialize, Debug)]
-#[serde(try_from = "String")]
+#[derive(Clone, serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
 pub enum Entity {
     Restaurants,
     Meals(String),
@@ -46,6 +52,24 @@ impl TryFrom<String> for Entity {
     }
 }
 
+impl From<Entity> for String {
+    fn from(entity: Entity) -> Self {
+        entity.to_string()
+    }
+}
+
+impl Entity {
+    /// Entity name without the restaurant suffix carried by meals batches.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Entity::Restaurants => "restaurants",
+            Entity::Meals(_) => "meals",
+            Entity::Schools => "schools",
+        }
+    }
+}
+
+#[allow(clippy::to_string_trait_impl)]
 impl ToString for Entity {
     fn to_string(&self) -> String {
         match self {
@@ -1,20 +1,115 @@
-use std::future::Future;
+use std::{fmt::Display, future::Future, str::FromStr};
 
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
-use sqlx::{PgPool, PgTransaction, types::Uuid};
+use sqlx::{
+    PgPool, PgTransaction,
+    types::{Json, Uuid},
+};
 use thiserror::Error;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Debug, Clone, ToSchema, Serialize, Deserialize)]
+use crate::{models::status::StatusSchema, regions::CrousRegion};
+
+/// What kind of place a restaurant is, students after a sandwich and those
+/// after a full meal don't go to the same ones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, ToSchema, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VenueType {
+    /// Full meals at student prices
+    RestoU,
+    Cafeteria,
+    Brasserie,
+    FoodTruck,
+    #[default]
+    Unknown,
+}
+
+impl VenueType {
+    /// Type told by a label or a name, e.g. `Cafétéria` or `Brasserie
+    /// Triolet`, `None` when it doesn't tell.
+    pub fn classify(label: &str) -> Option<Self> {
+        let label = label.to_lowercase().replace(['’', '\''], " ");
+        let has = |words: &[&str]| words.iter().any(|word| label.contains(word));
+        if has(&["food truck", "foodtruck", "food-truck"]) {
+            Some(VenueType::FoodTruck)
+        } else if has(&["cafét", "cafet", "café", "kiosque", "snack"]) {
+            Some(VenueType::Cafeteria)
+        } else if has(&["brasserie"]) {
+            Some(VenueType::Brasserie)
+        } else if has(&["resto u", "restaurant"]) {
+            Some(VenueType::RestoU)
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for VenueType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VenueType::RestoU => f.write_str("resto_u"),
+            VenueType::Cafeteria => f.write_str("cafeteria"),
+            VenueType::Brasserie => f.write_str("brasserie"),
+            VenueType::FoodTruck => f.write_str("food_truck"),
+            VenueType::Unknown => f.write_str("unknown"),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum VenueTypeError {
+    #[error("Unknown venue type : {0}")]
+    Unknown(String),
+}
+
+impl FromStr for VenueType {
+    type Err = VenueTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "resto_u" => Ok(VenueType::RestoU),
+            "cafeteria" => Ok(VenueType::Cafeteria),
+            "brasserie" => Ok(VenueType::Brasserie),
+            "food_truck" => Ok(VenueType::FoodTruck),
+            "unknown" => Ok(VenueType::Unknown),
+            _ => Err(VenueTypeError::Unknown(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ToSchema, Serialize, Deserialize)]
 pub struct RestaurantSchema {
     // optional because of put requests
     pub id: String,
     pub name: String,
     pub url: String,
     pub city: Option<String>,
+    #[serde(rename = "type", default)]
+    pub venue_type: VenueType,
     pub coordinates: Option<String>,
     pub opening_hours: Option<String>,
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub phone: Option<String>,
+    /// e.g. `IZLY`, `Carte bancaire`
+    #[serde(default)]
+    pub payment_methods: Vec<String>,
+    #[serde(default)]
+    pub amenities: Vec<String>,
+    /// Whether the restaurant is accessible to people with reduced mobility,
+    /// unknown unless the page says so
+    #[serde(default)]
+    pub accessible: Option<bool>,
+    /// How to get there
+    #[serde(default)]
+    pub access: Option<String>,
+    #[serde(default)]
+    pub photos: Vec<String>,
+    /// Open, or closed and why, as the banners of the page tell
+    #[serde(default)]
+    pub status: StatusSchema,
 }
 
 impl From<Restaurant> for RestaurantSchema {
@@ -24,8 +119,17 @@ impl From<Restaurant> for RestaurantSchema {
             name: restaurant.name,
             url: restaurant.url,
             city: restaurant.city,
+            venue_type: restaurant.venue_type,
             coordinates: restaurant.coordinates,
             opening_hours: restaurant.opening_hours,
+            address: restaurant.address,
+            phone: restaurant.phone,
+            payment_methods: restaurant.payment_methods,
+            amenities: restaurant.amenities,
+            accessible: restaurant.accessible,
+            access: restaurant.access,
+            photos: restaurant.photos,
+            status: restaurant.status,
         }
     }
 }
@@ -37,20 +141,78 @@ impl From<&Restaurant> for RestaurantSchema {
             name: restaurant.name.clone(),
             url: restaurant.url.clone(),
             city: restaurant.city.clone(),
+            venue_type: restaurant.venue_type,
             coordinates: restaurant.coordinates.clone(),
             opening_hours: restaurant.opening_hours.clone(),
+            address: restaurant.address.clone(),
+            phone: restaurant.phone.clone(),
+            payment_methods: restaurant.payment_methods.clone(),
+            amenities: restaurant.amenities.clone(),
+            accessible: restaurant.accessible,
+            access: restaurant.access.clone(),
+            photos: restaurant.photos.clone(),
+            status: restaurant.status.clone(),
         }
     }
 }
 
+impl RestaurantSchema {
+    /// The restaurant as served on `day`, see `StatusSchema::on`.
+    pub fn on(mut self, day: NaiveDate) -> Self {
+        self.status = self.status.on(day);
+        self
+    }
+}
+
+/// Filters of `GET /{region}/restaurants`, all optional.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RestaurantQuerySchema {
+    /// Case-insensitive search on the name and the city
+    pub q: Option<String>,
+    /// Only the restaurants of this type
+    #[serde(rename = "type")]
+    #[param(rename = "type")]
+    pub venue_type: Option<VenueType>,
+}
+
+impl RestaurantQuerySchema {
+    pub fn matches(&self, restaurant: &RestaurantSchema) -> bool {
+        if self
+            .venue_type
+            .is_some_and(|venue_type| venue_type != restaurant.venue_type)
+        {
+            return false;
+        }
+        let Some(q) = self.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) else {
+            return true;
+        };
+        let q = q.to_lowercase();
+        restaurant.name.to_lowercase().contains(&q)
+            || restaurant
+                .city
+                .as_deref()
+                .is_some_and(|city| city.to_lowercase().contains(&q))
+    }
+}
+
 #[derive(Clone)]
 pub struct Restaurant {
     pub restaurant_id: String,
     pub name: String,
     pub url: String,
     pub city: Option<String>,
+    pub venue_type: VenueType,
     pub coordinates: Option<String>,
     pub opening_hours: Option<String>,
+    pub address: Option<String>,
+    pub phone: Option<String>,
+    pub payment_methods: Vec<String>,
+    pub amenities: Vec<String>,
+    pub accessible: Option<bool>,
+    pub access: Option<String>,
+    pub photos: Vec<String>,
+    pub status: StatusSchema,
     pub created_at: Option<NaiveDateTime>,
     pub updated_at: Option<NaiveDateTime>,
     pub batch_id: Uuid,
@@ -72,9 +234,11 @@ pub trait RestaurantModel {
         restaurant: Restaurant,
         tx: &mut PgTransaction,
     ) -> impl Future<Output = Result<(), RestaurantModelError>> + Send;
+    /// The restaurant if it was last scraped in `region`.
     fn get_restaurant_by_id(
         &self,
         id: String,
+        region: CrousRegion,
     ) -> impl Future<Output = Result<Restaurant, RestaurantModelError>> + Send;
     fn get_all_restaurants_batch(
         &self,
@@ -89,13 +253,22 @@ impl RestaurantModel for PgPool {
         tx: &mut PgTransaction<'_>,
     ) -> Result<(), RestaurantModelError> {
         sqlx::query!(
-            "INSERT INTO restaurants (restaurant_id, name, url, city, coordinates, opening_hours, batch_id) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            "INSERT INTO restaurants (restaurant_id, name, url, city, venue_type, coordinates, opening_hours, address, phone, payment_methods, amenities, accessible, access, photos, status, batch_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)",
             restaurant.restaurant_id,
             restaurant.name,
             restaurant.url,
             restaurant.city,
+            restaurant.venue_type.to_string(),
             restaurant.coordinates,
             restaurant.opening_hours,
+            restaurant.address,
+            restaurant.phone,
+            &restaurant.payment_methods,
+            &restaurant.amenities,
+            restaurant.accessible,
+            restaurant.access,
+            &restaurant.photos,
+            Json(&restaurant.status) as _,
             restaurant.batch_id
         )
         .execute(&mut **tx)
@@ -105,10 +278,17 @@ impl RestaurantModel for PgPool {
         Ok(())
     }
 
-    async fn get_restaurant_by_id(&self, id: String) -> Result<Restaurant, RestaurantModelError> {
+    async fn get_restaurant_by_id(
+        &self,
+        id: String,
+        region: CrousRegion,
+    ) -> Result<Restaurant, RestaurantModelError> {
         let row = sqlx::query!(
-            "SELECT restaurant_id, name, url, city, coordinates, opening_hours, created_at, updated_at, batch_id FROM restaurants WHERE restaurant_id = $1",
-            id
+            "SELECT r.restaurant_id, r.name, r.url, r.city, r.venue_type, r.coordinates, r.opening_hours, r.address, r.phone, r.payment_methods, r.amenities, r.accessible, r.access, r.photos, r.status as \"status: Json<StatusSchema>\", r.created_at, r.updated_at, r.batch_id FROM restaurants r
+            JOIN scrape_batch b ON b.batch_id = r.batch_id
+            WHERE r.restaurant_id = $1 AND b.region = $2",
+            id,
+            region.to_string()
         )
         .fetch_optional(self)
         .await
@@ -120,8 +300,17 @@ impl RestaurantModel for PgPool {
             name: row.name,
             url: row.url,
             city: row.city,
+            venue_type: row.venue_type.parse().unwrap_or_default(),
             coordinates: row.coordinates,
             opening_hours: row.opening_hours,
+            address: row.address,
+            phone: row.phone,
+            payment_methods: row.payment_methods,
+            amenities: row.amenities,
+            accessible: row.accessible,
+            access: row.access,
+            photos: row.photos,
+            status: row.status.map(|status| status.0).unwrap_or_default(),
             created_at: row.created_at,
             updated_at: row.updated_at,
             batch_id: row.batch_id,
@@ -133,7 +322,7 @@ impl RestaurantModel for PgPool {
         batch_id: Uuid,
     ) -> Result<Vec<Restaurant>, RestaurantModelError> {
         let rows = sqlx::query!(
-            "SELECT restaurant_id, name, url, city, coordinates, opening_hours, created_at, updated_at, batch_id FROM restaurants WHERE batch_id = $1",
+            "SELECT restaurant_id, name, url, city, venue_type, coordinates, opening_hours, address, phone, payment_methods, amenities, accessible, access, photos, status as \"status: Json<StatusSchema>\", created_at, updated_at, batch_id FROM restaurants WHERE batch_id = $1",
             batch_id
         )
         .fetch_all(self)
@@ -147,8 +336,17 @@ impl RestaurantModel for PgPool {
                 name: row.name,
                 url: row.url,
                 city: row.city,
+                venue_type: row.venue_type.parse().unwrap_or_default(),
                 coordinates: row.coordinates,
                 opening_hours: row.opening_hours,
+                address: row.address,
+                phone: row.phone,
+                payment_methods: row.payment_methods,
+                amenities: row.amenities,
+                accessible: row.accessible,
+                access: row.access,
+                photos: row.photos,
+                status: row.status.map(|status| status.0).unwrap_or_default(),
                 created_at: row.created_at,
                 updated_at: row.updated_at,
                 batch_id: row.batch_id,
@@ -158,3 +356,71 @@ impl RestaurantModel for PgPool {
         Ok(restaurants)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_matches_name_and_city() {
+        let restaurant = RestaurantSchema {
+            id: "resto-u-triolet".to_string(),
+            name: "Resto U' Triolet".to_string(),
+            url: "https://www.crous-montpellier.fr/restaurant/resto-u-triolet/".to_string(),
+            city: Some("Montpellier".to_string()),
+            coordinates: None,
+            opening_hours: None,
+            ..Default::default()
+        };
+        let query = |q: &str| RestaurantQuerySchema {
+            q: Some(q.to_string()),
+            ..Default::default()
+        };
+        assert!(RestaurantQuerySchema::default().matches(&restaurant));
+        assert!(query("triolet").matches(&restaurant));
+        assert!(query(" MONTPELLIER ").matches(&restaurant));
+        assert!(!query("richter").matches(&restaurant));
+    }
+
+    #[test]
+    fn test_query_filters_on_type() {
+        let restaurant = RestaurantSchema {
+            name: "Cafét' Richter".to_string(),
+            venue_type: VenueType::Cafeteria,
+            ..Default::default()
+        };
+        let query = |venue_type| RestaurantQuerySchema {
+            venue_type: Some(venue_type),
+            ..Default::default()
+        };
+        assert!(query(VenueType::Cafeteria).matches(&restaurant));
+        assert!(!query(VenueType::RestoU).matches(&restaurant));
+    }
+
+    #[test]
+    fn test_classify_venue_type() {
+        assert_eq!(VenueType::classify("Cafétéria"), Some(VenueType::Cafeteria));
+        assert_eq!(
+            VenueType::classify("Cafét' Richter"),
+            Some(VenueType::Cafeteria)
+        );
+        assert_eq!(
+            VenueType::classify("Brasserie Triolet"),
+            Some(VenueType::Brasserie)
+        );
+        assert_eq!(
+            VenueType::classify("Sévenans • Resto U’"),
+            Some(VenueType::RestoU)
+        );
+        assert_eq!(
+            VenueType::classify("Food truck"),
+            Some(VenueType::FoodTruck)
+        );
+        assert_eq!(VenueType::classify("Restaurant"), Some(VenueType::RestoU));
+        assert_eq!(VenueType::classify("Le Bullier"), None);
+        assert_eq!(
+            "food_truck".parse::<VenueType>().unwrap(),
+            VenueType::FoodTruck
+        );
+    }
+}
@@ -1,19 +1,149 @@
-use std::str::FromStr;
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
 
 use chrono::NaiveDateTime;
 use serde::Deserialize;
-use sqlx::{PgPool, PgTransaction, types::Uuid};
+use sqlx::{
+    PgPool, PgTransaction,
+    types::{Json, Uuid},
+};
+use utoipa::{IntoParams, ToSchema};
 
-use crate::{models::Entity, regions::CrousRegion};
+use crate::{
+    models::{Entity, EntityError, status::StatusSchema},
+    regions::CrousRegion,
+};
 
-#[derive(Clone, Deserialize, serde::Serialize, Debug)]
+#[derive(Clone, Deserialize, serde::Serialize, Debug, ToSchema)]
 pub struct ScrapeBatch {
+    #[schema(value_type = String)]
     pub batch_id: Uuid,
+    #[schema(value_type = String, example = "meals-resto-u-triolet")]
     pub entity: Entity,
+    #[schema(value_type = String)]
     pub author: Uuid,
     pub region: String,
+    #[schema(value_type = Option<String>)]
     pub scraped_at: Option<NaiveDateTime>,
     pub checksum: String,
+    /// Position in the batch log, assigned by the database on insert
+    #[serde(default)]
+    pub seq: Option<i64>,
+    /// What changed compared to the previous batch of the same entity
+    #[serde(default)]
+    pub summary: Option<BatchSummary>,
+}
+
+/// Keys of the items added, removed or modified by a batch. Restaurants are
+/// keyed by id, meals by `<date>/<meal_type>`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, serde::Serialize, ToSchema)]
+pub struct BatchSummary {
+    pub total: usize,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    /// Items of the batch that aren't open, restaurants by id and menu days
+    /// by date
+    #[serde(default)]
+    pub notices: BTreeMap<String, StatusSchema>,
+}
+
+impl BatchSummary {
+    pub fn diff<T: PartialEq>(previous: &HashMap<String, T>, current: &HashMap<String, T>) -> Self {
+        let mut summary = BatchSummary {
+            total: current.len(),
+            ..Default::default()
+        };
+        for (key, item) in current {
+            match previous.get(key) {
+                None => summary.added.push(key.clone()),
+                Some(previous_item) if previous_item != item => summary.changed.push(key.clone()),
+                Some(_) => {}
+            }
+        }
+        summary.removed = previous
+            .keys()
+            .filter(|key| !current.contains_key(*key))
+            .cloned()
+            .collect();
+        summary.added.sort();
+        summary.removed.sort();
+        summary.changed.sort();
+        summary
+    }
+
+    /// Keeps the statuses that aren't open, to tell subscribers about them.
+    pub fn with_notices<'a>(
+        mut self,
+        statuses: impl IntoIterator<Item = (String, &'a StatusSchema)>,
+    ) -> Self {
+        self.notices = statuses
+            .into_iter()
+            .filter(|(_, status)| !status.is_open())
+            .map(|(key, status)| (key, status.clone()))
+            .collect();
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Filters of `GET /events`, matched against the batch region and entity.
+#[derive(Clone, Debug, Default, Deserialize, serde::Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EventFilterSchema {
+    /// Region name, case-insensitive
+    pub region: Option<String>,
+    /// `restaurants`, `meals` or `schools`
+    pub entity: Option<String>,
+    /// Restaurant id, only meals batches of this restaurant match
+    pub restaurant: Option<String>,
+}
+
+impl EventFilterSchema {
+    pub fn validate(&self) -> Result<(), EntityError> {
+        match self.entity.as_deref() {
+            None | Some("restaurants" | "meals" | "schools") => Ok(()),
+            Some(entity) => Err(EntityError::Unknown(entity.to_string())),
+        }
+    }
+
+    pub fn matches(&self, batch: &ScrapeBatch) -> bool {
+        let region_matches = self
+            .region
+            .as_deref()
+            .is_none_or(|region| region.eq_ignore_ascii_case(&batch.region));
+        let entity_matches = self
+            .entity
+            .as_deref()
+            .is_none_or(|entity| entity == batch.entity.kind());
+        let restaurant_matches = self.restaurant.as_deref().is_none_or(|restaurant| {
+            matches!(&batch.entity, Entity::Meals(restaurant_id) if restaurant_id == restaurant)
+        });
+        region_matches && entity_matches && restaurant_matches
+    }
+}
+
+pub const DEFAULT_BATCHES_LIMIT: i64 = 20;
+
+/// Payload of the `scraping_channel` notifications, the batch itself is
+/// loaded afterwards to stay under the NOTIFY size limit.
+#[derive(Clone, Debug, Deserialize, serde::Serialize)]
+pub struct BatchNotification {
+    pub batch_id: Uuid,
+    pub seq: Option<i64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, serde::Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BatchQuerySchema {
+    /// Full entity name, e.g. `restaurants` or `meals-<restaurant_id>`
+    pub entity: Option<String>,
+    pub limit: Option<i64>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -39,6 +169,27 @@ pub trait ScrapedBatchModel {
         entity: &Entity,
         region: CrousRegion,
     ) -> impl Future<Output = Result<Option<ScrapeBatch>, ScrapedBatchModelError>> + Send;
+
+    fn get_batch(
+        &self,
+        batch_id: Uuid,
+    ) -> impl Future<Output = Result<Option<ScrapeBatch>, ScrapedBatchModelError>> + Send;
+
+    fn get_batches(
+        &self,
+        region: CrousRegion,
+        query: BatchQuerySchema,
+    ) -> impl Future<Output = Result<Vec<ScrapeBatch>, ScrapedBatchModelError>> + Send;
+
+    /// Batches of every region committed after `seq`, oldest first.
+    fn get_batches_since(
+        &self,
+        seq: i64,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<ScrapeBatch>, ScrapedBatchModelError>> + Send;
+
+    /// Seq of the last committed batch, 0 before the first one.
+    fn last_seq(&self) -> impl Future<Output = Result<i64, ScrapedBatchModelError>> + Send;
 }
 impl ScrapedBatchModel for PgPool {
     async fn create_batch(
@@ -49,13 +200,22 @@ impl ScrapedBatchModel for PgPool {
             .begin()
             .await
             .map_err(|e| ScrapedBatchModelError::TransactionError(e.to_string()))?;
+        // the counter row stays locked until the batch commits, so a batch
+        // never gets a lower seq than one already committed
+        let seq =
+            sqlx::query_scalar!("UPDATE scrape_batch_counter SET seq = seq + 1 RETURNING seq")
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|e| ScrapedBatchModelError::DatabaseError(e.to_string()))?;
         sqlx::query!(
-            "INSERT INTO scrape_batch(batch_id, entity, author, region, checksum) VALUES ($1, $2, $3, $4, $5)",
+            "INSERT INTO scrape_batch(batch_id, entity, author, region, checksum, summary, seq) VALUES ($1, $2, $3, $4, $5, $6, $7)",
             batch.batch_id,
             batch.entity.to_string(),
             batch.author,
             batch.region,
-            batch.checksum
+            batch.checksum,
+            batch.summary.map(Json) as _,
+            seq
         )
         .execute(&mut *tx)
         .await
@@ -70,7 +230,7 @@ impl ScrapedBatchModel for PgPool {
         region: CrousRegion,
     ) -> Result<Option<ScrapeBatch>, ScrapedBatchModelError> {
         let row = sqlx::query!(
-            "SELECT batch_id, entity, author, region, checksum, scraped_at FROM scrape_batch WHERE entity = $1 AND region = $2 ORDER BY scraped_at LIMIT 1",
+            "SELECT batch_id, entity, author, region, checksum, scraped_at, seq, summary as \"summary: Json<BatchSummary>\" FROM scrape_batch WHERE entity = $1 AND region = $2 ORDER BY seq DESC LIMIT 1",
             entity.to_string(),
             region.to_string()
         )
@@ -88,6 +248,187 @@ impl ScrapedBatchModel for PgPool {
             region: row.region,
             scraped_at: row.scraped_at,
             checksum: row.checksum,
+            seq: Some(row.seq),
+            summary: row.summary.map(|summary| summary.0),
         }))
     }
+
+    async fn get_batch(
+        &self,
+        batch_id: Uuid,
+    ) -> Result<Option<ScrapeBatch>, ScrapedBatchModelError> {
+        let row = sqlx::query!(
+            "SELECT batch_id, entity, author, region, checksum, scraped_at, seq, summary as \"summary: Json<BatchSummary>\" FROM scrape_batch WHERE batch_id = $1",
+            batch_id
+        )
+        .fetch_optional(self)
+        .await
+        .map_err(|e| ScrapedBatchModelError::DatabaseError(e.to_string()))?;
+
+        let Some(row) = row else { return Ok(None) };
+
+        Ok(Some(ScrapeBatch {
+            batch_id: row.batch_id,
+            entity: Entity::from_str(&row.entity)
+                .map_err(|_| ScrapedBatchModelError::NotAnEntity)?,
+            author: row.author,
+            region: row.region,
+            scraped_at: row.scraped_at,
+            checksum: row.checksum,
+            seq: Some(row.seq),
+            summary: row.summary.map(|summary| summary.0),
+        }))
+    }
+
+    async fn get_batches(
+        &self,
+        region: CrousRegion,
+        query: BatchQuerySchema,
+    ) -> Result<Vec<ScrapeBatch>, ScrapedBatchModelError> {
+        let rows = sqlx::query!(
+            "SELECT batch_id, entity, author, region, checksum, scraped_at, seq, summary as \"summary: Json<BatchSummary>\" FROM scrape_batch
+            WHERE region = $1 AND ($2::TEXT IS NULL OR entity = $2)
+            ORDER BY scraped_at DESC LIMIT $3",
+            region.to_string(),
+            query.entity,
+            query.limit.unwrap_or(DEFAULT_BATCHES_LIMIT)
+        )
+        .fetch_all(self)
+        .await
+        .map_err(|e| ScrapedBatchModelError::DatabaseError(e.to_string()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(ScrapeBatch {
+                    batch_id: row.batch_id,
+                    entity: Entity::from_str(&row.entity)
+                        .map_err(|_| ScrapedBatchModelError::NotAnEntity)?,
+                    author: row.author,
+                    region: row.region,
+                    scraped_at: row.scraped_at,
+                    checksum: row.checksum,
+                    seq: Some(row.seq),
+                    summary: row.summary.map(|summary| summary.0),
+                })
+            })
+            .collect()
+    }
+
+    async fn get_batches_since(
+        &self,
+        seq: i64,
+        limit: i64,
+    ) -> Result<Vec<ScrapeBatch>, ScrapedBatchModelError> {
+        let rows = sqlx::query!(
+            "SELECT batch_id, entity, author, region, checksum, scraped_at, seq, summary as \"summary: Json<BatchSummary>\" FROM scrape_batch
+            WHERE seq > $1 ORDER BY seq LIMIT $2",
+            seq,
+            limit
+        )
+        .fetch_all(self)
+        .await
+        .map_err(|e| ScrapedBatchModelError::DatabaseError(e.to_string()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(ScrapeBatch {
+                    batch_id: row.batch_id,
+                    entity: Entity::from_str(&row.entity)
+                        .map_err(|_| ScrapedBatchModelError::NotAnEntity)?,
+                    author: row.author,
+                    region: row.region,
+                    scraped_at: row.scraped_at,
+                    checksum: row.checksum,
+                    seq: Some(row.seq),
+                    summary: row.summary.map(|summary| summary.0),
+                })
+            })
+            .collect()
+    }
+
+    async fn last_seq(&self) -> Result<i64, ScrapedBatchModelError> {
+        let seq = sqlx::query_scalar!("SELECT seq FROM scrape_batch_counter")
+            .fetch_optional(self)
+            .await
+            .map_err(|e| ScrapedBatchModelError::DatabaseError(e.to_string()))?;
+
+        Ok(seq.unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_json_round_trip() {
+        let batch = ScrapeBatch {
+            batch_id: Uuid::nil(),
+            entity: Entity::Meals("resto-u-triolet".to_string()),
+            author: Uuid::nil(),
+            region: "Montpellier".to_string(),
+            scraped_at: None,
+            checksum: "abc".to_string(),
+            seq: Some(42),
+            summary: None,
+        };
+        let json = serde_json::to_value(&batch).unwrap();
+        assert_eq!(json["entity"], "meals-resto-u-triolet");
+        let decoded: ScrapeBatch = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.entity, batch.entity);
+    }
+
+    #[test]
+    fn test_summary_diff() {
+        let previous = HashMap::from([
+            ("a".to_string(), 1),
+            ("b".to_string(), 2),
+            ("c".to_string(), 3),
+        ]);
+        let current = HashMap::from([
+            ("a".to_string(), 1),
+            ("b".to_string(), 20),
+            ("d".to_string(), 4),
+        ]);
+        let summary = BatchSummary::diff(&previous, &current);
+        assert_eq!(summary.total, 3);
+        assert_eq!(summary.added, vec!["d"]);
+        assert_eq!(summary.removed, vec!["c"]);
+        assert_eq!(summary.changed, vec!["b"]);
+        assert!(BatchSummary::diff(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn test_event_filter() {
+        let batch = ScrapeBatch {
+            batch_id: Uuid::nil(),
+            entity: Entity::Meals("brasserie-triolet".to_string()),
+            author: Uuid::nil(),
+            region: "Montpellier".to_string(),
+            scraped_at: None,
+            checksum: "abc".to_string(),
+            seq: None,
+            summary: None,
+        };
+        let filter = |region: Option<&str>, entity: Option<&str>, restaurant: Option<&str>| {
+            EventFilterSchema {
+                region: region.map(str::to_string),
+                entity: entity.map(str::to_string),
+                restaurant: restaurant.map(str::to_string),
+            }
+        };
+        assert!(filter(None, None, None).matches(&batch));
+        assert!(
+            filter(
+                Some("montpellier"),
+                Some("meals"),
+                Some("brasserie-triolet")
+            )
+            .matches(&batch)
+        );
+        assert!(!filter(Some("paris"), None, None).matches(&batch));
+        assert!(!filter(None, Some("restaurants"), None).matches(&batch));
+        assert!(!filter(None, None, Some("resto-u-richter")).matches(&batch));
+        assert!(filter(None, Some("teapot"), None).validate().is_err());
+    }
 }
@@ -0,0 +1,307 @@
+use std::fmt::Display;
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::validation::MONTHS;
+
+/// Words of a notice telling the restaurant is closed, checked first as a
+/// reopening notice also says `ouvert`.
+const CLOSED_MARKERS: &[&str] = &[
+    "fermé",
+    "fermeture",
+    "réouverture",
+    "pas de service",
+    "closed",
+];
+/// Words of a notice telling there's nothing to show without saying why.
+const UNKNOWN_MARKERS: &[&str] = &[
+    "non communiqué",
+    "pas de menu",
+    "menu indisponible",
+    "non disponible",
+];
+const OPEN_MARKERS: &[&str] = &["ouvert"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OpeningState {
+    Open,
+    Closed,
+    /// Nothing on the page says whether it's open, or it says it doesn't know
+    #[default]
+    Unknown,
+}
+
+impl OpeningState {
+    /// Which notice wins when a page has several.
+    fn priority(self) -> u8 {
+        match self {
+            OpeningState::Closed => 2,
+            OpeningState::Unknown => 1,
+            OpeningState::Open => 0,
+        }
+    }
+}
+
+impl Display for OpeningState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OpeningState::Open => f.write_str("open"),
+            OpeningState::Closed => f.write_str("closed"),
+            OpeningState::Unknown => f.write_str("unknown"),
+        }
+    }
+}
+
+/// Whether a restaurant or a menu day is open, read from the banners and
+/// notices of its page such as `Fermé pour travaux du 2 au 13 mars 2026`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct StatusSchema {
+    pub state: OpeningState,
+    /// The notice as written on the page
+    pub reason: Option<String>,
+    /// First day the notice applies to, when it says
+    #[schema(value_type = Option<String>)]
+    pub from: Option<NaiveDate>,
+    /// Last day the notice applies to, when it says
+    #[schema(value_type = Option<String>)]
+    pub until: Option<NaiveDate>,
+}
+
+impl Display for StatusSchema {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.state)?;
+        if let Some(reason) = &self.reason {
+            write!(f, " : {}", reason)?;
+        }
+        match (self.from, self.until) {
+            (Some(from), Some(until)) if from == until => write!(f, " ({})", from),
+            (Some(from), Some(until)) => write!(f, " ({} - {})", from, until),
+            (Some(from), None) => write!(f, " (from {})", from),
+            (None, Some(until)) => write!(f, " (until {})", until),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl StatusSchema {
+    pub fn open() -> Self {
+        Self {
+            state: OpeningState::Open,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.state == OpeningState::Open
+    }
+
+    /// Whether the notice is dated and its dates include `day`.
+    pub fn covers(&self, day: NaiveDate) -> bool {
+        (self.from.is_some() || self.until.is_some())
+            && self.from.is_none_or(|from| from <= day)
+            && self.until.is_none_or(|until| day <= until)
+    }
+
+    /// The status on `day`, a closure whose last day passed reading as open
+    /// again while keeping its notice.
+    pub fn on(&self, day: NaiveDate) -> Self {
+        match self.until {
+            Some(until) if self.state == OpeningState::Closed && until < day => Self {
+                state: OpeningState::Open,
+                ..self.clone()
+            },
+            _ => self.clone(),
+        }
+    }
+
+    /// Reads a notice, `None` when the text isn't one.
+    pub fn from_notice(notice: &str) -> Option<Self> {
+        let reason = notice.split_whitespace().collect::<Vec<_>>().join(" ");
+        let text = reason.to_lowercase();
+        let has = |markers: &[&str]| markers.iter().any(|marker| text.contains(marker));
+        let state = if has(CLOSED_MARKERS) {
+            OpeningState::Closed
+        } else if has(UNKNOWN_MARKERS) {
+            OpeningState::Unknown
+        } else if has(OPEN_MARKERS) {
+            OpeningState::Open
+        } else {
+            return None;
+        };
+
+        let dates = notice_dates(&text);
+        let (from, until) = match dates.as_slice() {
+            [] => (None, None),
+            [date] if text.contains("réouverture") => (None, date.pred_opt()),
+            [date] if text.contains("jusqu") => (None, Some(*date)),
+            [date]
+                if ["à partir", "à compter", "dès"]
+                    .iter()
+                    .any(|w| text.contains(w)) =>
+            {
+                (Some(*date), None)
+            }
+            [date] => (Some(*date), Some(*date)),
+            [first, .., last] => (Some(*first), Some(*last)),
+        };
+
+        Some(Self {
+            state,
+            reason: Some(reason),
+            from,
+            until,
+        })
+    }
+
+    /// The most telling of several notices, a closure over anything else.
+    pub fn detect(notices: &[impl AsRef<str>]) -> Option<Self> {
+        notices
+            .iter()
+            .filter_map(|notice| Self::from_notice(notice.as_ref()))
+            .fold(None, |best: Option<Self>, status| match best {
+                Some(best) if best.state.priority() >= status.state.priority() => Some(best),
+                _ => Some(status),
+            })
+    }
+}
+
+fn parse_day(word: &str) -> Option<u32> {
+    word.trim_end_matches("er")
+        .parse()
+        .ok()
+        .filter(|day| (1..=31).contains(day))
+}
+
+/// Dates of a lowercased notice in order, `2 mars 2026` or `02/03/2026`. A
+/// missing year is the one of the next date, and a day followed by `au`
+/// borrows the month of the next date, as in `du 2 au 13 mars 2026`.
+fn notice_dates(text: &str) -> Vec<NaiveDate> {
+    let words: Vec<&str> = text
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric() && c != '/'))
+        .collect();
+
+    // (day, month, year) as written
+    let mut mentions: Vec<(u32, u32, Option<i32>)> = Vec::new();
+    let mut bare_days = Vec::new();
+    for (index, word) in words.iter().enumerate() {
+        if let Ok(date) = NaiveDate::parse_from_str(word, "%d/%m/%Y") {
+            mentions.push((date.day(), date.month(), Some(date.year())));
+            continue;
+        }
+        let Some(month) = MONTHS.iter().position(|month| month == word) else {
+            if words.get(index + 1) == Some(&"au")
+                && let Some(day) = parse_day(word)
+            {
+                bare_days.push(day);
+            }
+            continue;
+        };
+        let Some(day) = index
+            .checked_sub(1)
+            .and_then(|previous| parse_day(words[previous]))
+        else {
+            continue;
+        };
+        let month = month as u32 + 1;
+        let year = words
+            .get(index + 1)
+            .and_then(|year| year.parse().ok())
+            .filter(|year| *year > 1900);
+        mentions.extend(bare_days.drain(..).map(|day| (day, month, year)));
+        mentions.push((day, month, year));
+    }
+
+    let mut dates = Vec::new();
+    let mut next: Option<NaiveDate> = None;
+    for (day, month, year) in mentions.into_iter().rev() {
+        let date = match year {
+            Some(year) => NaiveDate::from_ymd_opt(year, month, day),
+            None => next.and_then(|next| {
+                NaiveDate::from_ymd_opt(next.year(), month, day)
+                    .filter(|date| *date <= next)
+                    .or_else(|| NaiveDate::from_ymd_opt(next.year() - 1, month, day))
+            }),
+        };
+        if let Some(date) = date {
+            next = Some(date);
+            dates.push(date);
+        }
+    }
+    dates.reverse();
+    dates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(year, month, day)
+    }
+
+    #[test]
+    fn test_closure_notices() {
+        let works = StatusSchema::from_notice("Fermé pour travaux du 2 au 13 mars 2026").unwrap();
+        assert_eq!(works.state, OpeningState::Closed);
+        assert_eq!(
+            (works.from, works.until),
+            (date(2026, 3, 2), date(2026, 3, 13))
+        );
+
+        let holidays = StatusSchema::from_notice(
+            "Restaurant fermé pendant les vacances, du 20 décembre au 4 janvier 2027",
+        )
+        .unwrap();
+        assert_eq!(
+            (holidays.from, holidays.until),
+            (date(2026, 12, 20), date(2027, 1, 4))
+        );
+
+        let reopening = StatusSchema::from_notice("Réouverture le lundi 1er juin 2026").unwrap();
+        assert_eq!(reopening.state, OpeningState::Closed);
+        assert_eq!((reopening.from, reopening.until), (None, date(2026, 5, 31)));
+
+        let unknown = StatusSchema::from_notice(" Menu non  communiqué ").unwrap();
+        assert_eq!(unknown.state, OpeningState::Unknown);
+        assert_eq!(unknown.reason.as_deref(), Some("Menu non communiqué"));
+        assert_eq!((unknown.from, unknown.until), (None, None));
+
+        assert_eq!(StatusSchema::from_notice("Frites maison"), None);
+    }
+
+    #[test]
+    fn test_closure_ends_after_its_last_day() {
+        let reopening = StatusSchema::from_notice("Réouverture le 5 mars 2026").unwrap();
+        let day = |day| NaiveDate::from_ymd_opt(2026, 3, day).unwrap();
+        assert!(reopening.covers(day(4)));
+        assert_eq!(reopening.on(day(4)).state, OpeningState::Closed);
+        assert!(!reopening.covers(day(5)));
+        let reopened = reopening.on(day(5));
+        assert!(reopened.is_open());
+        assert_eq!(reopened.reason, reopening.reason);
+
+        let undated = StatusSchema::from_notice("Fermé pour travaux").unwrap();
+        assert!(!undated.covers(day(5)));
+        assert_eq!(undated.on(day(5)).state, OpeningState::Closed);
+    }
+
+    #[test]
+    fn test_detect_prefers_closures() {
+        let status = StatusSchema::detect(&[
+            "Ouvert pendant les vacances",
+            "Menu non communiqué",
+            "Fermé le 14/07/2026",
+        ])
+        .unwrap();
+        assert_eq!(status.state, OpeningState::Closed);
+        assert_eq!(
+            (status.from, status.until),
+            (date(2026, 7, 14), date(2026, 7, 14))
+        );
+        assert_eq!(StatusSchema::detect(&["Salade"]), None);
+    }
+}
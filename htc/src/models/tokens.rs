@@ -0,0 +1,229 @@
+use std::future::Future;
+
+use base64::prelude::*;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{PgPool, types::Uuid};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use crate::models::scrape_batch::ScrapeBatch;
+
+/// Entity kinds a subscriber token can be scoped to.
+pub const TOKEN_ENTITIES: [&str; 3] = ["restaurants", "meals", "schools"];
+
+#[derive(Debug, Clone, ToSchema, Serialize, Deserialize)]
+pub struct TokenRequestSchema {
+    pub name: String,
+    #[serde(default)]
+    pub regions: Vec<String>,
+    #[serde(default)]
+    pub entities: Vec<String>,
+    #[schema(value_type = Option<String>)]
+    pub expires_at: Option<NaiveDateTime>,
+    /// Unix timestamp the request was signed at, refused once older than
+    /// `MAX_REQUEST_AGE`
+    pub issued_at: i64,
+}
+
+#[derive(Debug, Clone, ToSchema, Serialize, Deserialize)]
+pub struct TokenRevocationSchema {
+    #[schema(value_type = String)]
+    pub token_id: Uuid,
+}
+
+#[derive(Debug, Clone, ToSchema, Serialize, Deserialize)]
+pub struct IssuedTokenSchema {
+    #[schema(value_type = String)]
+    pub token_id: Uuid,
+    pub name: String,
+    // only returned once, the server keeps the hash
+    pub token: String,
+    pub regions: Vec<String>,
+    pub entities: Vec<String>,
+    #[schema(value_type = Option<String>)]
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SubscriberToken {
+    pub token_id: Uuid,
+    pub name: String,
+    pub token_hash: String,
+    pub regions: Vec<String>,
+    pub entities: Vec<String>,
+    pub author: Uuid,
+    pub created_at: Option<NaiveDateTime>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+impl SubscriberToken {
+    pub fn scope(&self) -> TokenScope {
+        TokenScope {
+            regions: self.regions.clone(),
+            entities: self.entities.clone(),
+        }
+    }
+}
+
+/// What a subscriber is allowed to receive. An empty list doesn't restrict.
+#[derive(Clone, Debug, Default)]
+pub struct TokenScope {
+    pub regions: Vec<String>,
+    pub entities: Vec<String>,
+}
+
+impl TokenScope {
+    pub fn allows(&self, batch: &ScrapeBatch) -> bool {
+        let region_allowed = self.regions.is_empty() || self.regions.contains(&batch.region);
+        let entity_allowed = self.entities.is_empty()
+            || self
+                .entities
+                .iter()
+                .any(|entity| entity == batch.entity.kind());
+        region_allowed && entity_allowed
+    }
+}
+
+pub fn hash_token(token: &str) -> String {
+    BASE64_STANDARD.encode(Sha256::digest(token))
+}
+
+#[derive(Error, Debug)]
+pub enum TokenModelError {
+    #[error("Token not found")]
+    NotFound,
+    #[error("Token expired")]
+    Expired,
+    #[error("Token revoked")]
+    Revoked,
+    #[error("Invalid token scope : {0}")]
+    InvalidScope(String),
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+pub trait TokenModel {
+    fn create_token(
+        &self,
+        token: SubscriberToken,
+    ) -> impl Future<Output = Result<(), TokenModelError>> + Send;
+    fn get_token_by_hash(
+        &self,
+        token_hash: String,
+    ) -> impl Future<Output = Result<SubscriberToken, TokenModelError>> + Send;
+    fn revoke_token(
+        &self,
+        token_id: Uuid,
+    ) -> impl Future<Output = Result<(), TokenModelError>> + Send;
+}
+
+impl TokenModel for PgPool {
+    async fn create_token(&self, token: SubscriberToken) -> Result<(), TokenModelError> {
+        sqlx::query!(
+            "INSERT INTO subscriber_tokens (token_id, name, token_hash, regions, entities, author, expires_at) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            token.token_id,
+            token.name,
+            token.token_hash,
+            &token.regions,
+            &token.entities,
+            token.author,
+            token.expires_at
+        )
+        .execute(self)
+        .await
+        .map_err(|e| TokenModelError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_token_by_hash(
+        &self,
+        token_hash: String,
+    ) -> Result<SubscriberToken, TokenModelError> {
+        let row = sqlx::query!(
+            "SELECT token_id, name, token_hash, regions, entities, author, created_at, expires_at, revoked_at FROM subscriber_tokens WHERE token_hash = $1",
+            token_hash
+        )
+        .fetch_optional(self)
+        .await
+        .map_err(|e| TokenModelError::DatabaseError(e.to_string()))?
+        .ok_or(TokenModelError::NotFound)?;
+
+        Ok(SubscriberToken {
+            token_id: row.token_id,
+            name: row.name,
+            token_hash: row.token_hash,
+            regions: row.regions,
+            entities: row.entities,
+            author: row.author,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+            revoked_at: row.revoked_at,
+        })
+    }
+
+    async fn revoke_token(&self, token_id: Uuid) -> Result<(), TokenModelError> {
+        let result = sqlx::query!(
+            "UPDATE subscriber_tokens SET revoked_at = NOW() WHERE token_id = $1 AND revoked_at IS NULL",
+            token_id
+        )
+        .execute(self)
+        .await
+        .map_err(|e| TokenModelError::DatabaseError(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(TokenModelError::NotFound);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::Entity;
+
+    use super::*;
+
+    fn make_batch(entity: Entity, region: &str) -> ScrapeBatch {
+        ScrapeBatch {
+            batch_id: Uuid::nil(),
+            entity,
+            author: Uuid::nil(),
+            region: region.to_string(),
+            scraped_at: None,
+            checksum: "checksum".to_string(),
+            seq: None,
+            summary: None,
+        }
+    }
+
+    #[test]
+    fn test_empty_scope_allows_everything() {
+        let scope = TokenScope::default();
+        assert!(scope.allows(&make_batch(Entity::Restaurants, "Montpellier")));
+        assert!(scope.allows(&make_batch(Entity::Meals("ri".to_string()), "Paris")));
+    }
+
+    #[test]
+    fn test_scope_filters_region_and_entity() {
+        let scope = TokenScope {
+            regions: vec!["Montpellier".to_string()],
+            entities: vec!["meals".to_string()],
+        };
+        assert!(scope.allows(&make_batch(
+            Entity::Meals("brasserie-triolet".to_string()),
+            "Montpellier"
+        )));
+        assert!(!scope.allows(&make_batch(Entity::Restaurants, "Montpellier")));
+        assert!(!scope.allows(&make_batch(Entity::Meals("ri".to_string()), "Paris")));
+    }
+
+    #[test]
+    fn test_hash_token_is_stable() {
+        assert_eq!(hash_token("htc_secret"), hash_token("htc_secret"));
+        assert_ne!(hash_token("htc_secret"), hash_token("htc_other"));
+    }
+}
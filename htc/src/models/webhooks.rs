@@ -0,0 +1,642 @@
+use std::{future::Future, net::IpAddr};
+
+use chrono::{NaiveDateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::{
+    PgPool,
+    types::{Json, Uuid},
+};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use crate::models::{scrape_batch::ScrapeBatch, tokens::TokenScope};
+
+/// Header carrying `sha256=<hex>` of `"{timestamp}.{body}"`.
+pub const SIGNATURE_HEADER: &str = "X-HTC-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-HTC-Timestamp";
+pub const EVENT_HEADER: &str = "X-HTC-Event";
+/// Same for every attempt of a delivery, receivers can use it to deduplicate.
+pub const DELIVERY_HEADER: &str = "X-HTC-Delivery";
+
+#[derive(Debug, Clone, ToSchema, Serialize, Deserialize)]
+pub struct WebhookRequestSchema {
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub regions: Vec<String>,
+    #[serde(default)]
+    pub entities: Vec<String>,
+    /// Unix timestamp the request was signed at, refused once older than
+    /// `MAX_REQUEST_AGE`
+    pub issued_at: i64,
+}
+
+#[derive(Debug, Clone, ToSchema, Serialize, Deserialize)]
+pub struct RegisteredWebhookSchema {
+    #[schema(value_type = String)]
+    pub webhook_id: Uuid,
+    pub name: String,
+    pub url: String,
+    // only returned once, needed to check the signatures
+    pub secret: String,
+    pub regions: Vec<String>,
+    pub entities: Vec<String>,
+}
+
+#[derive(Debug, Clone, ToSchema, Serialize, Deserialize)]
+pub struct WebhookSchema {
+    #[schema(value_type = String)]
+    pub webhook_id: Uuid,
+    pub name: String,
+    pub url: String,
+    pub regions: Vec<String>,
+    pub entities: Vec<String>,
+    pub active: bool,
+    pub consecutive_failures: i32,
+    #[schema(value_type = Option<String>)]
+    pub created_at: Option<NaiveDateTime>,
+    #[schema(value_type = Option<String>)]
+    pub disabled_at: Option<NaiveDateTime>,
+    pub disabled_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, ToSchema, Serialize, Deserialize)]
+pub struct WebhookQuerySchema {
+    /// Only list the webhooks still (or no longer) active
+    pub active: Option<bool>,
+}
+
+/// What a signed `WebhookTargetSchema` may be used for, so a body signed to
+/// enable a webhook can't be replayed to delete it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ToSchema, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookAction {
+    Delete,
+    Enable,
+}
+
+/// Designates a webhook to delete or enable again.
+#[derive(Debug, Clone, ToSchema, Serialize, Deserialize)]
+pub struct WebhookTargetSchema {
+    #[schema(value_type = String)]
+    pub webhook_id: Uuid,
+    pub action: WebhookAction,
+    /// Unix timestamp the request was signed at, refused once older than
+    /// `MAX_REQUEST_AGE`
+    pub issued_at: i64,
+}
+
+impl WebhookTargetSchema {
+    pub fn new(webhook_id: Uuid, action: WebhookAction) -> Self {
+        Self {
+            webhook_id,
+            action,
+            issued_at: Utc::now().timestamp(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, ToSchema, Serialize, Deserialize)]
+pub struct DeliveryQuerySchema {
+    #[schema(value_type = String)]
+    pub webhook_id: Uuid,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, ToSchema, Serialize, Deserialize)]
+pub struct WebhookDeliverySchema {
+    #[schema(value_type = String)]
+    pub delivery_id: Uuid,
+    #[schema(value_type = String)]
+    pub batch_id: Uuid,
+    pub attempt: i32,
+    pub status_code: Option<i32>,
+    pub success: bool,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+    #[schema(value_type = Option<String>)]
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+/// Body POSTed to the webhooks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookNotification {
+    pub event: String,
+    pub delivery_id: Uuid,
+    pub batch: ScrapeBatch,
+}
+
+#[derive(Clone, Debug)]
+pub struct Webhook {
+    pub webhook_id: Uuid,
+    pub name: String,
+    pub url: String,
+    pub secret: String,
+    pub regions: Vec<String>,
+    pub entities: Vec<String>,
+    pub author: Uuid,
+    pub created_at: Option<NaiveDateTime>,
+    pub active: bool,
+    pub consecutive_failures: i32,
+    pub disabled_at: Option<NaiveDateTime>,
+    pub disabled_reason: Option<String>,
+}
+
+impl Webhook {
+    pub fn scope(&self) -> TokenScope {
+        TokenScope {
+            regions: self.regions.clone(),
+            entities: self.entities.clone(),
+        }
+    }
+}
+
+impl From<Webhook> for WebhookSchema {
+    fn from(webhook: Webhook) -> Self {
+        WebhookSchema {
+            webhook_id: webhook.webhook_id,
+            name: webhook.name,
+            url: webhook.url,
+            regions: webhook.regions,
+            entities: webhook.entities,
+            active: webhook.active,
+            consecutive_failures: webhook.consecutive_failures,
+            created_at: webhook.created_at,
+            disabled_at: webhook.disabled_at,
+            disabled_reason: webhook.disabled_reason,
+        }
+    }
+}
+
+/// A delivery waiting in `webhook_outbox` for its next attempt.
+#[derive(Clone, Debug)]
+pub struct PendingDelivery {
+    pub delivery_id: Uuid,
+    pub webhook_id: Uuid,
+    pub batch: ScrapeBatch,
+    /// Number of the next attempt, from 1
+    pub attempt: i32,
+}
+
+#[derive(Clone, Debug)]
+pub struct WebhookDelivery {
+    pub delivery_id: Uuid,
+    pub webhook_id: Uuid,
+    pub batch_id: Uuid,
+    pub attempt: i32,
+    pub status_code: Option<i32>,
+    pub success: bool,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+impl From<WebhookDelivery> for WebhookDeliverySchema {
+    fn from(delivery: WebhookDelivery) -> Self {
+        WebhookDeliverySchema {
+            delivery_id: delivery.delivery_id,
+            batch_id: delivery.batch_id,
+            attempt: delivery.attempt,
+            status_code: delivery.status_code,
+            success: delivery.success,
+            error: delivery.error,
+            duration_ms: delivery.duration_ms,
+            delivered_at: delivery.delivered_at,
+        }
+    }
+}
+
+fn webhook_mac(secret: &str, timestamp: i64, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// `sha256=<hex>` HMAC of `"{timestamp}.{body}"`, the timestamp is part of
+/// the signed content so a captured notification can't be replayed later.
+pub fn sign_webhook(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mac = webhook_mac(secret, timestamp, body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Constant time check of a `X-HTC-Signature` header, for receivers.
+pub fn verify_webhook(secret: &str, timestamp: i64, body: &[u8], signature: &str) -> bool {
+    let Some(Ok(signature)) = signature.strip_prefix("sha256=").map(hex::decode) else {
+        return false;
+    };
+    webhook_mac(secret, timestamp, body)
+        .verify_slice(&signature)
+        .is_ok()
+}
+
+/// Whether a webhook may be sent to `ip`, refusing the private, loopback,
+/// link-local and other non routable addresses so a webhook can't reach the
+/// services next to the API.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || first == 0
+                // shared address space, RFC 6598
+                || (first == 100 && (second & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(ip));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // unique local fc00::/7 and link-local fe80::/10
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum WebhookModelError {
+    #[error("Webhook not found")]
+    NotFound,
+    #[error("Invalid webhook : {0}")]
+    InvalidWebhook(String),
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+}
+
+pub trait WebhookModel {
+    fn create_webhook(
+        &self,
+        webhook: Webhook,
+    ) -> impl Future<Output = Result<(), WebhookModelError>> + Send;
+    fn get_webhooks(
+        &self,
+        active: Option<bool>,
+    ) -> impl Future<Output = Result<Vec<Webhook>, WebhookModelError>> + Send;
+    fn delete_webhook(
+        &self,
+        webhook_id: Uuid,
+    ) -> impl Future<Output = Result<(), WebhookModelError>> + Send;
+    fn enable_webhook(
+        &self,
+        webhook_id: Uuid,
+    ) -> impl Future<Output = Result<(), WebhookModelError>> + Send;
+    fn record_delivery(
+        &self,
+        delivery: WebhookDelivery,
+    ) -> impl Future<Output = Result<(), WebhookModelError>> + Send;
+    /// Resets or increments the consecutive failures of a webhook, disabling
+    /// it once `max_failures` is reached. Returns whether it is still active.
+    fn record_outcome(
+        &self,
+        webhook_id: Uuid,
+        success: bool,
+        max_failures: i32,
+    ) -> impl Future<Output = Result<bool, WebhookModelError>> + Send;
+    fn get_deliveries(
+        &self,
+        webhook_id: Uuid,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<WebhookDelivery>, WebhookModelError>> + Send;
+    /// Queues a delivery of `batch` to each webhook in `webhook_outbox`.
+    fn enqueue_deliveries(
+        &self,
+        webhook_ids: Vec<Uuid>,
+        batch: &ScrapeBatch,
+    ) -> impl Future<Output = Result<(), WebhookModelError>> + Send;
+    /// Takes up to `limit` due deliveries, pushing their next attempt
+    /// `lease_secs` away so another worker doesn't take them meanwhile.
+    fn claim_deliveries(
+        &self,
+        limit: i64,
+        lease_secs: f64,
+    ) -> impl Future<Output = Result<Vec<PendingDelivery>, WebhookModelError>> + Send;
+    fn retry_delivery(
+        &self,
+        delivery_id: Uuid,
+        attempt: i32,
+        delay_secs: f64,
+    ) -> impl Future<Output = Result<(), WebhookModelError>> + Send;
+    /// Removes a delivery from the outbox, delivered or given up.
+    fn complete_delivery(
+        &self,
+        delivery_id: Uuid,
+    ) -> impl Future<Output = Result<(), WebhookModelError>> + Send;
+}
+
+impl WebhookModel for PgPool {
+    async fn create_webhook(&self, webhook: Webhook) -> Result<(), WebhookModelError> {
+        sqlx::query!(
+            "INSERT INTO webhooks (webhook_id, name, url, secret, regions, entities, author) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            webhook.webhook_id,
+            webhook.name,
+            webhook.url,
+            webhook.secret,
+            &webhook.regions,
+            &webhook.entities,
+            webhook.author
+        )
+        .execute(self)
+        .await
+        .map_err(|e| WebhookModelError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_webhooks(&self, active: Option<bool>) -> Result<Vec<Webhook>, WebhookModelError> {
+        let rows = sqlx::query!(
+            "SELECT webhook_id, name, url, secret, regions, entities, author, created_at, active, consecutive_failures, disabled_at, disabled_reason FROM webhooks WHERE ($1::BOOLEAN IS NULL OR active = $1) ORDER BY created_at",
+            active
+        )
+        .fetch_all(self)
+        .await
+        .map_err(|e| WebhookModelError::DatabaseError(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Webhook {
+                webhook_id: row.webhook_id,
+                name: row.name,
+                url: row.url,
+                secret: row.secret,
+                regions: row.regions,
+                entities: row.entities,
+                author: row.author,
+                created_at: row.created_at,
+                active: row.active,
+                consecutive_failures: row.consecutive_failures,
+                disabled_at: row.disabled_at,
+                disabled_reason: row.disabled_reason,
+            })
+            .collect())
+    }
+
+    async fn delete_webhook(&self, webhook_id: Uuid) -> Result<(), WebhookModelError> {
+        let result = sqlx::query!("DELETE FROM webhooks WHERE webhook_id = $1", webhook_id)
+            .execute(self)
+            .await
+            .map_err(|e| WebhookModelError::DatabaseError(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(WebhookModelError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn enable_webhook(&self, webhook_id: Uuid) -> Result<(), WebhookModelError> {
+        let result = sqlx::query!(
+            "UPDATE webhooks SET active = TRUE, consecutive_failures = 0, disabled_at = NULL, disabled_reason = NULL WHERE webhook_id = $1",
+            webhook_id
+        )
+        .execute(self)
+        .await
+        .map_err(|e| WebhookModelError::DatabaseError(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(WebhookModelError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn record_delivery(&self, delivery: WebhookDelivery) -> Result<(), WebhookModelError> {
+        sqlx::query!(
+            "INSERT INTO webhook_deliveries (delivery_id, webhook_id, batch_id, attempt, status_code, success, error, duration_ms) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            delivery.delivery_id,
+            delivery.webhook_id,
+            delivery.batch_id,
+            delivery.attempt,
+            delivery.status_code,
+            delivery.success,
+            delivery.error,
+            delivery.duration_ms
+        )
+        .execute(self)
+        .await
+        .map_err(|e| WebhookModelError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn record_outcome(
+        &self,
+        webhook_id: Uuid,
+        success: bool,
+        max_failures: i32,
+    ) -> Result<bool, WebhookModelError> {
+        let row = sqlx::query!(
+            r#"UPDATE webhooks SET
+                consecutive_failures = CASE WHEN $2 THEN 0 ELSE consecutive_failures + 1 END,
+                active = active AND ($2 OR consecutive_failures + 1 < $3),
+                disabled_at = CASE WHEN active AND NOT $2 AND consecutive_failures + 1 >= $3 THEN NOW() ELSE disabled_at END,
+                disabled_reason = CASE WHEN active AND NOT $2 AND consecutive_failures + 1 >= $3
+                    THEN 'Disabled after ' || (consecutive_failures + 1) || ' failed deliveries in a row'
+                    ELSE disabled_reason END
+            WHERE webhook_id = $1
+            RETURNING active"#,
+            webhook_id,
+            success,
+            max_failures
+        )
+        .fetch_optional(self)
+        .await
+        .map_err(|e| WebhookModelError::DatabaseError(e.to_string()))?
+        .ok_or(WebhookModelError::NotFound)?;
+
+        Ok(row.active)
+    }
+
+    async fn get_deliveries(
+        &self,
+        webhook_id: Uuid,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, WebhookModelError> {
+        let rows = sqlx::query!(
+            "SELECT delivery_id, webhook_id, batch_id, attempt, status_code, success, error, duration_ms, delivered_at FROM webhook_deliveries WHERE webhook_id = $1 ORDER BY delivered_at DESC, attempt DESC LIMIT $2",
+            webhook_id,
+            limit
+        )
+        .fetch_all(self)
+        .await
+        .map_err(|e| WebhookModelError::DatabaseError(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| WebhookDelivery {
+                delivery_id: row.delivery_id,
+                webhook_id: row.webhook_id,
+                batch_id: row.batch_id,
+                attempt: row.attempt,
+                status_code: row.status_code,
+                success: row.success,
+                error: row.error,
+                duration_ms: row.duration_ms,
+                delivered_at: row.delivered_at,
+            })
+            .collect())
+    }
+
+    async fn enqueue_deliveries(
+        &self,
+        webhook_ids: Vec<Uuid>,
+        batch: &ScrapeBatch,
+    ) -> Result<(), WebhookModelError> {
+        sqlx::query!(
+            "INSERT INTO webhook_outbox (delivery_id, webhook_id, batch) SELECT gen_random_uuid(), webhook_id, $2 FROM UNNEST($1::UUID[]) AS webhook_id",
+            &webhook_ids,
+            Json(batch) as _
+        )
+        .execute(self)
+        .await
+        .map_err(|e| WebhookModelError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn claim_deliveries(
+        &self,
+        limit: i64,
+        lease_secs: f64,
+    ) -> Result<Vec<PendingDelivery>, WebhookModelError> {
+        let rows = sqlx::query!(
+            r#"UPDATE webhook_outbox SET next_attempt_at = NOW() + make_interval(secs => $2)
+            WHERE delivery_id IN (
+                SELECT delivery_id FROM webhook_outbox WHERE next_attempt_at <= NOW()
+                ORDER BY next_attempt_at LIMIT $1 FOR UPDATE SKIP LOCKED
+            )
+            RETURNING delivery_id, webhook_id, batch as "batch: Json<ScrapeBatch>", attempt"#,
+            limit,
+            lease_secs
+        )
+        .fetch_all(self)
+        .await
+        .map_err(|e| WebhookModelError::DatabaseError(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| PendingDelivery {
+                delivery_id: row.delivery_id,
+                webhook_id: row.webhook_id,
+                batch: row.batch.0,
+                attempt: row.attempt,
+            })
+            .collect())
+    }
+
+    async fn retry_delivery(
+        &self,
+        delivery_id: Uuid,
+        attempt: i32,
+        delay_secs: f64,
+    ) -> Result<(), WebhookModelError> {
+        sqlx::query!(
+            "UPDATE webhook_outbox SET attempt = $2, next_attempt_at = NOW() + make_interval(secs => $3) WHERE delivery_id = $1",
+            delivery_id,
+            attempt,
+            delay_secs
+        )
+        .execute(self)
+        .await
+        .map_err(|e| WebhookModelError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn complete_delivery(&self, delivery_id: Uuid) -> Result<(), WebhookModelError> {
+        sqlx::query!(
+            "DELETE FROM webhook_outbox WHERE delivery_id = $1",
+            delivery_id
+        )
+        .execute(self)
+        .await
+        .map_err(|e| WebhookModelError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_public_ips() {
+        for ip in ["93.184.216.34", "2606:2800:220:1::1"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify_webhook() {
+        let body = br#"{"event":"batch"}"#;
+        let signature = sign_webhook("whsec_test", 1_700_000_000, body);
+        assert!(signature.starts_with("sha256="));
+        assert!(verify_webhook(
+            "whsec_test",
+            1_700_000_000,
+            body,
+            &signature
+        ));
+        // replayed with another timestamp, another secret or a tampered body
+        assert!(!verify_webhook(
+            "whsec_test",
+            1_700_000_001,
+            body,
+            &signature
+        ));
+        assert!(!verify_webhook(
+            "whsec_other",
+            1_700_000_000,
+            body,
+            &signature
+        ));
+        assert!(!verify_webhook(
+            "whsec_test",
+            1_700_000_000,
+            b"{}",
+            &signature
+        ));
+        assert!(!verify_webhook(
+            "whsec_test",
+            1_700_000_000,
+            body,
+            "sha256=zz"
+        ));
+    }
+
+    #[test]
+    fn test_known_signature() {
+        // echo -n '42.hello' | openssl dgst -sha256 -hmac secret
+        assert_eq!(
+            sign_webhook("secret", 42, b"hello"),
+            "sha256=b405574dbe8f5633df309a5e9b1f9741ff4065e202974ba665a97ec691affaa5"
+        );
+    }
+}
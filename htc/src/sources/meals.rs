@@ -1,6 +1,6 @@
 use crawler::restaurant_page::RestaurantPageData;
 
-use crate::models::{meals::MealSchema, restaurants::RestaurantSchema};
+use crate::models::{meals::MealSchema, restaurants::RestaurantSchema, status::StatusSchema};
 
 pub struct RestaurantPageScrapedData {
     pub restaurant: RestaurantSchema,
@@ -14,6 +14,9 @@ impl From<RestaurantPageScrapedData> for Vec<MealSchema> {
     fn from(val: RestaurantPageScrapedData) -> Self {
         let mut meals = Vec::new();
         for menu in val.page.menus {
+            if let Some(status) = StatusSchema::detect(&menu.notices) {
+                meals.push(MealSchema::notice(&val.restaurant.id, &menu.date, status));
+            }
             for meal_data in menu.meals {
                 for category in meal_data.categories {
                     for dish in category.dishes {
@@ -22,6 +25,7 @@ impl From<RestaurantPageScrapedData> for Vec<MealSchema> {
                             foodies: Some(dish),
                             meal_type: category.name.clone(),
                             date: Some(menu.date.clone()),
+                            status: None,
                         });
                     }
                 }
@@ -45,6 +49,7 @@ mod tests {
             coordinates: None,
             opening_hours: None,
             city: None,
+            ..Default::default()
         }
     }
 
@@ -56,6 +61,7 @@ mod tests {
                 menus: vec![],
                 hours: "12:00 - 14:00".to_string(),
                 coordinates: (48.5734, 7.7521),
+                ..Default::default()
             },
         };
         let meals: Vec<MealSchema> = scraped.into();
@@ -78,9 +84,11 @@ mod tests {
                             dishes: vec!["Spaghetti".to_string()],
                         }],
                     }],
+                    notices: vec![],
                 }],
                 hours: "12:00 - 14:00".to_string(),
                 coordinates: (48.5734, 7.7521),
+                ..Default::default()
             },
         };
         let meals: Vec<MealSchema> = scraped.into();
@@ -111,9 +119,11 @@ mod tests {
                             },
                         ],
                     }],
+                    notices: vec![],
                 }],
                 hours: "12:00 - 14:00".to_string(),
                 coordinates: (48.5734, 7.7521),
+                ..Default::default()
             },
         };
         let meals: Vec<MealSchema> = scraped.into();
@@ -141,6 +151,7 @@ mod tests {
                                 dishes: vec!["Spaghetti".to_string()],
                             }],
                         }],
+                        notices: vec![],
                     },
                     MenuData {
                         date: "2024-06-02".to_string(),
@@ -151,10 +162,12 @@ mod tests {
                                 dishes: vec!["Pizza".to_string()],
                             }],
                         }],
+                        notices: vec![],
                     },
                 ],
                 hours: "12:00 - 14:00".to_string(),
                 coordinates: (48.5734, 7.7521),
+                ..Default::default()
             },
         };
         let meals: Vec<MealSchema> = scraped.into();
@@ -162,4 +175,27 @@ mod tests {
         assert_eq!(meals[0].date, Some("2024-06-01".to_string()));
         assert_eq!(meals[1].date, Some("2024-06-02".to_string()));
     }
+
+    #[test]
+    fn test_menu_notice_becomes_a_notice_row() {
+        let scraped = RestaurantPageScrapedData {
+            restaurant: make_restaurant(),
+            page: RestaurantPageData {
+                menus: vec![MenuData {
+                    date: "2024-06-03".to_string(),
+                    meals: vec![],
+                    notices: vec!["Menu non communiqué".to_string()],
+                }],
+                ..Default::default()
+            },
+        };
+        let meals: Vec<MealSchema> = scraped.into();
+        assert_eq!(meals.len(), 1);
+        assert!(meals[0].is_notice());
+        assert_eq!(meals[0].foodies, None);
+        assert_eq!(
+            meals[0].status.as_ref().map(|status| status.state),
+            Some(crate::models::status::OpeningState::Unknown)
+        );
+    }
 }
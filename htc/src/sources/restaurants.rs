@@ -1,4 +1,12 @@
-use crate::{id::build_id, models::restaurants::RestaurantSchema};
+use chrono::{Local, NaiveDate};
+
+use crate::{
+    id::build_id,
+    models::{
+        restaurants::{RestaurantSchema, VenueType},
+        status::StatusSchema,
+    },
+};
 use crawler::{restaurant_list::RestaurantData, restaurant_page::RestaurantPageData};
 
 pub struct RestaurantScrapedData {
@@ -6,29 +14,117 @@ pub struct RestaurantScrapedData {
     pub description: RestaurantData,
 }
 
+/// Amenities telling the restaurant welcomes people with reduced mobility.
+const ACCESSIBILITY_MARKERS: &[&str] = &["handicap", "pmr", "mobilité réduite"];
+
+/// Only a positive answer is trusted, pages don't list what's missing.
+fn accessible(amenities: &[String]) -> Option<bool> {
+    amenities
+        .iter()
+        .any(|amenity| {
+            let amenity = amenity.to_lowercase();
+            ACCESSIBILITY_MARKERS
+                .iter()
+                .any(|marker| amenity.contains(marker))
+        })
+        .then_some(true)
+}
+
+/// The banners of the page first, then whether it has dishes, then what its
+/// menu days say when they have none. A page serving dishes only gives way
+/// to a banner dated for `today`, as undated ones such as `Fermé le samedi`
+/// tell the weekly closing days.
+fn status(page: &RestaurantPageData, today: NaiveDate) -> StatusSchema {
+    let has_dishes = page
+        .menus
+        .iter()
+        .flat_map(|menu| &menu.meals)
+        .flat_map(|meal| &meal.categories)
+        .any(|category| !category.dishes.is_empty());
+    if let Some(status) = StatusSchema::detect(&page.notices)
+        && (!has_dishes || status.covers(today))
+    {
+        return status;
+    }
+    if has_dishes {
+        return StatusSchema::open();
+    }
+    page.menus
+        .iter()
+        .find_map(|menu| StatusSchema::detect(&menu.notices))
+        .unwrap_or_default()
+}
+
+/// The label of the list first, then the name the page gives, then the one
+/// of the list.
+fn venue_type(description: &RestaurantData, page: &RestaurantPageData) -> VenueType {
+    [
+        description.venue_type.as_deref(),
+        page.title.as_deref(),
+        Some(description.name.as_str()),
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(VenueType::classify)
+    .unwrap_or_default()
+}
+
+#[allow(clippy::from_over_into)]
 impl Into<RestaurantSchema> for RestaurantScrapedData {
     fn into(self) -> RestaurantSchema {
+        let status = status(&self.page, Local::now().date_naive());
+        let venue_type = venue_type(&self.description, &self.page);
         let (latitude, longitude) = self.page.coordinates;
         RestaurantSchema {
             id: build_id(&self.description.name),
             name: self.description.name,
             url: self.description.crous_url,
             city: Some(self.description.city),
+            venue_type,
             coordinates: Some(format!("{},{}", latitude, longitude)),
             opening_hours: Some(self.page.hours),
+            address: self.page.address,
+            phone: self.page.phone,
+            payment_methods: self.page.payment_methods,
+            accessible: accessible(&self.page.amenities),
+            amenities: self.page.amenities,
+            access: self.page.access,
+            photos: self.page.photos,
+            status,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crawler::restaurant_page::{FoodCategory, MealData, MenuData};
+    use std::sync::Arc;
+
+    use crawler::{
+        Scraper,
+        fetcher::{FetchFuture, Fetcher},
+        restaurant_page::{FoodCategory, MealData, MenuData, RestaurantPageScraper},
+    };
 
     use super::*;
+    use crate::models::status::OpeningState;
+
+    /// Serves the same page whatever the url.
+    struct PageFetcher(String);
+
+    impl Fetcher for PageFetcher {
+        fn fetch<'a>(&'a self, _url: &'a str) -> FetchFuture<'a> {
+            Box::pin(async move { Ok(self.0.clone()) })
+        }
+    }
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 2, 18).unwrap()
+    }
 
     #[test]
     fn test_restaurant_conversion() {
         let restaurant_page_data = RestaurantPageData {
+            title: None,
             menus: vec![MenuData {
                 date: "2024-06-01".to_string(),
                 meals: vec![MealData {
@@ -44,15 +140,24 @@ mod tests {
                         },
                     ],
                 }],
+                notices: vec![],
             }],
             hours: "12:00 - 14:00".to_string(),
             coordinates: (48.5734, 7.7521),
+            address: Some("1 rue de la Paix, 67000 Strasbourg".to_string()),
+            phone: None,
+            payment_methods: vec!["IZLY".to_string()],
+            amenities: vec!["Accès Handicapé".to_string(), "Accès wifi".to_string()],
+            access: Some("Tram C, arrêt Esplanade".to_string()),
+            photos: vec![],
+            notices: vec![],
         };
 
         let restaurant_data = RestaurantData {
             name: "Test Restaurant".to_string(),
             city: "Strasbourg".to_string(),
             crous_url: "https://example.com/restaurant".to_string(),
+            venue_type: None,
         };
 
         let scraped = RestaurantScrapedData {
@@ -66,5 +171,125 @@ mod tests {
         assert_eq!(restaurant.city, Some("Strasbourg".to_string()));
         assert_eq!(restaurant.coordinates, Some("48.5734,7.7521".to_string()));
         assert_eq!(restaurant.opening_hours, Some("12:00 - 14:00".to_string()));
+        assert_eq!(restaurant.payment_methods, vec!["IZLY"]);
+        assert_eq!(restaurant.accessible, Some(true));
+        assert_eq!(
+            restaurant.access,
+            Some("Tram C, arrêt Esplanade".to_string())
+        );
+        assert!(restaurant.status.is_open());
+        assert_eq!(restaurant.venue_type, VenueType::RestoU);
+    }
+
+    #[test]
+    fn test_venue_type_prefers_the_list_label() {
+        let description = |venue_type: Option<&str>| RestaurantData {
+            name: "Le Bullier".to_string(),
+            city: "Paris".to_string(),
+            crous_url: "https://example.com/bullier".to_string(),
+            venue_type: venue_type.map(str::to_string),
+        };
+        let page = RestaurantPageData {
+            title: Some("Brasserie Le Bullier".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            venue_type(&description(Some("Food truck")), &page),
+            VenueType::FoodTruck
+        );
+        assert_eq!(venue_type(&description(None), &page), VenueType::Brasserie);
+        assert_eq!(
+            venue_type(&description(None), &RestaurantPageData::default()),
+            VenueType::Unknown
+        );
+    }
+
+    #[test]
+    fn test_restaurant_status_from_notices() {
+        let closed_menu = |notice: &str| MenuData {
+            date: "Menu du lundi 2 mars 2026".to_string(),
+            meals: vec![],
+            notices: vec![notice.to_string()],
+        };
+        let page = RestaurantPageData {
+            menus: vec![closed_menu("Menu non communiqué")],
+            ..Default::default()
+        };
+        assert_eq!(status(&page, today()).state, OpeningState::Unknown);
+
+        let page = RestaurantPageData {
+            menus: vec![closed_menu("Menu non communiqué")],
+            notices: vec!["Fermé pour travaux jusqu'au 13 mars 2026".to_string()],
+            ..Default::default()
+        };
+        let closed = status(&page, today());
+        assert_eq!(closed.state, OpeningState::Closed);
+        assert_eq!(closed.until, NaiveDate::from_ymd_opt(2026, 3, 13));
+
+        assert_eq!(
+            status(&RestaurantPageData::default(), today()),
+            StatusSchema::default()
+        );
+    }
+
+    #[test]
+    fn test_banners_give_way_to_dishes_unless_dated_today() {
+        let serving = |notice: &str| RestaurantPageData {
+            menus: vec![MenuData {
+                date: "Menu du mercredi 18 février 2026".to_string(),
+                meals: vec![MealData {
+                    title: "Déjeuner".to_string(),
+                    categories: vec![FoodCategory {
+                        name: "Plat".to_string(),
+                        dishes: vec!["Lasagnes".to_string()],
+                    }],
+                }],
+                notices: vec![],
+            }],
+            notices: vec![notice.to_string()],
+            ..Default::default()
+        };
+        assert!(status(&serving("Fermé pour travaux"), today()).is_open());
+        assert!(status(&serving("Fermé du 2 au 13 mars 2026"), today()).is_open());
+        assert_eq!(
+            status(&serving("Fermé du 16 au 20 février 2026"), today()).state,
+            OpeningState::Closed
+        );
+    }
+
+    #[tokio::test]
+    async fn test_weekly_closing_sentence_on_a_real_page() {
+        // a captured page, with the sentence some sites write above the menus
+        let html = include_str!("../../../crawler/src/stubs/brasserie-triolet.html").replacen(
+            r#"<section class="menus">"#,
+            r#"<section class="menus"><p>Le restaurant est fermé le samedi et le dimanche.</p>"#,
+            1,
+        );
+        let page = RestaurantPageScraper::new(
+            "https://www.crous-montpellier.fr/restaurant/brasserie-triolet/".to_string(),
+            Arc::new(PageFetcher(html)),
+        )
+        .scrape()
+        .await
+        .unwrap();
+        assert_eq!(
+            page.notices,
+            vec!["Le restaurant est fermé le samedi et le dimanche."]
+        );
+        assert!(page.menus.iter().all(|menu| menu.notices.is_empty()));
+        assert!(status(&page, today()).is_open());
+
+        // without dishes, the banner is all the page says
+        let page = RestaurantPageData {
+            menus: vec![],
+            ..page
+        };
+        assert_eq!(status(&page, today()).state, OpeningState::Closed);
+    }
+
+    #[test]
+    fn test_accessibility_is_unknown_unless_listed() {
+        assert_eq!(accessible(&["Accès wifi".to_string()]), None);
+        assert_eq!(accessible(&["Ascenseur PMR".to_string()]), Some(true));
     }
 }
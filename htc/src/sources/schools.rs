@@ -7,6 +7,7 @@ pub struct SchoolApiScrapedData {
     pub api_data: ApiSchool,
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<School> for SchoolApiScrapedData {
     fn into(self) -> School {
         School {
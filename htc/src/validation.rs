@@ -0,0 +1,509 @@
+//! Sanity checks of a scraped batch, run by crousctl before uploading it and
+//! by the API before saving it, so a page whose layout changed doesn't wipe
+//! good data with empty menus or `(0, 0)` coordinates.
+
+use std::{collections::BTreeMap, fmt::Display};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{meals::MealSchema, restaurants::RestaurantSchema},
+    regions::CrousRegion,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Reported, the batch is still uploaded.
+    Warning,
+    /// The batch is rejected.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Finding {
+    pub severity: Severity,
+    pub rule: String,
+    /// What the finding is about, a restaurant URL, a date or the batch.
+    pub subject: String,
+    pub message: String,
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {} : {}", self.rule, self.subject, self.message)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationReport {
+    pub findings: Vec<Finding>,
+}
+
+impl ValidationReport {
+    fn push(&mut self, severity: Severity, rule: &str, subject: &str, message: String) {
+        self.findings.push(Finding {
+            severity,
+            rule: rule.to_string(),
+            subject: subject.to_string(),
+            message,
+        });
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Finding> {
+        self.findings
+            .iter()
+            .filter(|finding| finding.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Finding> {
+        self.findings
+            .iter()
+            .filter(|finding| finding.severity == Severity::Warning)
+    }
+
+    pub fn is_blocking(&self) -> bool {
+        self.errors().next().is_some()
+    }
+}
+
+/// Lists the errors, what the API answers when it rejects a batch.
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let errors: Vec<String> = self.errors().map(Finding::to_string).collect();
+        write!(f, "{}", errors.join("; "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationRules {
+    /// Dishes a meals batch must have at least.
+    pub min_dishes: usize,
+    /// Share of the server's restaurants a batch may lose at once.
+    pub max_drop_ratio: f64,
+    /// Share of restaurants that may fail a per restaurant rule before it's
+    /// more likely the scraper than the restaurants.
+    pub max_suspicious_ratio: f64,
+    pub max_days_behind: i64,
+    pub max_days_ahead: i64,
+}
+
+impl Default for ValidationRules {
+    fn default() -> Self {
+        Self {
+            min_dishes: 3,
+            max_drop_ratio: 0.5,
+            max_suspicious_ratio: 0.5,
+            max_days_behind: 7,
+            max_days_ahead: 60,
+        }
+    }
+}
+
+/// Rough bounding box of the places a CROUS has restaurants in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min_lat: f64,
+    pub max_lat: f64,
+    pub min_lon: f64,
+    pub max_lon: f64,
+}
+
+impl Bounds {
+    const fn new(min_lat: f64, max_lat: f64, min_lon: f64, max_lon: f64) -> Self {
+        Self {
+            min_lat,
+            max_lat,
+            min_lon,
+            max_lon,
+        }
+    }
+
+    pub fn contains(&self, (lat, lon): (f64, f64)) -> bool {
+        (self.min_lat..=self.max_lat).contains(&lat) && (self.min_lon..=self.max_lon).contains(&lon)
+    }
+}
+
+/// Boxes are generous on purpose, they catch swapped or missing coordinates,
+/// not a campus across the border of the académie.
+pub fn region_bounds(region: CrousRegion) -> Bounds {
+    match region {
+        CrousRegion::Aixmarseille => Bounds::new(42.9, 45.2, 4.2, 7.2),
+        CrousRegion::Amiens => Bounds::new(48.8, 50.5, 1.2, 4.4),
+        CrousRegion::Antillesguyane => Bounds::new(2.0, 18.2, -63.2, -51.5),
+        CrousRegion::Bordeaux => Bounds::new(42.7, 46.0, -1.9, 1.6),
+        CrousRegion::Bourgogne => Bounds::new(46.1, 48.5, 2.7, 7.3),
+        CrousRegion::Clermont => Bounds::new(44.5, 46.9, 1.9, 4.7),
+        CrousRegion::Corse => Bounds::new(41.3, 43.1, 8.5, 9.6),
+        CrousRegion::Creteil => Bounds::new(48.0, 49.3, 2.2, 3.6),
+        CrousRegion::Grenoble => Bounds::new(44.1, 46.5, 4.2, 7.2),
+        CrousRegion::Lille => Bounds::new(50.0, 51.1, 1.5, 4.3),
+        CrousRegion::Limoges => Bounds::new(44.9, 46.5, 0.6, 2.7),
+        CrousRegion::Lorraine => Bounds::new(47.8, 49.7, 4.8, 7.7),
+        CrousRegion::Lyon => Bounds::new(45.0, 46.6, 3.6, 6.0),
+        CrousRegion::Montpellier => Bounds::new(42.3, 44.9, 2.4, 4.9),
+        CrousRegion::Nantes => Bounds::new(46.2, 48.6, -2.7, 1.0),
+        CrousRegion::Normandie => Bounds::new(48.1, 50.1, -2.0, 1.9),
+        CrousRegion::Nice => Bounds::new(42.9, 44.4, 5.6, 7.8),
+        CrousRegion::Orleans => Bounds::new(46.3, 48.6, 0.0, 3.2),
+        CrousRegion::Paris => Bounds::new(48.7, 49.0, 2.1, 2.6),
+        CrousRegion::Poitiers => Bounds::new(45.1, 47.2, -1.6, 1.3),
+        CrousRegion::Reims => Bounds::new(47.5, 50.2, 3.3, 5.9),
+        CrousRegion::Rennes => Bounds::new(47.2, 49.0, -5.2, -1.0),
+        CrousRegion::Reunion => Bounds::new(-21.5, -12.5, 44.9, 55.9),
+        CrousRegion::Strasbourg => Bounds::new(47.4, 49.1, 6.8, 8.3),
+        CrousRegion::Toulouse => Bounds::new(42.3, 45.1, -0.4, 3.0),
+        CrousRegion::Versailles => Bounds::new(48.3, 49.3, 1.4, 2.6),
+    }
+}
+
+pub(crate) const MONTHS: [&str; 12] = [
+    "janvier",
+    "février",
+    "mars",
+    "avril",
+    "mai",
+    "juin",
+    "juillet",
+    "août",
+    "septembre",
+    "octobre",
+    "novembre",
+    "décembre",
+];
+
+/// Date of a menu title, e.g. `Menu du mercredi 18 février 2026`, or an ISO
+/// date.
+pub fn parse_menu_date(title: &str) -> Option<NaiveDate> {
+    if let Ok(date) = NaiveDate::parse_from_str(title.trim(), "%Y-%m-%d") {
+        return Some(date);
+    }
+    let title = title.to_lowercase();
+    let words: Vec<&str> = title.split_whitespace().collect();
+    let (index, month) = words.iter().enumerate().find_map(|(index, word)| {
+        let month = MONTHS.iter().position(|month| month == word)?;
+        Some((index, month as u32 + 1))
+    })?;
+    let day = words
+        .get(index.checked_sub(1)?)?
+        .trim_end_matches("er")
+        .parse()
+        .ok()?;
+    let year = words.get(index + 1)?.parse().ok()?;
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+fn coordinates(restaurant: &RestaurantSchema) -> Option<(f64, f64)> {
+    let (lat, lon) = restaurant.coordinates.as_deref()?.split_once(',')?;
+    let coordinates = (lat.trim().parse().ok()?, lon.trim().parse().ok()?);
+    (coordinates != (0.0, 0.0)).then_some(coordinates)
+}
+
+/// `current` is what the server has for the region.
+pub fn validate_restaurants(
+    region: CrousRegion,
+    restaurants: &[RestaurantSchema],
+    current: &[RestaurantSchema],
+    rules: &ValidationRules,
+) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let batch = format!("{} restaurants", region);
+    if restaurants.is_empty() {
+        report.push(
+            Severity::Error,
+            "not_empty",
+            &batch,
+            "no restaurant".to_string(),
+        );
+        return report;
+    }
+
+    let bounds = region_bounds(region);
+    let mut misplaced = 0;
+    let mut without_hours = 0;
+    for restaurant in restaurants {
+        match coordinates(restaurant) {
+            None => {
+                misplaced += 1;
+                report.push(
+                    Severity::Warning,
+                    "coordinates",
+                    &restaurant.url,
+                    "no coordinates".to_string(),
+                );
+            }
+            Some(coordinates) if !bounds.contains(coordinates) => {
+                misplaced += 1;
+                report.push(
+                    Severity::Warning,
+                    "coordinates",
+                    &restaurant.url,
+                    format!("{:?} is outside of {}", coordinates, region),
+                );
+            }
+            Some(_) => {}
+        }
+        let hours = restaurant.opening_hours.as_deref().map(str::trim);
+        if hours.is_none_or(|hours| hours.is_empty() || hours == "N/A") {
+            without_hours += 1;
+            report.push(
+                Severity::Warning,
+                "opening_hours",
+                &restaurant.url,
+                "no opening hours".to_string(),
+            );
+        }
+    }
+
+    let suspicious =
+        |count: usize| count as f64 > restaurants.len() as f64 * rules.max_suspicious_ratio;
+    if suspicious(misplaced) {
+        report.push(
+            Severity::Error,
+            "coordinates",
+            &batch,
+            format!(
+                "{} of {} restaurants have no plausible coordinates",
+                misplaced,
+                restaurants.len()
+            ),
+        );
+    }
+    if suspicious(without_hours) {
+        report.push(
+            Severity::Error,
+            "opening_hours",
+            &batch,
+            format!(
+                "{} of {} restaurants have no opening hours",
+                without_hours,
+                restaurants.len()
+            ),
+        );
+    }
+
+    if (restaurants.len() as f64) < current.len() as f64 * (1.0 - rules.max_drop_ratio) {
+        report.push(
+            Severity::Error,
+            "drop",
+            &batch,
+            format!(
+                "{} restaurants where the server has {}",
+                restaurants.len(),
+                current.len()
+            ),
+        );
+    }
+    report
+}
+
+fn dishes_by_date(meals: &[MealSchema]) -> BTreeMap<String, usize> {
+    let mut dishes = BTreeMap::new();
+    for meal in meals {
+        if meal
+            .foodies
+            .as_deref()
+            .is_some_and(|foodies| !foodies.trim().is_empty())
+        {
+            *dishes
+                .entry(meal.date.clone().unwrap_or_default())
+                .or_default() += 1;
+        }
+    }
+    dishes
+}
+
+/// `current` is what the server has for the same restaurants.
+pub fn validate_meals(
+    meals: &[MealSchema],
+    current: &[MealSchema],
+    today: NaiveDate,
+    rules: &ValidationRules,
+) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let batch = meals.first().map_or("meals".to_string(), |meal| {
+        format!("{} meals", meal.restaurant_id)
+    });
+
+    let dishes = dishes_by_date(meals);
+    let total: usize = dishes.values().sum();
+    // a closed restaurant or one without menus says so, it isn't a layout change
+    let noticed = meals
+        .iter()
+        .any(|meal| meal.status.as_ref().is_some_and(|status| !status.is_open()));
+    if total < rules.min_dishes {
+        report.push(
+            if noticed {
+                Severity::Warning
+            } else {
+                Severity::Error
+            },
+            "min_dishes",
+            &batch,
+            format!("{} dishes, at least {} expected", total, rules.min_dishes),
+        );
+    }
+
+    let current = dishes_by_date(current);
+    for (date, count) in &dishes {
+        match parse_menu_date(date) {
+            None => report.push(
+                Severity::Warning,
+                "date",
+                date,
+                "not a menu date".to_string(),
+            ),
+            Some(day) => {
+                let days = (day - today).num_days();
+                if days < -rules.max_days_behind || days > rules.max_days_ahead {
+                    report.push(
+                        Severity::Error,
+                        "date",
+                        date,
+                        format!("{} days away from today", days),
+                    );
+                }
+            }
+        }
+        if let Some(previous) = current.get(date)
+            && (*count as f64) < *previous as f64 * (1.0 - rules.max_drop_ratio)
+        {
+            report.push(
+                Severity::Warning,
+                "drop",
+                date,
+                format!("{} dishes where the server has {}", count, previous),
+            );
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::status::StatusSchema;
+
+    fn restaurant(url: &str, coordinates: &str, hours: &str) -> RestaurantSchema {
+        RestaurantSchema {
+            id: url.to_string(),
+            name: url.to_string(),
+            url: url.to_string(),
+            city: None,
+            coordinates: Some(coordinates.to_string()),
+            opening_hours: Some(hours.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn meal(date: &str, foodies: &str) -> MealSchema {
+        MealSchema {
+            meal_type: "Déjeuner".to_string(),
+            foodies: Some(foodies.to_string()),
+            date: Some(date.to_string()),
+            restaurant_id: "triolet".to_string(),
+            status: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_menu_date() {
+        assert_eq!(
+            parse_menu_date("Menu du mercredi 18 février 2026"),
+            NaiveDate::from_ymd_opt(2026, 2, 18)
+        );
+        assert_eq!(
+            parse_menu_date("Menu du lundi 1er juin 2026"),
+            NaiveDate::from_ymd_opt(2026, 6, 1)
+        );
+        assert_eq!(
+            parse_menu_date("2024-06-01"),
+            NaiveDate::from_ymd_opt(2024, 6, 1)
+        );
+        assert_eq!(parse_menu_date("Menu"), None);
+    }
+
+    #[test]
+    fn test_validate_restaurants() {
+        let rules = ValidationRules::default();
+        let good = restaurant("triolet", "43.63,3.86", "11h30 - 13h30");
+        let report = validate_restaurants(
+            CrousRegion::Montpellier,
+            std::slice::from_ref(&good),
+            &[],
+            &rules,
+        );
+        assert!(report.findings.is_empty());
+
+        // one broken restaurant out of three only warns
+        let broken = restaurant("richter", "0,0", "N/A");
+        let batch = [good.clone(), good.clone(), broken.clone()];
+        let report = validate_restaurants(CrousRegion::Montpellier, &batch, &[], &rules);
+        assert!(!report.is_blocking());
+        assert_eq!(report.warnings().count(), 2);
+
+        // all of them looks like the layout changed
+        let report = validate_restaurants(
+            CrousRegion::Montpellier,
+            &[broken.clone(), broken],
+            &[],
+            &rules,
+        );
+        assert!(report.is_blocking());
+
+        let report = validate_restaurants(
+            CrousRegion::Paris,
+            std::slice::from_ref(&good),
+            &[good.clone(), good.clone(), good.clone()],
+            &rules,
+        );
+        let rules: Vec<&str> = report.errors().map(|e| e.rule.as_str()).collect();
+        assert_eq!(rules, ["coordinates", "drop"]);
+    }
+
+    #[test]
+    fn test_validate_meals() {
+        let rules = ValidationRules::default();
+        let today = NaiveDate::from_ymd_opt(2026, 2, 17).unwrap();
+        let date = "Menu du mercredi 18 février 2026";
+        let meals = [
+            meal(date, "Frites"),
+            meal(date, "Salade"),
+            meal(date, "Tarte"),
+        ];
+        assert!(
+            validate_meals(&meals, &[], today, &rules)
+                .findings
+                .is_empty()
+        );
+
+        let report = validate_meals(&meals[..1], &meals, today, &rules);
+        assert!(report.is_blocking());
+        assert!(report.warnings().any(|warning| warning.rule == "drop"));
+
+        let stale = "Menu du mercredi 18 février 2025";
+        let meals = [
+            meal(stale, "Frites"),
+            meal(stale, "Salade"),
+            meal(stale, "Tarte"),
+        ];
+        let report = validate_meals(&meals, &[], today, &rules);
+        assert_eq!(report.errors().next().unwrap().rule, "date");
+    }
+
+    #[test]
+    fn test_validate_closed_meals() {
+        let rules = ValidationRules::default();
+        let today = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        let status = StatusSchema::from_notice("Fermé pour travaux").unwrap();
+        let meals = [MealSchema::notice(
+            "triolet",
+            "Menu du lundi 2 mars 2026",
+            status,
+        )];
+        let report = validate_meals(&meals, &[], today, &rules);
+        assert!(!report.is_blocking(), "{}", report);
+        assert_eq!(report.warnings().next().unwrap().rule, "min_dishes");
+    }
+}
@@ -38,6 +38,21 @@ pub enum SigningError {
     ParsingPublicKeyFailed(String),
     #[error("Invalid base64")]
     InvalidBASE64,
+    #[error("Request issued at {0} is too old or in the future")]
+    Stale(i64),
+}
+
+/// Seconds a signed admin request stays valid after its `issued_at`, so a
+/// captured body can't be replayed once the window is over.
+pub const MAX_REQUEST_AGE: i64 = 300;
+
+/// Checks the `issued_at` unix timestamp of a signed request against `now`,
+/// allowing the same drift in the future for clock skew.
+pub fn check_issued_at(issued_at: i64, now: i64) -> Result<(), SigningError> {
+    if (now - issued_at).abs() > MAX_REQUEST_AGE {
+        return Err(SigningError::Stale(issued_at));
+    }
+    Ok(())
 }
 
 impl<T> SignedPayload<T>
@@ -53,12 +68,30 @@ where
     }
 
     pub fn verify(&self, public_key: &str) -> Result<(&T, String), SigningError> {
-        let serialized_payload = serde_json::json!(self.payload).to_string();
+        let serialized_payload = serialize_payload(&self.payload);
         let digest = verify(serialized_payload, &self.signature, public_key)?;
         Ok((&self.payload, digest))
     }
 }
 
+/// Exact string that gets hashed then signed. Going through `serde_json::Value`
+/// sorts object keys, so the field order of `T` doesn't matter.
+pub fn serialize_payload<T>(payload: &T) -> String
+where
+    T: Serialize,
+{
+    serde_json::json!(payload).to_string()
+}
+
+/// Digest of a payload as returned by [`SignedPayload::verify`] and stored as
+/// the batch checksum.
+pub fn payload_digest<T>(payload: &T) -> String
+where
+    T: Serialize,
+{
+    BASE64_STANDARD.encode(Sha256::digest(serialize_payload(payload)))
+}
+
 pub fn sign<T>(
     payload: T,
     private_key: &str,
@@ -67,7 +100,7 @@ pub fn sign<T>(
 where
     T: Serialize + DeserializeOwned + Debug,
 {
-    let serialized_payload = serde_json::json!(payload).to_string();
+    let serialized_payload = serialize_payload(&payload);
     let digest = &Sha256::digest(&serialized_payload)[..];
 
     let private_key = BASE64_STANDARD
@@ -112,6 +145,12 @@ pub fn verify(payload: String, signature: &str, public_key: &str) -> Result<Stri
     Ok(BASE64_STANDARD.encode(digest))
 }
 
+/// Short, stable identifier of a base64 encoded public key, safe to log.
+pub fn key_fingerprint(public_key: &str) -> String {
+    let digest = BASE64_STANDARD.encode(Sha256::digest(public_key));
+    format!("SHA256:{}", &digest[..16])
+}
+
 pub fn read_pkcs8_pem_private_key(content: &str) -> Result<SigningKey, SigningError> {
     SigningKey::from_pkcs8_pem(content)
         .map_err(|e| SigningError::ParsingPrivateKeyFailed(e.to_string()))
@@ -122,7 +161,9 @@ mod tests {
     use base64::prelude::*;
     use serde::{Deserialize, Serialize};
 
-    use crate::verifiable::{sign, verify};
+    use crate::verifiable::{
+        MAX_REQUEST_AGE, SignedPayload, check_issued_at, payload_digest, sign, verify,
+    };
 
     #[derive(Serialize, Deserialize, Debug)]
     struct Foo {
@@ -180,4 +221,24 @@ mod tests {
         let res = verify(serialized_payload, &res.signature, &public_key_b64());
         assert!(res.is_err());
     }
+
+    #[test]
+    fn test_payload_digest_matches_verify() {
+        let payload = serde_json::json!({ "bar": "baz", "foo": [1, 2] });
+
+        let signed =
+            SignedPayload::<serde_json::Value>::sign(payload.clone(), &private_key_b64(), "John")
+                .unwrap();
+        let (_, digest) = signed.verify(&public_key_b64()).unwrap();
+        assert_eq!(digest, payload_digest(&payload));
+    }
+
+    #[test]
+    fn test_check_issued_at() {
+        let now = 1_700_000_000;
+        assert!(check_issued_at(now, now).is_ok());
+        assert!(check_issued_at(now - MAX_REQUEST_AGE, now).is_ok());
+        assert!(check_issued_at(now - MAX_REQUEST_AGE - 1, now).is_err());
+        assert!(check_issued_at(now + MAX_REQUEST_AGE + 1, now).is_err());
+    }
 }
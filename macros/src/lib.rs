@@ -198,15 +198,6 @@ pub fn generate_crous_data(input: TokenStream) -> TokenStream {
     quote! {
         pub struct CrousUrl(pub String);
 
-        impl CrousUrl {
-            pub fn to_list_url(&self) -> String {
-                if self.0.contains("paris") {
-                    return format!("{}se-restaurer/ou-manger/carte", self.0);
-                }
-                format!("{}se-restaurer/ou-manger/", self.0)
-            }
-        }
-
         impl ::std::ops::Deref for CrousUrl {
             type Target = str;
             fn deref(&self) -> &str {
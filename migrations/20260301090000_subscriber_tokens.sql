@@ -0,0 +1,13 @@
+-- Subscriber tokens (read-only credentials for /events)
+
+CREATE TABLE IF NOT EXISTS subscriber_tokens(
+		token_id UUID PRIMARY KEY,
+		name VARCHAR(200) NOT NULL,
+		token_hash VARCHAR(100) NOT NULL UNIQUE,
+		regions TEXT[] NOT NULL DEFAULT '{}',
+		entities TEXT[] NOT NULL DEFAULT '{}',
+		author UUID NOT NULL REFERENCES admins(admin_id),
+		created_at TIMESTAMP DEFAULT NOW(),
+		expires_at TIMESTAMP,
+		revoked_at TIMESTAMP
+);
@@ -0,0 +1,28 @@
+-- Audit log (append-only)
+
+CREATE TABLE IF NOT EXISTS audit_log(
+		audit_id UUID PRIMARY KEY,
+		action VARCHAR(100) NOT NULL,
+		author VARCHAR(200) NOT NULL,
+		admin_id UUID REFERENCES admins(admin_id),
+		key_fingerprint VARCHAR(100),
+		client_version TEXT,
+		source_ip VARCHAR(100),
+		region VARCHAR(500),
+		payload_digest VARCHAR(100),
+		outcome VARCHAR(50) NOT NULL,
+		detail TEXT,
+		created_at TIMESTAMP DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_created_at_idx ON audit_log(created_at DESC);
+
+CREATE OR REPLACE FUNCTION audit_log_append_only() RETURNS TRIGGER AS $$
+BEGIN
+		RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_append_only_trigger
+		BEFORE UPDATE OR DELETE ON audit_log
+		FOR EACH ROW EXECUTE PROCEDURE audit_log_append_only();
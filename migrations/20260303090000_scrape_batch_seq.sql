@@ -0,0 +1,5 @@
+-- Scrape batch sequence, used as SSE event id for replays
+
+ALTER TABLE scrape_batch ADD COLUMN IF NOT EXISTS seq BIGSERIAL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS scrape_batch_seq_idx ON scrape_batch(seq);
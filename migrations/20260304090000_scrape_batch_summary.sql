@@ -0,0 +1,3 @@
+-- Scrape batch diff summary, sent along the batch on /events
+
+ALTER TABLE scrape_batch ADD COLUMN IF NOT EXISTS summary JSONB;
@@ -0,0 +1,31 @@
+-- Outbound webhooks notified on each new scrape batch
+
+CREATE TABLE IF NOT EXISTS webhooks(
+		webhook_id UUID PRIMARY KEY,
+		name VARCHAR(200) NOT NULL,
+		url TEXT NOT NULL,
+		secret VARCHAR(100) NOT NULL,
+		regions TEXT[] NOT NULL DEFAULT '{}',
+		entities TEXT[] NOT NULL DEFAULT '{}',
+		author UUID NOT NULL REFERENCES admins(admin_id),
+		created_at TIMESTAMP DEFAULT NOW(),
+		active BOOLEAN NOT NULL DEFAULT TRUE,
+		consecutive_failures INT NOT NULL DEFAULT 0,
+		disabled_at TIMESTAMP,
+		disabled_reason TEXT
+);
+
+CREATE TABLE IF NOT EXISTS webhook_deliveries(
+		delivery_id UUID NOT NULL,
+		webhook_id UUID NOT NULL REFERENCES webhooks(webhook_id) ON DELETE CASCADE,
+		batch_id UUID NOT NULL,
+		attempt INT NOT NULL,
+		status_code INT,
+		success BOOLEAN NOT NULL,
+		error TEXT,
+		duration_ms BIGINT NOT NULL,
+		delivered_at TIMESTAMP DEFAULT NOW(),
+		PRIMARY KEY (delivery_id, attempt)
+);
+
+CREATE INDEX IF NOT EXISTS webhook_deliveries_webhook_idx ON webhook_deliveries(webhook_id, delivered_at DESC);
@@ -0,0 +1,12 @@
+-- Notifications a handler couldn't parse or process, kept for inspection
+
+CREATE TABLE IF NOT EXISTS event_dead_letters(
+		dead_letter_id UUID PRIMARY KEY,
+		channel VARCHAR(100) NOT NULL,
+		handler VARCHAR(100) NOT NULL,
+		payload TEXT NOT NULL,
+		error TEXT NOT NULL,
+		created_at TIMESTAMP DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS event_dead_letters_created_idx ON event_dead_letters(created_at DESC);
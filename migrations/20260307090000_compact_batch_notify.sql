@@ -0,0 +1,24 @@
+-- Notify on commit with the batch identifiers only, NOTIFY payloads are capped at 8000 bytes
+
+DROP TRIGGER IF EXISTS notify_scraped_trigger ON scrape_batch;
+
+CREATE OR REPLACE FUNCTION notify_scraped() RETURNS TRIGGER AS $$
+BEGIN
+		PERFORM pg_notify(
+				'scraping_channel',
+				json_build_object(
+						'operation', TG_OP,
+						'table', TG_TABLE_NAME,
+						'data', json_build_object('batch_id', NEW.batch_id, 'seq', NEW.seq),
+						'entity', NEW.entity
+				)::text
+		);
+		RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- deferred until the commit, once the restaurants, meals or schools of the batch are written
+CREATE CONSTRAINT TRIGGER notify_scraped_trigger
+		AFTER INSERT OR UPDATE ON scrape_batch
+		DEFERRABLE INITIALLY DEFERRED
+		FOR EACH ROW EXECUTE PROCEDURE notify_scraped();
@@ -0,0 +1,13 @@
+-- Scraper health reports pushed by `crousctl doctor`, one per check of a region
+
+CREATE TABLE IF NOT EXISTS region_health(
+		health_id UUID PRIMARY KEY,
+		region TEXT NOT NULL,
+		status TEXT NOT NULL,
+		pages_checked INT NOT NULL,
+		report JSONB NOT NULL,
+		author UUID NOT NULL REFERENCES admins(admin_id),
+		checked_at TIMESTAMP DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS region_health_region_idx ON region_health(region, checked_at DESC);
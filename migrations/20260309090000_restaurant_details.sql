@@ -0,0 +1,10 @@
+-- Practical info scraped from the restaurant pages
+
+ALTER TABLE restaurants
+		ADD COLUMN IF NOT EXISTS address TEXT,
+		ADD COLUMN IF NOT EXISTS phone VARCHAR(100),
+		ADD COLUMN IF NOT EXISTS payment_methods TEXT[] NOT NULL DEFAULT '{}',
+		ADD COLUMN IF NOT EXISTS amenities TEXT[] NOT NULL DEFAULT '{}',
+		ADD COLUMN IF NOT EXISTS accessible BOOLEAN,
+		ADD COLUMN IF NOT EXISTS access TEXT,
+		ADD COLUMN IF NOT EXISTS photos TEXT[] NOT NULL DEFAULT '{}';
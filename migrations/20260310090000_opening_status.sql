@@ -0,0 +1,7 @@
+-- Closures and notices read from the pages, on restaurants and on menu days
+
+ALTER TABLE restaurants
+		ADD COLUMN IF NOT EXISTS status JSONB;
+
+ALTER TABLE meals
+		ADD COLUMN IF NOT EXISTS status JSONB;
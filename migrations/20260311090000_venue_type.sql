@@ -0,0 +1,4 @@
+-- Venue type of the restaurants, resto_u, cafeteria, brasserie, food_truck or unknown
+
+ALTER TABLE restaurants
+		ADD COLUMN IF NOT EXISTS venue_type TEXT NOT NULL DEFAULT 'unknown';
@@ -0,0 +1,14 @@
+-- Batch sequences handed out from a counter row locked until the batch commits, so they follow commit order
+
+CREATE TABLE IF NOT EXISTS scrape_batch_counter (
+		id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+		seq BIGINT NOT NULL
+);
+
+INSERT INTO scrape_batch_counter (seq)
+		SELECT COALESCE(MAX(seq), 0) FROM scrape_batch
+		ON CONFLICT (id) DO NOTHING;
+
+ALTER TABLE scrape_batch ALTER COLUMN seq DROP DEFAULT;
+
+DROP SEQUENCE IF EXISTS scrape_batch_seq_seq;
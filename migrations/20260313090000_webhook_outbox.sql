@@ -0,0 +1,12 @@
+-- Webhook deliveries waiting for their next attempt, kept in the database so retries survive a restart
+
+CREATE TABLE IF NOT EXISTS webhook_outbox(
+		delivery_id UUID PRIMARY KEY,
+		webhook_id UUID NOT NULL REFERENCES webhooks(webhook_id) ON DELETE CASCADE,
+		batch JSONB NOT NULL,
+		attempt INT NOT NULL DEFAULT 1,
+		next_attempt_at TIMESTAMP NOT NULL DEFAULT NOW(),
+		created_at TIMESTAMP DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS webhook_outbox_due_idx ON webhook_outbox(next_attempt_at);